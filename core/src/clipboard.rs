@@ -0,0 +1,531 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    common::generate_id,
+    errors::{GraphError, GraphResult},
+    events::EventSystem,
+    graph::{FieldInstance, Graph, NodeInstance, NodeTemplate, NodeTemplateInfo, SlotInstance},
+    validation::PatternCache,
+};
+
+/// Nodes copied out of a graph, keeping their field values and only the connections that
+/// run between them. Serializable so it can be handed to another canvas.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ClipboardPayload {
+    pub nodes: Vec<NodeInstance>,
+    // The templates of the copied nodes, so a graph with other ids can match them by name
+    #[serde(default)]
+    pub templates: Vec<NodeTemplate>,
+}
+
+impl ClipboardPayload {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn to_json(&self) -> GraphResult<String> {
+        serde_json::to_string(self).map_err(|err| GraphError::DocumentParseFailed(err.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> GraphResult<Self> {
+        serde_json::from_str(json).map_err(|err| GraphError::DocumentParseFailed(err.to_string()))
+    }
+
+    /// Copies of the nodes with fresh ids, moved by the offset, with the connections
+    /// between them pointing at the new ids.
+    pub fn instantiate(&self, offset_x: f64, offset_y: f64) -> Vec<NodeInstance> {
+        let new_ids = self
+            .nodes
+            .iter()
+            .map(|node| (node.instance_id.clone(), generate_id()))
+            .collect::<HashMap<_, _>>();
+        self.nodes
+            .iter()
+            .map(|node| {
+                let mut node = node.clone();
+                node.instance_id = new_ids[&node.instance_id].clone();
+                node.x += offset_x;
+                node.y += offset_y;
+                for slot in &mut node.slots {
+                    slot.node_instance_id = node.instance_id.clone();
+                    slot.connections
+                        .retain(|conn| new_ids.contains_key(&conn.target_node_id));
+                    for conn in &mut slot.connections {
+                        conn.host_node_id = node.instance_id.clone();
+                        conn.target_node_id = new_ids[&conn.target_node_id].clone();
+                    }
+                }
+                node
+            })
+            .collect()
+    }
+}
+
+impl Graph {
+    /// Copies the given nodes. Connections to nodes outside the set are left behind.
+    pub fn copy_nodes(&self, node_ids: &[String]) -> GraphResult<ClipboardPayload> {
+        let copied = node_ids.iter().collect::<HashSet<_>>();
+        let mut nodes = vec![];
+        let mut templates: Vec<NodeTemplate> = vec![];
+        for node_id in node_ids {
+            let mut node = self
+                .node_instances
+                .get(node_id)
+                .ok_or(GraphError::NodeNotFound(node_id.clone()))?
+                .clone();
+            for slot in &mut node.slots {
+                slot.connections
+                    .retain(|conn| copied.contains(&conn.target_node_id));
+            }
+            let template = self
+                .node_templates
+                .get(&node.template_id)
+                .ok_or_else(|| GraphError::TemplateNotFound(node.template_id.clone()))?;
+            if !templates
+                .iter()
+                .any(|copied| copied.template_id == template.template_id)
+            {
+                templates.push(template.clone());
+            }
+            nodes.push(node);
+        }
+        Ok(ClipboardPayload { nodes, templates })
+    }
+
+    /// Points the payload's template, slot and field ids at this graph's templates. Ids the
+    /// graph knows are kept; the others are matched by name through the templates the
+    /// payload was copied with, so nodes copied on one canvas can be pasted into another
+    /// whose templates were registered with different ids. Anything left unmatched is
+    /// rejected by `paste_nodes`.
+    pub fn resolve_clipboard(&self, payload: &ClipboardPayload) -> ClipboardPayload {
+        // The template each copied node was made from, and the one it maps to here
+        let mut templates: HashMap<&str, (Option<&NodeTemplate>, &NodeTemplate)> = HashMap::new();
+        for node in &payload.nodes {
+            let source = payload
+                .templates
+                .iter()
+                .find(|template| template.template_id == node.template_id);
+            let target = self.node_templates.get(&node.template_id).or_else(|| {
+                let name = &source?.name;
+                self.node_templates
+                    .values()
+                    .find(|template| &template.name == name)
+            });
+            if let Some(target) = target {
+                templates.insert(&node.instance_id, (source, target));
+            }
+        }
+        let slot_id = |node_id: &str, slot_template_id: &mut String| {
+            if let Some((source, target)) = templates.get(node_id) {
+                if let Some(slot) = resolve_slot(*source, target, slot_template_id) {
+                    *slot_template_id = slot;
+                }
+            }
+        };
+
+        let mut resolved = payload.clone();
+        for node in &mut resolved.nodes {
+            let Some((source, target)) = templates.get(node.instance_id.as_str()) else {
+                continue;
+            };
+            node.template_id = target.template_id.clone();
+            for slot in &mut node.slots {
+                slot.node_template_id = target.template_id.clone();
+                slot_id(&node.instance_id, &mut slot.slot_template_id);
+                for conn in &mut slot.connections {
+                    slot_id(&conn.host_node_id, &mut conn.host_slot_template_id);
+                    slot_id(&conn.target_node_id, &mut conn.target_slot_template_id);
+                }
+            }
+            for field in &mut node.fields {
+                if target
+                    .field_templates
+                    .iter()
+                    .any(|field_template| field_template.id == field.field_template_id)
+                {
+                    continue;
+                }
+                let name = source.and_then(|source| {
+                    source
+                        .field_templates
+                        .iter()
+                        .find(|field_template| field_template.id == field.field_template_id)
+                        .map(|field_template| &field_template.name)
+                });
+                if let Some(field_template) = target
+                    .field_templates
+                    .iter()
+                    .find(|field_template| Some(&field_template.name) == name)
+                {
+                    field.field_template_id = field_template.id.clone();
+                }
+            }
+        }
+        resolved
+    }
+
+    /// Adds nodes produced by `ClipboardPayload::instantiate`. Each template must allow
+    /// creation and have room for the new instances, every slot and field must exist on the
+    /// node's template, field values must pass the same checks as `update_field`, and every
+    /// connection must pass the usual connection rules. Slots and fields the copy lacks are
+    /// added as they would be on a new node. Nothing is added if any check fails.
+    pub fn paste_nodes(
+        &mut self,
+        nodes: Vec<NodeInstance>,
+        events: &EventSystem,
+    ) -> GraphResult<()> {
+        let snapshot = self.node_instances.clone();
+        let (inner_events, held) = EventSystem::holding();
+        let result = self.insert_pasted_nodes(nodes, &inner_events);
+        match result {
+            Ok(()) => held.release(events),
+            Err(_) => self.node_instances = snapshot,
+        }
+        result
+    }
+
+    fn insert_pasted_nodes(
+        &mut self,
+        nodes: Vec<NodeInstance>,
+        events: &EventSystem,
+    ) -> GraphResult<()> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for node in &nodes {
+            *counts.entry(&node.template_id).or_default() += 1;
+        }
+        for (template_id, count) in counts {
+            let creation_failed = |name: &str, reason: GraphError| GraphError::NodeCreationFailed {
+                node_template_id: template_id.to_string(),
+                node_template_name: name.to_string(),
+                reason: Box::new(reason),
+            };
+            let template = self.node_templates.get(template_id).ok_or(creation_failed(
+                "?",
+                GraphError::TemplateNotFound(template_id.to_string()),
+            ))?;
+            if !template.can_create {
+                return Err(creation_failed(
+                    &template.name,
+                    GraphError::Other("Template cannot be created".to_string()),
+                ));
+            }
+            let existing = self.instances_of_node_template(template_id).len();
+            if template
+                .max_instances
+                .is_some_and(|max| existing + count > max)
+            {
+                return Err(creation_failed(
+                    &template.name,
+                    GraphError::Other("Maximum instances reached".to_string()),
+                ));
+            }
+        }
+
+        // Insert the nodes bare, then connect them so each connection is checked
+        let mut connections = vec![];
+        let mut pasted = vec![];
+        for mut node in nodes {
+            if self.node_instances.contains_key(&node.instance_id) {
+                return Err(GraphError::Other(format!(
+                    "Node {} already exists",
+                    node.instance_id
+                )));
+            }
+            let template = &self.node_templates[&node.template_id];
+            conform_to_template(&mut node, template, &self.patterns)?;
+            for slot in &mut node.slots {
+                connections.append(&mut slot.connections);
+            }
+            pasted.push(node.instance_id.clone());
+            self.node_instances.insert(node.instance_id.clone(), node);
+        }
+        // Once every node is in, so pasted nodes are also checked against each other
+        for node_id in &pasted {
+            let node = &self.node_instances[node_id];
+            let template = &self.node_templates[&node.template_id];
+            for field in &node.fields {
+                if let Some(field_template) = template
+                    .field_templates
+                    .iter()
+                    .find(|field_template| field_template.id == field.field_template_id)
+                {
+                    self.check_field_unique(node, field_template, &field.value)?;
+                }
+            }
+        }
+        for connection in connections {
+            if self
+                .get_slot_capabilities(&connection.host_node_id, &connection.host_slot_template_id)
+                .is_none()
+            {
+                return Err(GraphError::SlotNotFound {
+                    node_id: connection.host_node_id.clone(),
+                    slot_id: connection.host_slot_template_id.clone(),
+                });
+            }
+            self.connect_slots(connection, events)?;
+        }
+        Ok(())
+    }
+}
+
+// The id to use instead of `slot_template_id` when `target` has no slot with that id: the
+// slot named like the one it refers to on `source`
+fn resolve_slot(
+    source: Option<&NodeTemplate>,
+    target: &NodeTemplate,
+    slot_template_id: &str,
+) -> Option<String> {
+    if target.get_slot_template(slot_template_id).is_some() {
+        return None;
+    }
+    let name = &source?.get_slot_template(slot_template_id)?.name;
+    target
+        .get_slot_template_by_name(name)
+        .map(|slot| slot.id.clone())
+}
+
+// Checks a pasted node's slots and field values against its template and adds whatever
+// the template has that the copy does not
+fn conform_to_template(
+    node: &mut NodeInstance,
+    template: &NodeTemplate,
+    patterns: &PatternCache,
+) -> GraphResult<()> {
+    for slot in &node.slots {
+        if template.get_slot_template(&slot.slot_template_id).is_none() {
+            return Err(GraphError::SlotNotFound {
+                node_id: node.instance_id.clone(),
+                slot_id: slot.slot_template_id.clone(),
+            });
+        }
+    }
+    for field in &node.fields {
+        let field_template = template
+            .field_templates
+            .iter()
+            .find(|field_template| field_template.id == field.field_template_id)
+            .ok_or(GraphError::Other("Field template not found".to_string()))?;
+        field_template.check_value_with(&field.value, patterns)?;
+    }
+
+    for slot_template in &template.slot_templates {
+        if !node
+            .slots
+            .iter()
+            .any(|slot| slot.slot_template_id == slot_template.id)
+        {
+            node.slots.push(SlotInstance {
+                node_instance_id: node.instance_id.clone(),
+                node_template_id: template.template_id.clone(),
+                slot_template_id: slot_template.id.clone(),
+                connections: Vec::new(),
+                can_modify: true,
+            });
+        }
+    }
+    for field_template in &template.field_templates {
+        if !node
+            .fields
+            .iter()
+            .any(|field| field.field_template_id == field_template.id)
+        {
+            node.fields.push(FieldInstance {
+                node_instance_id: node.instance_id.clone(),
+                field_template_id: field_template.id.clone(),
+                value: field_template.default_value.clone(),
+                can_modify: true,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{FieldConstraints, FieldTemplate, FieldType, GraphCommand},
+        test_support::{add_node, child_template, connect, parent_template, CHILD, PARENT},
+    };
+
+    const LABEL: &str = "label";
+
+    // Children have a short label that no two children may share
+    fn labelled_graph() -> Graph {
+        let mut label =
+            FieldTemplate::new("Label", FieldType::String, "").with_constraints(FieldConstraints {
+                max_length: Some(5),
+                unique: true,
+                ..FieldConstraints::default()
+            });
+        label.id = LABEL.to_string();
+        let mut child = child_template();
+        child.field_templates.push(label);
+        let mut graph = Graph::new();
+        graph.register_template(parent_template());
+        graph.register_template(child);
+        graph
+    }
+
+    // The same templates with every id regenerated, as another canvas registers them
+    fn other_canvas() -> Graph {
+        let mut graph = Graph::new();
+        for mut template in labelled_graph().node_templates.into_values() {
+            template.template_id = generate_id();
+            for slot in &mut template.slot_templates {
+                slot.id = generate_id();
+            }
+            for field in &mut template.field_templates {
+                field.id = generate_id();
+            }
+            graph.register_template(template);
+        }
+        graph
+    }
+
+    // A parent connected to a child labelled "one", both copied
+    fn copy_family(graph: &mut Graph) -> ClipboardPayload {
+        add_node(graph, PARENT, "p");
+        add_node(graph, CHILD, "c");
+        connect(graph, "p", "c");
+        graph
+            .execute_command(
+                GraphCommand::UpdateField {
+                    node_id: "c".to_string(),
+                    field_template_id: LABEL.to_string(),
+                    new_value: "one".to_string(),
+                },
+                &EventSystem::new(),
+            )
+            .unwrap();
+        graph
+            .copy_nodes(&["p".to_string(), "c".to_string()])
+            .unwrap()
+    }
+
+    fn paste(graph: &mut Graph, payload: &ClipboardPayload) -> GraphResult<()> {
+        let nodes = graph.resolve_clipboard(payload).instantiate(10.0, 10.0);
+        graph.execute_command(GraphCommand::PasteNodes(nodes), &EventSystem::new())
+    }
+
+    #[test]
+    fn instantiate_points_connections_at_the_new_ids() {
+        let payload = copy_family(&mut labelled_graph());
+        let nodes = payload.instantiate(10.0, 20.0);
+
+        let ids = nodes
+            .iter()
+            .map(|node| &node.instance_id)
+            .collect::<Vec<_>>();
+        assert!(!ids.contains(&&"p".to_string()) && !ids.contains(&&"c".to_string()));
+        assert_eq!((nodes[0].x, nodes[0].y), (10.0, 20.0));
+        let connection = &nodes[0].slots[0].connections[0];
+        assert_eq!(&connection.host_node_id, ids[0]);
+        assert_eq!(&connection.target_node_id, ids[1]);
+    }
+
+    #[test]
+    fn copying_leaves_connections_to_other_nodes_behind() {
+        let mut graph = labelled_graph();
+        copy_family(&mut graph);
+
+        let payload = graph.copy_nodes(&["p".to_string()]).unwrap();
+
+        assert!(payload.nodes[0]
+            .slots
+            .iter()
+            .all(|slot| slot.connections.is_empty()));
+        assert_eq!(payload.templates.len(), 1);
+    }
+
+    #[test]
+    fn pasting_into_another_canvas_matches_templates_by_name() {
+        let payload = copy_family(&mut labelled_graph());
+        let payload = ClipboardPayload::from_json(&payload.to_json().unwrap()).unwrap();
+        let mut graph = other_canvas();
+
+        paste(&mut graph, &payload).unwrap();
+
+        let child_template = graph.get_node_template_by_name("Child").unwrap();
+        let child = graph
+            .node_instances
+            .values()
+            .find(|node| node.template_id == child_template.template_id)
+            .unwrap();
+        assert_eq!(
+            child.fields[0].field_template_id,
+            child_template.field_templates[0].id
+        );
+        assert_eq!(child.fields[0].value, "one");
+        let parent = graph
+            .node_instances
+            .values()
+            .find(|node| node.instance_id != child.instance_id)
+            .unwrap();
+        let connections = graph.get_node_connections(&parent.instance_id);
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].target_node_id, child.instance_id);
+        assert_eq!(
+            connections[0].target_slot_template_id,
+            child_template.slot_templates[0].id
+        );
+    }
+
+    #[test]
+    fn pasting_an_unknown_template_adds_nothing() {
+        let mut payload = copy_family(&mut labelled_graph());
+        payload.templates.clear();
+        let mut graph = other_canvas();
+
+        let result = paste(&mut graph, &payload);
+
+        assert!(matches!(result, Err(GraphError::NodeCreationFailed { .. })));
+        assert!(graph.node_instances.is_empty());
+    }
+
+    #[test]
+    fn pasting_a_slot_the_template_lacks_fails() {
+        let mut graph = labelled_graph();
+        let mut payload = copy_family(&mut graph);
+        payload.nodes[1].slots[0].slot_template_id = "nope".to_string();
+
+        let result = paste(&mut graph, &payload);
+
+        assert!(
+            matches!(result, Err(GraphError::SlotNotFound { slot_id, .. }) if slot_id == "nope")
+        );
+        assert_eq!(graph.node_instances.len(), 2);
+    }
+
+    #[test]
+    fn pasted_field_values_must_meet_their_constraints() {
+        let mut graph = other_canvas();
+        let mut payload = copy_family(&mut labelled_graph());
+        payload.nodes[1].fields[0].value = "too long".to_string();
+
+        let result = paste(&mut graph, &payload);
+
+        assert!(matches!(
+            result,
+            Err(GraphError::FieldLengthOutOfRange { actual: 8, .. })
+        ));
+        assert!(graph.node_instances.is_empty());
+    }
+
+    #[test]
+    fn pasting_a_taken_unique_value_fails() {
+        let mut graph = labelled_graph();
+        let payload = copy_family(&mut graph);
+
+        let result = paste(&mut graph, &payload);
+
+        assert!(matches!(
+            result,
+            Err(GraphError::DuplicateFieldValue { node_id, .. }) if node_id == "c"
+        ));
+        assert_eq!(graph.node_instances.len(), 2);
+    }
+}
@@ -1,6 +1,6 @@
 use derivative::Derivative;
 
-use crate::graph::NodeTemplate;
+use crate::{graph::NodeTemplate, keymap::Keymap};
 use std::collections::HashSet;
 
 #[derive(Clone, Debug)]
@@ -31,11 +31,15 @@ pub struct GraphCanvasConfig {
     pub default_node_height: f64,
     pub connection_control_point_distance: f64,
     pub slot_radius: f64,
+    // Screen pixels around a slot that count as touching it; fingers are less precise
+    pub touch_hit_radius: f64,
 
     // Templates and initial state
     pub node_templates: Vec<NodeTemplate>,
     pub template_groups: Vec<TemplateGroup>,
     pub initial_nodes: Vec<InitialNode>,
+    // The molecule_schema constraint schema the templates were imported from, kept for exporting
+    pub molecule_schema: Option<String>,
 
     // Behavioral settings
     pub show_default_toolbar: bool,
@@ -43,6 +47,9 @@ pub struct GraphCanvasConfig {
     pub grid_size: f64,
     pub is_mutable: bool,
     pub is_movable: bool,
+    pub keymap: Keymap,
+    // Prefix for the DOM ids of this canvas and its toolbar; numbered automatically when None
+    pub dom_id_prefix: Option<String>,
     // pub custom_toolbar: Option<HtmlElement>,
 }
 impl GraphCanvasConfig {
@@ -53,14 +60,18 @@ impl GraphCanvasConfig {
             default_node_height: 100.0,
             connection_control_point_distance: 75.0,
             slot_radius: 12.0,
+            touch_hit_radius: 24.0,
             node_templates: Vec::new(),
             template_groups: Vec::new(),
             initial_nodes: Vec::new(),
+            molecule_schema: None,
             show_default_toolbar: true,
             snap_to_grid: false,
             grid_size: 20.0,
             is_mutable: true,
             is_movable: true,
+            keymap: Keymap::default(),
+            dom_id_prefix: None,
         }
     }
 
@@ -93,10 +104,11 @@ impl GraphCanvasConfig {
 
         // First, populate all explicitly defined groups
         for group in &self.template_groups {
+            // Groups may list templates by id or by name
             let templates = group.templates.iter().map(|template_name| {
                 self.node_templates
                     .iter()
-                    .find(|t| t.name == *template_name)
+                    .find(|t| t.template_id == *template_name || t.name == *template_name)
                     .expect(&format!(
                         "Configuration incorrect. Template not found: {}. Template group which holds unfound template: {}",
                         template_name, group.name
@@ -190,6 +202,8 @@ impl InitialNode {
 pub struct InitialConnection {
     pub host_slot_name: String,
     pub target_instance_id: String,
+    // Name of the incoming slot on the target; when absent the first accepting one is used
+    pub target_slot_name: Option<String>,
     pub can_delete: bool,
 }
 
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use crate::{
+    errors::{GraphError, GraphResult},
+    graph::{Graph, NodeInstance, NodeTemplate, NodeTemplateInfo, SlotType},
+};
+
+/// Bump this whenever the document layout changes, and teach `GraphDocument::migrate`
+/// how to bring older documents forward.
+pub const CURRENT_DOCUMENT_VERSION: u32 = 1;
+
+/// The persisted form of a `Graph`: templates, instances (with their positions, lock flags,
+/// slot connections and field values). Command history is not persisted.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct GraphDocument {
+    pub version: u32,
+    pub node_templates: Vec<NodeTemplate>,
+    pub node_instances: Vec<NodeInstance>,
+}
+
+impl GraphDocument {
+    pub fn to_json(&self) -> GraphResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| GraphError::DocumentParseFailed(err.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> GraphResult<Self> {
+        // Read the version first so a newer document fails with a clear error instead of a
+        // confusing missing-field message.
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|err| GraphError::DocumentParseFailed(err.to_string()))?;
+        let version =
+            value
+                .get("version")
+                .and_then(|v| v.as_u64())
+                .ok_or(GraphError::DocumentParseFailed(
+                    "Document has no version".to_string(),
+                ))? as u32;
+        if version > CURRENT_DOCUMENT_VERSION {
+            return Err(GraphError::UnsupportedDocumentVersion {
+                found: version,
+                supported: CURRENT_DOCUMENT_VERSION,
+            });
+        }
+        let document: GraphDocument = serde_json::from_value(value)
+            .map_err(|err| GraphError::DocumentParseFailed(err.to_string()))?;
+        Ok(document.migrate())
+    }
+
+    // No older versions exist yet
+    fn migrate(self) -> Self {
+        self
+    }
+}
+
+impl Graph {
+    pub fn to_document(&self) -> GraphDocument {
+        // Sort so that saving the same graph twice produces the same document
+        let mut node_templates = self.node_templates.values().cloned().collect::<Vec<_>>();
+        node_templates.sort_by(|a, b| a.template_id.cmp(&b.template_id));
+        let mut node_instances = self.node_instances.values().cloned().collect::<Vec<_>>();
+        node_instances.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+
+        GraphDocument {
+            version: CURRENT_DOCUMENT_VERSION,
+            node_templates,
+            node_instances,
+        }
+    }
+
+    pub fn from_document(document: GraphDocument) -> GraphResult<Graph> {
+        if document.version > CURRENT_DOCUMENT_VERSION {
+            return Err(GraphError::UnsupportedDocumentVersion {
+                found: document.version,
+                supported: CURRENT_DOCUMENT_VERSION,
+            });
+        }
+        let document = document.migrate();
+
+        let node_templates = document
+            .node_templates
+            .into_iter()
+            .map(|template| (template.template_id.clone(), template))
+            .collect::<HashMap<_, _>>();
+        let node_instances = document
+            .node_instances
+            .into_iter()
+            .map(|instance| (instance.instance_id.clone(), instance))
+            .collect::<HashMap<_, _>>();
+
+        // Templates are stored as-is, so they must not go through `register_template` again
+        let mut graph = Graph::new();
+        graph.node_templates = node_templates;
+        graph.node_instances = node_instances;
+        graph.check_document_references()?;
+        Ok(graph)
+    }
+
+    // Every instance, slot and connection in a loaded document has to point at something
+    // that exists, otherwise the rest of the code will panic on lookup.
+    fn check_document_references(&self) -> GraphResult<()> {
+        let mut errors = vec![];
+        for instance in self.node_instances.values() {
+            let Some(template) = self.node_templates.get(&instance.template_id) else {
+                errors.push(GraphError::InvalidDocument(format!(
+                    "Node {} uses unknown template {}",
+                    instance.instance_id, instance.template_id
+                )));
+                continue;
+            };
+            for slot_template in &template.slot_templates {
+                if !instance
+                    .slots
+                    .iter()
+                    .any(|slot| slot.slot_template_id == slot_template.id)
+                {
+                    errors.push(GraphError::InvalidDocument(format!(
+                        "Node {} is missing slot {} of template {}",
+                        instance.instance_id, slot_template.id, template.name
+                    )));
+                }
+            }
+            for slot in &instance.slots {
+                if !template
+                    .slot_templates
+                    .iter()
+                    .any(|st| st.id == slot.slot_template_id)
+                {
+                    errors.push(GraphError::InvalidDocument(format!(
+                        "Node {} has slot {} which is not in template {}",
+                        instance.instance_id, slot.slot_template_id, template.name
+                    )));
+                }
+                for connection in &slot.connections {
+                    // Connections are stored on the slot they leave from
+                    if connection.host_node_id != instance.instance_id
+                        || connection.host_slot_template_id != slot.slot_template_id
+                    {
+                        errors.push(GraphError::InvalidDocument(format!(
+                            "Slot {}:{} holds a connection from {}:{}",
+                            instance.instance_id,
+                            slot.slot_template_id,
+                            connection.host_node_id,
+                            connection.host_slot_template_id
+                        )));
+                    }
+                    let target_slot_type = self
+                        .node_instances
+                        .get(&connection.target_node_id)
+                        .filter(|target| {
+                            target
+                                .slots
+                                .iter()
+                                .any(|s| s.slot_template_id == connection.target_slot_template_id)
+                        })
+                        .and_then(|target| self.node_templates.get(&target.template_id))
+                        .and_then(|target_template| {
+                            target_template.get_slot_template(&connection.target_slot_template_id)
+                        })
+                        .map(|target_slot| &target_slot.slot_type);
+                    let problem = match target_slot_type {
+                        None => "targets missing slot",
+                        Some(SlotType::Outgoing) => "targets outgoing slot",
+                        Some(SlotType::Incoming) => continue,
+                    };
+                    errors.push(GraphError::InvalidDocument(format!(
+                        "Connection from {}:{} {} {}:{}",
+                        connection.host_node_id,
+                        connection.host_slot_template_id,
+                        problem,
+                        connection.target_node_id,
+                        connection.target_slot_template_id
+                    )));
+                }
+            }
+            for field in &instance.fields {
+                if !template
+                    .field_templates
+                    .iter()
+                    .any(|ft| ft.id == field.field_template_id)
+                {
+                    errors.push(GraphError::InvalidDocument(format!(
+                        "Node {} has field {} which is not in template {}",
+                        instance.instance_id, field.field_template_id, template.name
+                    )));
+                }
+            }
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(GraphError::ListOfErrors(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graph::Connection, test_support::*};
+
+    fn saved_family() -> String {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, CHILD, "c");
+        connect(&mut graph, "p", "c");
+        graph.to_document().to_json().unwrap()
+    }
+
+    #[test]
+    fn a_saved_graph_loads_back_unchanged() {
+        let json = saved_family();
+
+        let graph = Graph::from_document(GraphDocument::from_json(&json).unwrap()).unwrap();
+
+        assert_eq!(graph.node_templates.len(), 2);
+        assert_eq!(graph.node_instances.len(), 2);
+        assert_eq!(
+            graph.get_node_connections("p"),
+            vec![child_connection("p", "c")]
+        );
+        assert_eq!(graph.to_document().to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn loading_does_not_add_the_default_incoming_slot_twice() {
+        let graph =
+            Graph::from_document(GraphDocument::from_json(&saved_family()).unwrap()).unwrap();
+
+        assert_eq!(graph.node_templates[CHILD].slot_templates.len(), 1);
+        assert!(!graph.history.can_undo());
+    }
+
+    #[test]
+    fn newer_documents_are_rejected() {
+        let json = saved_family().replacen(
+            &format!("\"version\": {}", CURRENT_DOCUMENT_VERSION),
+            &format!("\"version\": {}", CURRENT_DOCUMENT_VERSION + 1),
+            1,
+        );
+
+        let result = GraphDocument::from_json(&json);
+
+        assert!(matches!(
+            result,
+            Err(GraphError::UnsupportedDocumentVersion { found, .. })
+                if found == CURRENT_DOCUMENT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn documents_without_a_version_are_rejected() {
+        let result = GraphDocument::from_json(r#"{"node_templates": [], "node_instances": []}"#);
+        assert!(matches!(result, Err(GraphError::DocumentParseFailed(_))));
+    }
+
+    #[test]
+    fn dangling_references_are_reported_together() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        let mut document = graph.to_document();
+        // A connection to a node that is not saved, and a node of an unknown template
+        document.node_instances[0].slots[0]
+            .connections
+            .push(child_connection("p", "c"));
+        document.node_instances.push({
+            let mut orphan = document.node_instances[0].clone();
+            orphan.instance_id = "orphan".to_string();
+            orphan.template_id = "missing".to_string();
+            orphan.slots.clear();
+            orphan
+        });
+
+        let result = Graph::from_document(document);
+
+        let Err(GraphError::ListOfErrors(errors)) = result else {
+            panic!("expected ListOfErrors");
+        };
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn connections_and_slots_must_match_their_nodes() {
+        let mut document = GraphDocument::from_json(&saved_family()).unwrap();
+        let parent = document
+            .node_instances
+            .iter_mut()
+            .find(|node| node.instance_id == "p")
+            .unwrap();
+        // A connection claiming to leave from another node, and one into an outgoing slot
+        let connections = &mut parent.slots[0].connections;
+        connections[0].host_node_id = "c".to_string();
+        connections.push(Connection {
+            target_node_id: "p".to_string(),
+            target_slot_template_id: CHILDREN_SLOT.to_string(),
+            ..child_connection("p", "c")
+        });
+        // A node without the incoming slot its template declares
+        let mut slotless = document.node_instances[0].clone();
+        slotless.instance_id = "slotless".to_string();
+        slotless.template_id = CHILD.to_string();
+        slotless.slots.clear();
+        slotless.fields.clear();
+        document.node_instances.push(slotless);
+
+        let Err(GraphError::ListOfErrors(errors)) = Graph::from_document(document) else {
+            panic!("expected ListOfErrors");
+        };
+        let messages = errors.iter().map(|err| err.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        for expected in [
+            "holds a connection from c:",
+            "targets outgoing slot",
+            "missing slot",
+        ] {
+            assert!(messages.iter().any(|message| message.contains(expected)));
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use core::fmt;
+
+use crate::{graph::Connection, validation::ValidationReport};
+
+#[derive(Debug, Clone)]
+pub enum GraphError {
+    SaveFailed {
+        reason: Box<GraphError>,
+    },
+    SlotMalformed {
+        template_name: String,
+        slot_name: String,
+        min: usize,
+        max: Option<usize>,
+        actual: usize,
+    },
+    GraphLockFailed,
+    SetupFailed(String),
+    ConfigurationError(String, Box<GraphError>),
+    NodeNotFound(String),
+    SlotNotFound {
+        node_id: String,
+        slot_id: String,
+    },
+    TemplateNotFound(String),
+    ConnectionCreationFailed {
+        node_template_name: String,
+        slot_template_name: String,
+        reason: Box<GraphError>,
+    },
+    InvalidConnection {
+        connection: Connection,
+        reason: String,
+    },
+    ValidationFailed(ValidationReport),
+    LockFailed(String),
+    SomeSlotDeletionsFailed {
+        failures: Vec<GraphError>,
+    },
+    SomeConnectionDeletionsFailed {
+        failures: Vec<GraphError>,
+    },
+    NodeCreationFailed {
+        node_template_id: String,
+        node_template_name: String,
+        reason: Box<GraphError>,
+    },
+    NodeDeletionFailed {
+        node_id: String,
+        node_template_name: String,
+        reason: Box<GraphError>,
+    },
+    SlotDeletionFailed {
+        slot_name: String,
+        reason: Box<GraphError>,
+    },
+    ConnectionDeletionFailed {
+        connection: Connection,
+        reason: Box<GraphError>,
+    },
+    ConnectionLocked,
+    SlotTemplateLocked {
+        name: String,
+    },
+    SlotInstanceLocked,
+    NodeTemplateLocked {
+        name: String,
+    },
+    NodeInstanceLocked,
+    DocumentParseFailed(String),
+    UnsupportedDocumentVersion {
+        found: u32,
+        supported: u32,
+    },
+    InvalidDocument(String),
+    SchemaParseFailed(String),
+    // The schema refers to things it does not define
+    InvalidSchema(String),
+    // Part of an imported schema that has no equivalent on the canvas
+    UnsupportedSchemaConstruct {
+        item: String,
+        reason: String,
+    },
+    // The value cannot be stored in a field of this type
+    InvalidFieldValue {
+        field_name: String,
+        value: String,
+        reason: String,
+    },
+    // The field type cannot be built from what the host gave
+    InvalidFieldType {
+        field_type: String,
+        reason: String,
+    },
+    FieldRequired {
+        field_name: String,
+    },
+    FieldPatternMismatch {
+        field_name: String,
+        pattern: String,
+    },
+    // The template's own pattern does not compile
+    InvalidFieldPattern {
+        field_name: String,
+        pattern: String,
+        reason: String,
+    },
+    FieldLengthOutOfRange {
+        field_name: String,
+        min: Option<usize>,
+        max: Option<usize>,
+        actual: usize,
+    },
+    FieldValueOutOfRange {
+        field_name: String,
+        min: Option<f64>,
+        max: Option<f64>,
+        value: f64,
+    },
+    DuplicateFieldValue {
+        field_name: String,
+        value: String,
+        // The node that already has this value
+        node_id: String,
+    },
+    // commit() was called without begin_session()
+    NoEditingSession,
+    // The sync adapter refused a change and the graph was rolled back
+    SyncRejected {
+        reason: Box<GraphError>,
+    },
+    ListOfErrors(Vec<GraphError>),
+    Other(String),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::ConfigurationError(msg, inner_error) => {
+                write!(f, "Configuration error: {}; {}", msg, inner_error)
+            }
+            GraphError::SetupFailed(err) => write!(f, "Setup failed: {}", err),
+            GraphError::NodeNotFound(id) => write!(f, "Node not found: {}", id),
+            GraphError::SlotNotFound { node_id, slot_id } => {
+                write!(f, "Slot {} not found on node {}", slot_id, node_id)
+            }
+            GraphError::TemplateNotFound(id) => write!(f, "Template not found: {}", id),
+            GraphError::ConnectionCreationFailed {
+                node_template_name,
+                slot_template_name,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "Connection Creation Failed. From node {}, slot {}; Reason: {:#?}",
+                    node_template_name, slot_template_name, reason
+                )
+            }
+            GraphError::InvalidConnection { connection, reason } => {
+                let Connection {
+                    host_node_id,
+                    host_slot_template_id: host_slot_id,
+                    target_node_id,
+                    target_slot_template_id: target_slot_id,
+                    ..
+                } = connection;
+                write!(
+                    f,
+                    "Invalid connection from {}:{} to {}:{} - {}",
+                    host_node_id, host_slot_id, target_node_id, target_slot_id, reason
+                )
+            }
+            GraphError::ValidationFailed(report) => {
+                write!(f, "Validation failed:")?;
+                for issue in report.errors() {
+                    write!(f, "\n - {}", issue.message)?;
+                }
+                Ok(())
+            }
+            GraphError::LockFailed(msg) => write!(f, "Lock acquisition failed: {}", msg),
+            GraphError::NodeCreationFailed {
+                node_template_id,
+                node_template_name,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "Node creation failed: {}\n Template name: {}\n Reason: {:#?}",
+                    node_template_id, node_template_name, reason
+                )
+            }
+            GraphError::NodeDeletionFailed {
+                node_id,
+                node_template_name,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "Node deletion failed: {}\n Template name: {}\n Reason: {:#?}",
+                    node_id, node_template_name, reason
+                )
+            }
+            GraphError::SlotDeletionFailed { slot_name, reason } => {
+                write!(
+                    f,
+                    "Slot deletion failed: {}\n Reason: {:#?}",
+                    slot_name, reason
+                )
+            }
+            GraphError::ConnectionDeletionFailed { connection, reason } => {
+                write!(
+                    f,
+                    "Connection deletion failed; {:?}; {}",
+                    connection, reason
+                )
+            }
+            GraphError::ConnectionLocked => {
+                write!(f, "Connection is locked and cannot be deleted")
+            }
+            GraphError::SlotTemplateLocked { name } => {
+                write!(
+                    f,
+                    "Slot Template is locked and cannot be modified: {}",
+                    name
+                )
+            }
+            GraphError::SlotInstanceLocked => {
+                write!(f, "Node Instance is locked and cannot be modified")
+            }
+            GraphError::NodeTemplateLocked { name } => {
+                write!(
+                    f,
+                    "Node Template is locked and cannot be modified: {}",
+                    name
+                )
+            }
+            GraphError::NodeInstanceLocked => {
+                write!(f, "Node Instance is locked and cannot be modified")
+            }
+            GraphError::SomeConnectionDeletionsFailed { failures } => {
+                write!(
+                    f,
+                    "Some connections in the requested action were not deleted"
+                )?;
+                for failure in failures {
+                    write!(f, "{}", failure)?;
+                }
+                Ok(())
+            }
+            GraphError::SomeSlotDeletionsFailed { failures } => {
+                write!(f, "Some slots in the requested action were not cleared")?;
+                for failure in failures {
+                    write!(f, "{}", failure)?;
+                }
+                Ok(())
+            }
+            GraphError::SaveFailed { reason } => {
+                write!(f, "Save action failed. \nReason: {:#?}", reason)
+            }
+            GraphError::GraphLockFailed => {
+                write!(f, "Could not obtain graph lock")
+            }
+            GraphError::SlotMalformed {
+                template_name,
+                slot_name,
+                min,
+                max,
+                actual,
+            } => {
+                write!(f, "Slot is not well-formed. \nTemplate Name: {}, Slot Name: {}, Minimum Connections: {}, Maximum Connections: {:#?}, Current Number of Instances: {}", template_name, slot_name, min, max, actual)
+            }
+            GraphError::DocumentParseFailed(msg) => {
+                write!(f, "Could not parse graph document: {}", msg)
+            }
+            GraphError::UnsupportedDocumentVersion { found, supported } => {
+                write!(
+                    f,
+                    "Graph document version {} is newer than the supported version {}",
+                    found, supported
+                )
+            }
+            GraphError::InvalidDocument(msg) => write!(f, "Invalid graph document: {}", msg),
+            GraphError::SchemaParseFailed(msg) => write!(f, "Could not parse schema: {}", msg),
+            GraphError::InvalidSchema(msg) => write!(f, "Invalid schema: {}", msg),
+            GraphError::UnsupportedSchemaConstruct { item, reason } => {
+                write!(f, "Cannot import {}: {}", item, reason)
+            }
+            GraphError::InvalidFieldValue {
+                field_name,
+                value,
+                reason,
+            } => write!(f, "{}: {} ({})", field_name, reason, value),
+            GraphError::InvalidFieldType { field_type, reason } => {
+                write!(f, "Invalid field type {}: {}", field_type, reason)
+            }
+            GraphError::FieldRequired { field_name } => {
+                write!(f, "{} is required", field_name)
+            }
+            GraphError::FieldPatternMismatch {
+                field_name,
+                pattern,
+            } => write!(f, "{} must match the pattern {}", field_name, pattern),
+            GraphError::InvalidFieldPattern {
+                field_name,
+                pattern,
+                reason,
+            } => write!(
+                f,
+                "{} has an invalid pattern {}: {}",
+                field_name, pattern, reason
+            ),
+            GraphError::FieldLengthOutOfRange {
+                field_name,
+                min,
+                max,
+                actual,
+            } => {
+                write!(f, "{} must be ", field_name)?;
+                match (min, max) {
+                    (Some(min), Some(max)) => write!(f, "{} to {} characters", min, max)?,
+                    (Some(min), None) => write!(f, "at least {} characters", min)?,
+                    (None, Some(max)) => write!(f, "at most {} characters", max)?,
+                    (None, None) => write!(f, "a valid length")?,
+                }
+                write!(f, ", got {}", actual)
+            }
+            GraphError::FieldValueOutOfRange {
+                field_name,
+                min,
+                max,
+                value,
+            } => {
+                write!(f, "{} must be ", field_name)?;
+                match (min, max) {
+                    (Some(min), Some(max)) => write!(f, "between {} and {}", min, max)?,
+                    (Some(min), None) => write!(f, "at least {}", min)?,
+                    (None, Some(max)) => write!(f, "at most {}", max)?,
+                    (None, None) => write!(f, "in range")?,
+                }
+                write!(f, ", got {}", value)
+            }
+            GraphError::DuplicateFieldValue {
+                field_name,
+                value,
+                node_id,
+            } => write!(
+                f,
+                "{} must be unique, {} is already used by node {}",
+                field_name, value, node_id
+            ),
+            GraphError::NoEditingSession => write!(f, "No editing session has been started"),
+            GraphError::SyncRejected { reason } => {
+                write!(f, "Sync adapter rejected the change: {}", reason)
+            }
+            GraphError::ListOfErrors(vec) => {
+                for error in vec {
+                    write!(f, "{:#?}", error)?;
+                }
+                Ok(())
+            }
+            GraphError::Other(msg) => write!(f, "Other error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+pub type GraphResult<T> = Result<T, GraphError>;
@@ -0,0 +1,268 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    errors::GraphError,
+    graph::{Connection, Graph, GraphCommand},
+    session::EditingSession,
+};
+
+#[derive(Debug, Clone)]
+pub enum SystemEvent {
+    // State Changes
+    NodeMoved {
+        node: String,
+        x: f64,
+        y: f64,
+    },
+    ConnectionStarted {
+        node: String,
+        slot: String,
+    },
+    ConnectionCompleted(Connection),
+    ConnectionFailed(String), // with reason
+
+    // UI Events
+    ContextMenuOpened(ContextMenuTarget),
+    ContextMenuClosed,
+    // The ids of every selected node, sorted
+    SelectionChanged(Vec<String>),
+
+    // Command Results
+    CommandExecuted(GraphCommand),
+    CommandFailed {
+        command: GraphCommand,
+        reason: GraphError,
+    },
+
+    // History
+    CommandUndone(GraphCommand),
+    CommandRedone(GraphCommand),
+    HistoryChanged {
+        can_undo: bool,
+        can_redo: bool,
+    },
+
+    // Draft sessions
+    SessionChanged(EditingSession),
+
+    // External sync
+    SyncRejected(GraphError),
+}
+
+impl SystemEvent {
+    // The name hosts use to subscribe to this kind of event
+    pub fn name(&self) -> &'static str {
+        match self {
+            SystemEvent::NodeMoved { .. } => "NodeMoved",
+            SystemEvent::ConnectionStarted { .. } => "ConnectionStarted",
+            SystemEvent::ConnectionCompleted(_) => "ConnectionCompleted",
+            SystemEvent::ConnectionFailed(_) => "ConnectionFailed",
+            SystemEvent::ContextMenuOpened(_) => "ContextMenuOpened",
+            SystemEvent::ContextMenuClosed => "ContextMenuClosed",
+            SystemEvent::SelectionChanged(_) => "SelectionChanged",
+            SystemEvent::CommandExecuted(_) => "CommandExecuted",
+            SystemEvent::CommandFailed { .. } => "CommandFailed",
+            SystemEvent::CommandUndone(_) => "CommandUndone",
+            SystemEvent::CommandRedone(_) => "CommandRedone",
+            SystemEvent::HistoryChanged { .. } => "HistoryChanged",
+            SystemEvent::SessionChanged(_) => "SessionChanged",
+            SystemEvent::SyncRejected(_) => "SyncRejected",
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub enum ContextMenuTarget {
+    Node(String),
+    // from_node
+    Connection(Connection),
+    Slot {
+        node_id: String,
+        slot_template_id: String,
+    },
+    Field {
+        node_id: String,
+        field_template_id: String,
+    },
+}
+impl ContextMenuTarget {
+    pub fn get_title(&self, graph: &Graph) -> String {
+        match self {
+            ContextMenuTarget::Node(node_id) => {
+                if let Some(node) = graph.node_instances.get(node_id) {
+                    if let Some(template) = graph.node_templates.get(&node.template_id) {
+                        format!("Node: {}", template.name)
+                    } else {
+                        "Unknown Node".to_string()
+                    }
+                } else {
+                    "Unknown Node".to_string()
+                }
+            }
+            ContextMenuTarget::Connection { .. } => "Connection".to_string(),
+            ContextMenuTarget::Slot {
+                node_id,
+                slot_template_id,
+            } => {
+                if let Some(node) = graph.node_instances.get(node_id) {
+                    if let Some(slot) = node
+                        .slots
+                        .iter()
+                        .find(|s| s.slot_template_id == *slot_template_id)
+                    {
+                        if let Some(template) = graph.node_templates.get(&node.template_id) {
+                            if let Some(slot_template) = template
+                                .slot_templates
+                                .iter()
+                                .find(|t| t.id == slot.slot_template_id)
+                            {
+                                return format!("Slot: {}", slot_template.name);
+                            }
+                        }
+                    }
+                }
+                "Unknown Slot".to_string()
+            }
+            ContextMenuTarget::Field {
+                node_id,
+                field_template_id,
+            } => {
+                if let Some(node) = graph.node_instances.get(node_id) {
+                    if let Some(field) = node
+                        .fields
+                        .iter()
+                        .find(|f| f.field_template_id == *field_template_id)
+                    {
+                        if let Some(template) = graph.node_templates.get(&node.template_id) {
+                            if let Some(field_template) = template
+                                .field_templates
+                                .iter()
+                                .find(|t| t.id == field.field_template_id)
+                            {
+                                return format!("Field: {}", field_template.name);
+                            }
+                        }
+                    }
+                }
+                "Unknown Field".to_string()
+            }
+        }
+    }
+}
+
+pub type EventListener = Box<dyn Fn(&SystemEvent) + Send + 'static>;
+pub type ListenerId = u32;
+
+pub struct EventSystem {
+    listeners: Vec<(ListenerId, EventListener)>,
+    next_listener_id: ListenerId,
+}
+
+impl Default for EventSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventSystem {
+    pub fn new() -> Self {
+        Self {
+            listeners: Vec::new(),
+            next_listener_id: 0,
+        }
+    }
+
+    /// Registers a listener for every event. The returned id can be passed to `unsubscribe`.
+    pub fn subscribe(&mut self, listener: EventListener) -> ListenerId {
+        let id = self.next_listener_id;
+        self.next_listener_id += 1;
+        self.listeners.push((id, listener));
+        id
+    }
+
+    /// Returns false if no listener with that id was registered.
+    pub fn unsubscribe(&mut self, id: ListenerId) -> bool {
+        let count = self.listeners.len();
+        self.listeners.retain(|(listener_id, _)| *listener_id != id);
+        self.listeners.len() != count
+    }
+
+    /// Drops every listener.
+    pub fn clear(&mut self) {
+        self.listeners.clear();
+    }
+
+    pub fn emit(&self, event: SystemEvent) {
+        for (_, listener) in &self.listeners {
+            listener(&event);
+        }
+    }
+
+    // An event system that only collects what is emitted on it, for work that may still be
+    // rolled back
+    pub(crate) fn holding() -> (Self, HeldEvents) {
+        let held = HeldEvents::default();
+        let sink = held.clone();
+        let mut events = Self::new();
+        events.subscribe(Box::new(move |event| {
+            if let Ok(mut held) = sink.0.lock() {
+                held.push(event.clone());
+            }
+        }));
+        (events, held)
+    }
+}
+
+// Events collected by `EventSystem::holding`. Dropping them discards them.
+#[derive(Clone, Default)]
+pub(crate) struct HeldEvents(Arc<Mutex<Vec<SystemEvent>>>);
+
+impl HeldEvents {
+    // Passes the collected events on, in the order they were emitted
+    pub(crate) fn release(self, events: &EventSystem) {
+        let held = self
+            .0
+            .lock()
+            .map(|mut held| std::mem::take(&mut *held))
+            .unwrap_or_default();
+        for event in held {
+            events.emit(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::record_events;
+
+    #[test]
+    fn unsubscribed_listeners_hear_nothing_more() {
+        let (mut events, names) = record_events();
+        events.emit(SystemEvent::ContextMenuClosed);
+        // The recording listener is the first one subscribed
+        assert!(events.unsubscribe(0));
+        assert!(!events.unsubscribe(0));
+
+        events.emit(SystemEvent::ContextMenuClosed);
+
+        assert_eq!(*names.lock().unwrap(), vec!["ContextMenuClosed"]);
+    }
+
+    #[test]
+    fn held_events_wait_until_released_and_keep_their_order() {
+        let (events, names) = record_events();
+        let (inner, held) = EventSystem::holding();
+        inner.emit(SystemEvent::ContextMenuClosed);
+        inner.emit(SystemEvent::SelectionChanged(vec![]));
+        assert!(names.lock().unwrap().is_empty());
+
+        held.release(&events);
+
+        assert_eq!(
+            *names.lock().unwrap(),
+            vec!["ContextMenuClosed", "SelectionChanged"]
+        );
+    }
+}
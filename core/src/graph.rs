@@ -1,33 +1,61 @@
 use crate::{
+    config::{InitialNode, TemplateIdentifier},
     errors::{GraphError, GraphResult},
-    log, InitialNode, TemplateIdentifier,
 };
 use std::collections::HashMap;
 
 use crate::{
     common::generate_id,
     events::{EventSystem, SystemEvent},
+    history::{CommandHistory, HistoryEntry},
+    session::EditingSession,
+    sync::GraphSync,
+    validation::PatternCache,
 };
 
+// Id of the incoming slot added to templates that do not declare any incoming slots
+pub const DEFAULT_INCOMING_SLOT_ID: &str = "incoming";
+
 pub trait NodeTemplateInfo {
     fn get_slot_template(&self, slot_id: &str) -> Option<&SlotTemplate>;
     fn get_slot_template_by_name(&self, slot_name: &str) -> Option<&SlotTemplate>;
 }
 
 // Template definitions
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 pub enum FieldType {
     Boolean,
     Integer,
     String,
+    Float {
+        min: Option<f64>,
+        max: Option<f64>,
+        // Values must be a whole number of steps from `min` (or from 0 without one)
+        step: Option<f64>,
+    },
+    // One of a fixed list of values
+    Enum {
+        options: Vec<String>,
+    },
+    // "#rrggbb" or "#rgb"
+    Color,
+    // "YYYY-MM-DD"
+    Date,
+    MultilineText,
+    // Any valid JSON document
+    Json,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 pub struct FieldTemplate {
     pub id: String,
     pub name: String,
     pub field_type: FieldType,
     pub default_value: String,
+    #[serde(default)]
+    pub constraints: FieldConstraints,
 }
 
 impl FieldTemplate {
@@ -37,11 +65,38 @@ impl FieldTemplate {
             name: name.to_string(),
             field_type,
             default_value: default_value.to_string(),
+            constraints: FieldConstraints::default(),
         }
     }
+
+    pub fn with_constraints(mut self, constraints: FieldConstraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Rules a field value must follow on top of those of its type. Checked whenever a field is
+/// updated and reported by `Graph::validate`.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[serde(default)]
+pub struct FieldConstraints {
+    // Rejects empty and whitespace-only values
+    pub required: bool,
+    // Regular expression the whole value must match
+    pub pattern: Option<String>,
+    // Lengths are counted in characters
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    // Only apply to numeric fields
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    // No two nodes of the same template may share a (non-empty) value
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 pub struct NodeTemplate {
     pub template_id: String,
     pub name: String,
@@ -76,6 +131,12 @@ impl NodeTemplate {
             default_color: "white".to_string(),
         }
     }
+
+    pub fn incoming_slot_templates(&self) -> impl Iterator<Item = &SlotTemplate> {
+        self.slot_templates
+            .iter()
+            .filter(|st| st.slot_type == SlotType::Incoming)
+    }
 }
 
 impl NodeTemplateInfo for NodeTemplate {
@@ -87,13 +148,20 @@ impl NodeTemplateInfo for NodeTemplate {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 pub struct SlotTemplate {
     pub id: String,
     pub name: String,
     pub position: SlotPosition,
     pub slot_type: SlotType,
-    pub allowed_connections: Vec<String>, // template_ids that can connect here
+    // Outgoing: template names this slot can connect to.
+    // Incoming: template names that can connect here (empty accepts any).
+    pub allowed_connections: Vec<String>,
+    // Incoming only: names of the outgoing slots that can connect here (empty accepts any)
+    #[serde(default)]
+    pub allowed_source_slots: Vec<String>,
+    // For incoming slots these count the connections ending here
     pub min_connections: usize,
     pub max_connections: Option<usize>,
     pub can_modify_connections: bool,
@@ -106,6 +174,7 @@ impl SlotTemplate {
             position: SlotPosition::Right,
             slot_type: SlotType::Outgoing,
             allowed_connections: vec![],
+            allowed_source_slots: vec![],
             min_connections: 0,
             max_connections: None,
             can_modify_connections: true,
@@ -114,7 +183,8 @@ impl SlotTemplate {
 }
 
 // Instance definitions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 pub struct FieldInstance {
     pub node_instance_id: String,
     pub field_template_id: String,
@@ -122,7 +192,9 @@ pub struct FieldInstance {
     pub can_modify: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
 pub struct NodeInstance {
     pub instance_id: String,
     pub template_id: String,
@@ -142,7 +214,7 @@ pub struct NodeInstance {
 
 impl NodeInstance {
     pub fn new(template: &NodeTemplate, instance_id: String, x: f64, y: f64) -> Self {
-        let mut slots = template
+        let slots = template
             .slot_templates
             .iter()
             .map(|st| SlotInstance {
@@ -154,15 +226,6 @@ impl NodeInstance {
             })
             .collect::<Vec<_>>();
 
-        // Add the standard incoming slot
-        slots.push(SlotInstance {
-            node_instance_id: instance_id.clone(),
-            node_template_id: template.template_id.clone(),
-            slot_template_id: "incoming".to_string(),
-            connections: Vec::new(),
-            can_modify: true,
-        });
-
         // Create field instances from template
         let fields = template
             .field_templates
@@ -190,12 +253,15 @@ impl NodeInstance {
             can_modify_fields: true,
         }
     }
-    pub fn capabilities<'a>(&'a self, graph: &'a Graph) -> NodeCapabilities<'a> {
-        let template = graph.node_templates.get(&self.template_id).unwrap();
-        NodeCapabilities {
+    pub fn capabilities<'a>(&'a self, graph: &'a Graph) -> GraphResult<NodeCapabilities<'a>> {
+        let template = graph
+            .node_templates
+            .get(&self.template_id)
+            .ok_or_else(|| graph.node_lookup_error(&self.instance_id))?;
+        Ok(NodeCapabilities {
             template,
             instance: self,
-        }
+        })
     }
 }
 
@@ -221,7 +287,8 @@ impl<'a> NodeCapabilities<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 pub struct SlotInstance {
     // pub id: String,
     pub slot_template_id: String,
@@ -231,15 +298,18 @@ pub struct SlotInstance {
     pub can_modify: bool,
 }
 impl SlotInstance {
-    pub fn capabilities<'a>(&'a self, graph: &'a Graph) -> SlotCapabilities<'a> {
-        let node_template = graph.node_templates.get(&self.node_template_id).unwrap();
-        let slot_template = node_template
-            .get_slot_template(&self.slot_template_id)
-            .unwrap();
-        SlotCapabilities {
+    pub fn capabilities<'a>(&'a self, graph: &'a Graph) -> GraphResult<SlotCapabilities<'a>> {
+        let slot_template = graph
+            .node_templates
+            .get(&self.node_template_id)
+            .and_then(|node_template| node_template.get_slot_template(&self.slot_template_id))
+            .ok_or_else(|| {
+                graph.slot_lookup_error(&self.node_instance_id, &self.slot_template_id)
+            })?;
+        Ok(SlotCapabilities {
             template: slot_template,
             instance: self,
-        }
+        })
     }
 }
 #[derive(Debug, Clone)]
@@ -248,7 +318,9 @@ pub struct SlotCapabilities<'a> {
     pub instance: &'a SlotInstance,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
 pub struct Connection {
     pub can_delete: bool,
     pub host_node_id: String,
@@ -257,11 +329,8 @@ pub struct Connection {
     pub target_slot_template_id: String,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(
-    feature = "js",
-    derive(serde::Serialize, serde::Deserialize, tsify::Tsify)
-)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 #[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
 pub enum SlotPosition {
     Left,
@@ -270,11 +339,8 @@ pub enum SlotPosition {
     Bottom,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-#[cfg_attr(
-    feature = "js",
-    derive(serde::Serialize, serde::Deserialize, tsify::Tsify)
-)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 #[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
 pub enum SlotType {
     Incoming,
@@ -285,6 +351,12 @@ pub enum SlotType {
 pub struct Graph {
     pub node_templates: HashMap<String, NodeTemplate>,
     pub node_instances: HashMap<String, NodeInstance>,
+    pub history: CommandHistory,
+    // None until a host starts a draft editing session
+    pub session: Option<EditingSession>,
+    // The host's external graph store, if it attached one
+    pub sync: Option<GraphSync>,
+    pub(crate) patterns: PatternCache,
 }
 
 impl Default for Graph {
@@ -298,16 +370,23 @@ impl Graph {
         Graph {
             node_templates: HashMap::new(),
             node_instances: HashMap::new(),
+            history: CommandHistory::new(),
+            session: None,
+            sync: None,
+            patterns: PatternCache::default(),
         }
     }
 
-    pub fn node_template_can_add_instance(&self, node_template_id: &str) -> bool {
+    pub fn node_template_can_add_instance(&self, node_template_id: &str) -> GraphResult<bool> {
         let instances_of_template = self.instances_of_node_template(node_template_id);
-        let template = self.node_templates.get(node_template_id).unwrap();
+        let template = self
+            .node_templates
+            .get(node_template_id)
+            .ok_or_else(|| GraphError::TemplateNotFound(node_template_id.to_string()))?;
         if let Some(max_instances) = template.max_instances {
-            instances_of_template.len() < max_instances
+            Ok(instances_of_template.len() < max_instances)
         } else {
-            true
+            Ok(true)
         }
     }
     pub fn instances_of_node_template(&self, template_id: &str) -> Vec<String> {
@@ -318,26 +397,24 @@ impl Graph {
             .collect()
     }
 
-    pub fn register_template(&mut self, template: NodeTemplate) {
-        let mut new_slots = template.slot_templates.clone();
-        new_slots.push(SlotTemplate {
-            id: "incoming".to_string(),
-            name: "Incoming".to_string(),
-            position: SlotPosition::Left,
-            slot_type: SlotType::Incoming,
-            allowed_connections: Vec::new(),
-            min_connections: 0,
-            max_connections: None,
-            can_modify_connections: true,
-        });
-        let template_with_incoming_slot = NodeTemplate {
-            slot_templates: new_slots,
-            ..template
-        };
-        self.node_templates.insert(
-            template_with_incoming_slot.template_id.clone(),
-            template_with_incoming_slot,
-        );
+    /// Templates that declare no incoming slots get a single unconstrained one, so that
+    /// every node can be connected to.
+    pub fn register_template(&mut self, mut template: NodeTemplate) {
+        if template.incoming_slot_templates().next().is_none() {
+            template.slot_templates.push(SlotTemplate {
+                id: DEFAULT_INCOMING_SLOT_ID.to_string(),
+                name: "Incoming".to_string(),
+                position: SlotPosition::Left,
+                slot_type: SlotType::Incoming,
+                allowed_connections: Vec::new(),
+                allowed_source_slots: Vec::new(),
+                min_connections: 0,
+                max_connections: None,
+                can_modify_connections: true,
+            });
+        }
+        self.node_templates
+            .insert(template.template_id.clone(), template);
     }
 
     pub fn create_initial_nodes(&mut self, initial_nodes: &Vec<InitialNode>) -> GraphResult<()> {
@@ -407,8 +484,6 @@ impl Graph {
                 if let Some(instance) = self.node_instances.get_mut(&instance_id) {
                     instance.can_delete = node.can_delete;
                     instance.can_move = node.can_move;
-                } else {
-                    log("Instance wasn't created");
                 }
                 Ok::<(String, &InitialNode), GraphError>((instance_id, node))
             })
@@ -445,7 +520,28 @@ impl Graph {
                     }
                     let host_node_slot_id = host_node_slot.unwrap().id.clone();
                     let target_node_id = other_caps.instance.instance_id.clone();
-                    let target_slot_template_id = "incoming".to_string();
+                    let target_slot_template_id = match &initial_connection.target_slot_name {
+                        Some(target_slot_name) => other_caps
+                            .template
+                            .incoming_slot_templates()
+                            .find(|st| st.name == *target_slot_name)
+                            .map(|st| st.id.clone()),
+                        None => self.resolve_target_slot(
+                            &host_node_id,
+                            &host_node_slot_id,
+                            &target_node_id,
+                        ),
+                    };
+                    let Some(target_slot_template_id) = target_slot_template_id else {
+                        connection_errors.push(GraphError::SlotNotFound {
+                            node_id: target_node_id,
+                            slot_id: initial_connection
+                                .target_slot_name
+                                .clone()
+                                .unwrap_or_default(),
+                        });
+                        break;
+                    };
 
                     drop(host_node_caps);
 
@@ -491,7 +587,7 @@ impl Graph {
             }
         };
 
-        if !self.node_template_can_add_instance(node_template_id) {
+        if !self.node_template_can_add_instance(node_template_id)? {
             return Err(GraphError::NodeCreationFailed {
                 node_template_id: node_template_id.to_string(),
                 node_template_name: template.name.clone(),
@@ -546,6 +642,25 @@ impl Graph {
         Ok(instance_id)
     }
 
+    // Why get_node_capabilities found nothing: the node, or the template it names, is missing
+    fn node_lookup_error(&self, node_id: &str) -> GraphError {
+        match self.node_instances.get(node_id) {
+            Some(instance) => GraphError::TemplateNotFound(instance.template_id.clone()),
+            None => GraphError::NodeNotFound(node_id.to_string()),
+        }
+    }
+
+    // Why get_slot_capabilities found nothing
+    fn slot_lookup_error(&self, node_id: &str, slot_template_id: &str) -> GraphError {
+        if self.get_node_capabilities(node_id).is_none() {
+            return self.node_lookup_error(node_id);
+        }
+        GraphError::SlotNotFound {
+            node_id: node_id.to_string(),
+            slot_id: slot_template_id.to_string(),
+        }
+    }
+
     pub fn get_node_capabilities(&self, instance_id: &str) -> Option<NodeCapabilities> {
         let instance = self.node_instances.get(instance_id)?;
         let template = self.node_templates.get(&instance.template_id)?;
@@ -580,9 +695,22 @@ impl Graph {
             ..
         } = connection;
         let from_slot_cap = self
-            .get_slot_capabilities(&host_node_id, &host_slot_template_id)
-            .unwrap();
-        let target_node_cap = self.get_node_capabilities(&target_node_id).unwrap();
+            .get_slot_capabilities(host_node_id, host_slot_template_id)
+            .ok_or_else(|| self.slot_lookup_error(host_node_id, host_slot_template_id))?;
+        let target_node_cap = self
+            .get_node_capabilities(target_node_id)
+            .ok_or_else(|| self.node_lookup_error(target_node_id))?;
+        let slot_types_match = if from_slot_cap.template.slot_type != SlotType::Outgoing {
+            Err("Connections must start at an outgoing slot")
+        } else if !target_node_cap
+            .template
+            .incoming_slot_templates()
+            .any(|st| st.id == *target_slot_template_id)
+        {
+            Err("Connections must end at an incoming slot of the target node")
+        } else {
+            Ok(())
+        };
         let connection_allowed = if from_slot_cap
             .template
             .allowed_connections
@@ -608,12 +736,38 @@ impl Graph {
             } else {
                 Err("Connection alrady exists")
             };
+        if let Some(err) = slot_types_match.err() {
+            return Err(GraphError::InvalidConnection {
+                connection: connection.clone(),
+                reason: err.to_string(),
+            });
+        }
+        if let Some(err) = self.target_slot_rejection(connection) {
+            return Err(GraphError::InvalidConnection {
+                connection: connection.clone(),
+                reason: err.to_string(),
+            });
+        }
         if let Some(err) = max_len_reached.err() {
             return Err(GraphError::InvalidConnection {
                 connection: connection.clone(),
                 reason: err.to_string(),
             });
         }
+        let target_slot_max = target_node_cap
+            .template
+            .get_slot_template(target_slot_template_id)
+            .and_then(|st| st.max_connections);
+        if self
+            .incoming_connections(target_node_id, target_slot_template_id)
+            .len()
+            >= target_slot_max.unwrap_or(usize::MAX)
+        {
+            return Err(GraphError::InvalidConnection {
+                connection: connection.clone(),
+                reason: "Target slot's max incoming connections is reached".to_string(),
+            });
+        }
         if let Some(err) = connection_already_exists.err() {
             return Err(GraphError::InvalidConnection {
                 connection: connection.clone(),
@@ -629,6 +783,72 @@ impl Graph {
         Ok(())
     }
 
+    // Checks the target slot's rules about which templates and slots may connect into it
+    pub(crate) fn target_slot_rejection(&self, connection: &Connection) -> Option<&'static str> {
+        let host_template = self
+            .node_instances
+            .get(&connection.host_node_id)
+            .and_then(|instance| self.node_templates.get(&instance.template_id))?;
+        let host_slot = host_template.get_slot_template(&connection.host_slot_template_id)?;
+        let target_slot = self
+            .node_instances
+            .get(&connection.target_node_id)
+            .and_then(|instance| self.node_templates.get(&instance.template_id))
+            .and_then(|template| template.get_slot_template(&connection.target_slot_template_id))?;
+        if !target_slot.allowed_connections.is_empty()
+            && !target_slot
+                .allowed_connections
+                .contains(&host_template.name)
+        {
+            return Some("The target slot does not accept connections from this node template");
+        }
+        if !target_slot.allowed_source_slots.is_empty()
+            && !target_slot.allowed_source_slots.contains(&host_slot.name)
+        {
+            return Some("The target slot does not accept connections from this slot");
+        }
+        None
+    }
+
+    /// All connections ending at the given incoming slot.
+    pub fn incoming_connections(&self, node_id: &str, slot_template_id: &str) -> Vec<&Connection> {
+        self.node_instances
+            .values()
+            .flat_map(|instance| instance.slots.iter())
+            .flat_map(|slot| slot.connections.iter())
+            .filter(|conn| {
+                conn.target_node_id == node_id && conn.target_slot_template_id == slot_template_id
+            })
+            .collect()
+    }
+
+    /// Picks the incoming slot on `target_node_id` that a connection from the given host slot
+    /// should attach to: the first one that would accept it, otherwise the first incoming slot
+    /// (so that executing the connection reports why it is invalid).
+    pub fn resolve_target_slot(
+        &self,
+        host_node_id: &str,
+        host_slot_template_id: &str,
+        target_node_id: &str,
+    ) -> Option<String> {
+        let target_caps = self.get_node_capabilities(target_node_id)?;
+        let mut incoming_slots = target_caps.template.incoming_slot_templates().peekable();
+        let first_incoming_slot = incoming_slots.peek()?.id.clone();
+        incoming_slots
+            .find(|st| {
+                self.is_valid_connection(&Connection {
+                    can_delete: true,
+                    host_node_id: host_node_id.to_string(),
+                    host_slot_template_id: host_slot_template_id.to_string(),
+                    target_node_id: target_node_id.to_string(),
+                    target_slot_template_id: st.id.clone(),
+                })
+                .is_ok()
+            })
+            .map(|st| st.id.clone())
+            .or(Some(first_incoming_slot))
+    }
+
     pub fn connect_slots(
         &mut self,
         connection: Connection,
@@ -641,10 +861,10 @@ impl Graph {
         } = connection.clone();
         let node_caps = self
             .get_node_capabilities(&host_node_id)
-            .expect("Node should exist");
+            .ok_or_else(|| self.node_lookup_error(&host_node_id))?;
         let slot_template = node_caps
             .get_slot_template(&host_slot_template_id)
-            .expect("Template should exist");
+            .ok_or_else(|| self.slot_lookup_error(&host_node_id, &host_slot_template_id))?;
 
         self.is_valid_connection(&connection).map_err(|err| {
             GraphError::ConnectionCreationFailed {
@@ -697,42 +917,30 @@ impl Graph {
     }
 
     pub fn is_graph_valid(&self) -> bool {
-        // Check if all nodes satisfy their template constraints
-        for instance in self.node_instances.values() {
-            if let Some(template) = self.node_templates.get(&instance.template_id) {
-                for (slot, slot_template) in
-                    instance.slots.iter().zip(template.slot_templates.iter())
-                {
-                    if slot.connections.len() < slot_template.min_connections
-                        || slot.connections.len()
-                            > slot_template.max_connections.unwrap_or(usize::MAX)
-                    {
-                        return false;
-                    }
-                    // Additional validation could go here
-                }
-            }
-        }
-        true
+        self.validate().is_valid()
     }
 
     fn check_slot_modifiable_status(&self, slot: &SlotInstance) -> GraphResult<()> {
         let node_caps = self
             .get_node_capabilities(&slot.node_instance_id)
-            .expect("Node should exist");
-        let slot = node_caps
+            .ok_or_else(|| self.node_lookup_error(&slot.node_instance_id))?;
+        let slot_template = node_caps
             .template
             .get_slot_template(&slot.slot_template_id)
-            .expect("Slot should exist");
+            .ok_or_else(|| {
+                self.slot_lookup_error(&slot.node_instance_id, &slot.slot_template_id)
+            })?;
         let slot_instance = node_caps
             .instance
             .slots
             .iter()
-            .find(|slot| slot.slot_template_id == *slot.slot_template_id)
-            .expect("Slot should exist");
-        if !slot.can_modify_connections {
+            .find(|instance_slot| instance_slot.slot_template_id == slot.slot_template_id)
+            .ok_or_else(|| {
+                self.slot_lookup_error(&slot.node_instance_id, &slot.slot_template_id)
+            })?;
+        if !slot_template.can_modify_connections {
             return Err(GraphError::SlotTemplateLocked {
-                name: slot.name.clone(),
+                name: slot_template.name.clone(),
             });
         }
         if !slot_instance.can_modify {
@@ -746,7 +954,7 @@ impl Graph {
     fn check_node_modifiable_status(&self, node: &NodeInstance) -> GraphResult<()> {
         let node_caps = self
             .get_node_capabilities(&node.instance_id)
-            .expect("Node should exist");
+            .ok_or_else(|| self.node_lookup_error(&node.instance_id))?;
         if !node_caps.template.can_modify_slots {
             return Err(GraphError::NodeTemplateLocked {
                 name: node_caps.template.name.clone(),
@@ -766,7 +974,7 @@ impl Graph {
         } = conn;
         let node_caps = self
             .get_node_capabilities(host_node_id)
-            .expect("Node should exist");
+            .ok_or_else(|| self.node_lookup_error(host_node_id))?;
         // let slot = node_caps
         //     .template
         //     .get_slot_template(host_slot_template_id)
@@ -776,7 +984,7 @@ impl Graph {
             .slots
             .iter()
             .find(|slot| slot.slot_template_id == *host_slot_template_id)
-            .expect("Slot should exist");
+            .ok_or_else(|| self.slot_lookup_error(host_node_id, host_slot_template_id))?;
         if !conn.can_delete {
             return Err(GraphError::ConnectionDeletionFailed {
                 connection: conn.clone(),
@@ -847,7 +1055,27 @@ impl Graph {
         Ok(())
     }
 
+    /// Deletes a node with all its connections. Nothing changes if it cannot be deleted.
     pub fn delete_node_instance(&mut self, node_id: &str) -> GraphResult<()> {
+        let snapshot = self.node_instances.clone();
+        let result = self.remove_node_instance(node_id);
+        if result.is_err() {
+            self.node_instances = snapshot;
+        }
+        result
+    }
+    pub fn delete_node_instances(&mut self, node_ids: &[String]) -> GraphResult<()> {
+        let snapshot = self.node_instances.clone();
+        for node_id in node_ids {
+            if let Err(err) = self.remove_node_instance(node_id) {
+                self.node_instances = snapshot;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+    // May fail after removing some of the node's connections; callers restore a snapshot
+    fn remove_node_instance(&mut self, node_id: &str) -> GraphResult<()> {
         let instance = self
             .node_instances
             .get(node_id)
@@ -918,13 +1146,26 @@ impl Graph {
         Ok(())
     }
     pub fn remove_all_incoming_connections(&mut self, node_id: &str) -> GraphResult<()> {
+        self.remove_incoming_connections(node_id, None)
+    }
+    // Removes connections ending at `node_id`, or only those ending at one of its incoming
+    // slots when `slot_template_id` is given
+    fn remove_incoming_connections(
+        &mut self,
+        node_id: &str,
+        slot_template_id: Option<&str>,
+    ) -> GraphResult<()> {
         let connections_to_remove = self.node_instances.values().fold(vec![], |mut agg, inst| {
             agg.append(&mut inst.slots.iter().fold(vec![], |mut agg, slot| {
                 agg.append(
                     &mut slot
                         .connections
                         .iter()
-                        .filter(|conn| conn.target_node_id == node_id)
+                        .filter(|conn| {
+                            conn.target_node_id == node_id
+                                && slot_template_id
+                                    .is_none_or(|id| conn.target_slot_template_id == id)
+                        })
                         .cloned()
                         .collect::<Vec<_>>(),
                 );
@@ -972,7 +1213,7 @@ impl Graph {
                 .find(|s| s.slot_template_id == slot_template_id)
             {
                 if slot_type == SlotType::Incoming {
-                    self.remove_all_incoming_connections(node_id)?;
+                    self.remove_incoming_connections(node_id, Some(slot_template_id))?;
                 } else {
                     let errs = slot
                         .connections
@@ -1003,6 +1244,15 @@ impl Graph {
             .find(|t| t.name == name)
             .cloned()
     }
+    /// Fails with the full validation report if the graph has any errors.
+    pub fn check_conformity(&self) -> GraphResult<()> {
+        let report = self.validate();
+        if report.is_valid() {
+            return Ok(());
+        }
+        Err(GraphError::ValidationFailed(report))
+    }
+
     pub fn get_node_template_by_identifier(
         &self,
         identifier: &TemplateIdentifier,
@@ -1014,9 +1264,12 @@ impl Graph {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
 pub enum GraphCommand {
     DeleteNode(String),
+    // Deletes every listed node, or none of them if any one cannot be deleted
+    DeleteNodes(Vec<String>),
     DeleteConnection(Connection),
     DeleteSlotConnections {
         node_id: String,
@@ -1027,46 +1280,85 @@ pub enum GraphCommand {
         template_id: String,
         x: f64,
         y: f64,
+        // Generated when not provided
+        id: Option<String>,
     },
     UpdateField {
         node_id: String,
         field_template_id: String,
         new_value: String,
     },
+    // Nodes from `ClipboardPayload::instantiate`, added together with their connections
+    PasteNodes(Vec<NodeInstance>),
+    // Applied in order as one command: if any fails, none of them take effect
+    Batch(Vec<GraphCommand>),
 }
 
 impl Graph {
+    /// Runs a command and records it for undo. A command that fails leaves the graph as it
+    /// was, even if it had already changed part of it.
     pub fn execute_command(
         &mut self,
         command: GraphCommand,
         events: &EventSystem,
     ) -> GraphResult<()> {
-        let result = match command.clone() {
+        let before = self.node_instances.clone();
+        // Events from inside the command are only passed on once it has succeeded, so
+        // listeners never hear about changes that were rolled back
+        let (inner_events, held) = EventSystem::holding();
+        let result = self.apply_command(command.clone(), &inner_events);
+        // A rejected sync has already put the graph back
+        let result = result.and_then(|_| self.sync_edit(events));
+        match &result {
+            Ok(_) => {
+                held.release(events);
+                let entry = HistoryEntry::from_diff(command.clone(), &before, &self.node_instances);
+                events.emit(SystemEvent::CommandExecuted(command));
+                let edited = !entry.is_empty();
+                if edited {
+                    self.history.record(entry);
+                    self.emit_history_changed(events);
+                }
+                self.refresh_session(edited, events);
+            }
+            Err(e) => {
+                self.node_instances = before;
+                events.emit(SystemEvent::CommandFailed {
+                    command,
+                    reason: e.clone(),
+                });
+            }
+        }
+        result
+    }
+
+    // Applies a command without recording or announcing it, so a batch runs as a single command
+    fn apply_command(&mut self, command: GraphCommand, events: &EventSystem) -> GraphResult<()> {
+        match command {
             GraphCommand::DeleteNode(node_id) => self.delete_node_instance(&node_id),
+            GraphCommand::DeleteNodes(node_ids) => self.delete_node_instances(&node_ids),
             GraphCommand::DeleteConnection(conn) => self.delete_connection(&conn),
             GraphCommand::DeleteSlotConnections {
                 node_id,
                 slot_template_id: slot_id,
             } => self.delete_slot_connections(&node_id, &slot_id),
             GraphCommand::CreateConnection(connection) => self.connect_slots(connection, events),
-            GraphCommand::CreateNode { template_id, x, y } => {
-                self.create_instance(&template_id, x, y, None).map(|_| ())
-            }
+            GraphCommand::CreateNode {
+                template_id,
+                x,
+                y,
+                id,
+            } => self.create_instance(&template_id, x, y, id).map(|_| ()),
             GraphCommand::UpdateField {
                 node_id,
                 field_template_id,
                 new_value,
             } => self.update_field(&node_id, &field_template_id, new_value),
-        };
-        match &result {
-            Ok(_) => {
-                events.emit(SystemEvent::CommandExecuted(command));
-            }
-            Err(e) => {
-                log(&format!("{:#?}", e));
-            }
+            GraphCommand::PasteNodes(nodes) => self.paste_nodes(nodes, events),
+            GraphCommand::Batch(commands) => commands
+                .into_iter()
+                .try_for_each(|command| self.apply_command(command, events)),
         }
-        result
     }
 
     // Method to update a field's value
@@ -1076,59 +1368,413 @@ impl Graph {
         field_template_id: &str,
         new_value: String,
     ) -> GraphResult<()> {
-        if let Some(node) = self.node_instances.get_mut(node_id) {
-            // Check if node allows field modifications
-            if !node.can_modify_fields {
-                return Err(GraphError::Other("Node fields are locked".to_string()));
-            }
-
-            // Find the field template to validate the value
-            let template = self
-                .node_templates
-                .get(&node.template_id)
-                .ok_or(GraphError::Other("Node template not found".to_string()))?;
+        let node = self
+            .node_instances
+            .get(node_id)
+            .ok_or_else(|| GraphError::NodeNotFound(node_id.to_string()))?;
 
-            let field_template = template
-                .field_templates
-                .iter()
-                .find(|ft| ft.id == field_template_id)
-                .ok_or(GraphError::Other("Field template not found".to_string()))?;
-
-            // Validate the value based on field type
-            match field_template.field_type {
-                FieldType::Boolean => {
-                    if new_value != "true" && new_value != "false" {
-                        return Err(GraphError::Other("Invalid boolean value".to_string()));
-                    }
-                }
-                FieldType::Integer => {
-                    if new_value.parse::<i32>().is_err() {
-                        return Err(GraphError::Other("Invalid integer value".to_string()));
-                    }
-                }
-                FieldType::String => {
-                    // All string values are valid
-                }
-            }
+        // Check if node allows field modifications
+        if !node.can_modify_fields {
+            return Err(GraphError::Other("Node fields are locked".to_string()));
+        }
 
-            // Update the field value
-            if let Some(field) = node
-                .fields
-                .iter_mut()
-                .find(|f| f.field_template_id == field_template_id)
-            {
-                if !field.can_modify {
-                    return Err(GraphError::Other("Field is locked".to_string()));
-                }
-                field.value = new_value;
-                return Ok(());
-            }
+        // Find the field template to validate the value
+        let template = self
+            .node_templates
+            .get(&node.template_id)
+            .ok_or_else(|| GraphError::TemplateNotFound(node.template_id.clone()))?;
 
+        let field_template = template
+            .field_templates
+            .iter()
+            .find(|ft| ft.id == field_template_id)
+            .ok_or(GraphError::Other("Field template not found".to_string()))?;
+
+        // Validate the value against its type and the template's constraints
+        field_template.check_value_with(&new_value, &self.patterns)?;
+        self.check_field_unique(node, field_template, &new_value)?;
+
+        // Update the field value
+        let node = self.node_instances.get_mut(node_id).unwrap();
+        let Some(field) = node
+            .fields
+            .iter_mut()
+            .find(|f| f.field_template_id == field_template_id)
+        else {
             return Err(GraphError::Other(
                 "Field not found in node instance".to_string(),
             ));
+        };
+        if !field.can_modify {
+            return Err(GraphError::Other("Field is locked".to_string()));
+        }
+        field.value = new_value;
+        Ok(())
+    }
+}
+
+// Lenient parsing for the names hosts pass in as plain strings (e.g. JS configs)
+impl From<String> for SlotPosition {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "Top" | "top" => SlotPosition::Top,
+            "Bottom" | "bottom" => SlotPosition::Bottom,
+            "Left" | "left" => SlotPosition::Left,
+            "Right" | "right" => SlotPosition::Right,
+            _ => SlotPosition::Top,
+        }
+    }
+}
+impl From<String> for SlotType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "Input" => SlotType::Incoming,
+            "Output" => SlotType::Outgoing,
+            _ => SlotType::Incoming,
         }
+    }
+}
+
+impl FieldType {
+    /// The field type a host names as a plain string. Enums cannot be read from a name alone,
+    /// so they need their `options`.
+    pub fn from_name(name: &str, options: Option<Vec<String>>) -> GraphResult<Self> {
+        let field_type = match name {
+            "Boolean" | "bool" | "boolean" => FieldType::Boolean,
+            "Integer" | "int" | "integer" => FieldType::Integer,
+            "String" | "string" | "text" => FieldType::String,
+            "Float" | "float" | "number" => FieldType::Float {
+                min: None,
+                max: None,
+                step: None,
+            },
+            "Enum" | "enum" | "select" => match options {
+                Some(options) if !options.is_empty() => FieldType::Enum { options },
+                _ => {
+                    return Err(GraphError::InvalidFieldType {
+                        field_type: name.to_string(),
+                        reason: "enum fields need at least one option".to_string(),
+                    })
+                }
+            },
+            "Color" | "color" => FieldType::Color,
+            "Date" | "date" => FieldType::Date,
+            "MultilineText" | "multiline" | "textarea" => FieldType::MultilineText,
+            "Json" | "json" => FieldType::Json,
+            _ => FieldType::String, // Default to string for unknown types
+        };
+        Ok(field_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn linked_graph() -> Graph {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, CHILD, "c");
+        graph
+    }
+
+    fn create_connection(graph: &mut Graph, connection: Connection) -> GraphResult<()> {
+        graph.execute_command(
+            GraphCommand::CreateConnection(connection),
+            &EventSystem::new(),
+        )
+    }
+
+    #[test]
+    fn connecting_from_an_unknown_host_node_fails() {
+        let mut graph = linked_graph();
+        let result = create_connection(&mut graph, child_connection("missing", "c"));
+        assert!(matches!(result, Err(GraphError::NodeNotFound(id)) if id == "missing"));
+    }
+
+    #[test]
+    fn connecting_from_an_unknown_host_slot_fails() {
+        let mut graph = linked_graph();
+        let mut connection = child_connection("p", "c");
+        connection.host_slot_template_id = "missing".to_string();
+        let result = create_connection(&mut graph, connection);
+        assert!(
+            matches!(result, Err(GraphError::SlotNotFound { slot_id, .. }) if slot_id == "missing")
+        );
+    }
 
-        Err(GraphError::Other("Node instance not found".to_string()))
+    #[test]
+    fn connecting_to_an_unknown_target_fails() {
+        let mut graph = linked_graph();
+        let result = create_connection(&mut graph, child_connection("p", "missing"));
+        let Err(GraphError::ConnectionCreationFailed { reason, .. }) = result else {
+            panic!("expected ConnectionCreationFailed, got {:?}", result);
+        };
+        assert!(matches!(*reason, GraphError::NodeNotFound(id) if id == "missing"));
+    }
+
+    #[test]
+    fn connecting_to_an_unknown_target_slot_fails() {
+        let mut graph = linked_graph();
+        let mut connection = child_connection("p", "c");
+        connection.target_slot_template_id = "missing".to_string();
+        assert!(create_connection(&mut graph, connection).is_err());
+        assert!(graph.get_node_connections("p").is_empty());
+    }
+
+    #[test]
+    fn deleting_a_connection_with_unknown_ids_fails() {
+        let mut graph = linked_graph();
+        connect(&mut graph, "p", "c");
+        let events = EventSystem::new();
+
+        let unknown_host = GraphCommand::DeleteConnection(child_connection("missing", "c"));
+        let Err(GraphError::ConnectionDeletionFailed { reason, .. }) =
+            graph.execute_command(unknown_host, &events)
+        else {
+            panic!("expected ConnectionDeletionFailed");
+        };
+        assert!(matches!(*reason, GraphError::NodeNotFound(id) if id == "missing"));
+
+        let mut connection = child_connection("p", "c");
+        connection.host_slot_template_id = "missing".to_string();
+        let Err(GraphError::ConnectionDeletionFailed { reason, .. }) =
+            graph.execute_command(GraphCommand::DeleteConnection(connection), &events)
+        else {
+            panic!("expected ConnectionDeletionFailed");
+        };
+        assert!(matches!(*reason, GraphError::SlotNotFound { .. }));
+        assert_eq!(graph.get_node_connections("p").len(), 1);
+    }
+
+    #[test]
+    fn deleting_slot_connections_of_an_unknown_node_fails() {
+        let mut graph = linked_graph();
+        let command = GraphCommand::DeleteSlotConnections {
+            node_id: "missing".to_string(),
+            slot_template_id: CHILDREN_SLOT.to_string(),
+        };
+        assert!(graph.execute_command(command, &EventSystem::new()).is_err());
+    }
+
+    #[test]
+    fn a_failed_batch_emits_only_command_failed() {
+        let mut graph = linked_graph();
+        let (events, names) = record_events();
+        let batch = GraphCommand::Batch(vec![
+            GraphCommand::CreateConnection(child_connection("p", "c")),
+            GraphCommand::DeleteNode("missing".to_string()),
+        ]);
+
+        assert!(graph.execute_command(batch, &events).is_err());
+
+        assert_eq!(*names.lock().unwrap(), vec!["CommandFailed"]);
+        assert!(graph.get_node_connections("p").is_empty());
+    }
+
+    #[test]
+    fn a_batch_emits_its_inner_events_once_it_succeeds() {
+        let mut graph = linked_graph();
+        add_node(&mut graph, CHILD, "c2");
+        let (events, names) = record_events();
+        let batch = GraphCommand::Batch(vec![
+            GraphCommand::CreateConnection(child_connection("p", "c")),
+            GraphCommand::CreateConnection(child_connection("p", "c2")),
+        ]);
+
+        graph.execute_command(batch, &events).unwrap();
+
+        assert_eq!(
+            *names.lock().unwrap(),
+            vec![
+                "ConnectionCompleted",
+                "ConnectionCompleted",
+                "CommandExecuted",
+                "HistoryChanged"
+            ]
+        );
+        assert!(graph.undo(&EventSystem::new()));
+        assert!(graph.get_node_connections("p").is_empty());
+    }
+
+    #[test]
+    fn a_node_that_cannot_be_deleted_keeps_all_its_connections() {
+        let mut graph = linked_graph();
+        add_node(&mut graph, PARENT, "p2");
+        connect(&mut graph, "p", "c");
+        let mut locked = child_connection("p2", "c");
+        locked.can_delete = false;
+        create_connection(&mut graph, locked).unwrap();
+
+        assert!(graph.delete_node_instance("c").is_err());
+
+        assert_eq!(graph.get_node_connections("p").len(), 1);
+        assert_eq!(graph.get_node_connections("p2").len(), 1);
+        assert!(graph.node_instances.contains_key("c"));
+    }
+
+    fn incoming_slot(name: &str, id: &str) -> SlotTemplate {
+        let mut slot = SlotTemplate::new(name);
+        slot.id = id.to_string();
+        slot.slot_type = SlotType::Incoming;
+        slot.position = SlotPosition::Left;
+        slot
+    }
+
+    // Children have a "Primary" slot that takes one connection from a "Children" slot, and
+    // a "Spare" slot that takes anything. Parents can also connect from an "Extras" slot.
+    fn two_slot_graph() -> Graph {
+        let mut extras = SlotTemplate::new("Extras");
+        extras.id = "extras".to_string();
+        extras.allowed_connections = vec!["Child".to_string()];
+        let mut parent = parent_template();
+        parent.slot_templates.push(extras);
+
+        let mut primary = incoming_slot("Primary", "primary");
+        primary.max_connections = Some(1);
+        primary.allowed_source_slots = vec!["Children".to_string()];
+        let mut child = child_template();
+        child.slot_templates.push(primary);
+        child.slot_templates.push(incoming_slot("Spare", "spare"));
+
+        let mut graph = Graph::new();
+        graph.register_template(parent);
+        graph.register_template(child);
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, PARENT, "p2");
+        add_node(&mut graph, CHILD, "c");
+        graph
+    }
+
+    fn slot_connection(host: &str, host_slot: &str, target: &str, target_slot: &str) -> Connection {
+        Connection {
+            can_delete: true,
+            host_node_id: host.to_string(),
+            host_slot_template_id: host_slot.to_string(),
+            target_node_id: target.to_string(),
+            target_slot_template_id: target_slot.to_string(),
+        }
+    }
+
+    fn rejection(result: GraphResult<()>) -> String {
+        match result {
+            Err(GraphError::ConnectionCreationFailed { reason, .. }) => match *reason {
+                GraphError::InvalidConnection { reason, .. } => reason,
+                other => panic!("expected InvalidConnection, got {:?}", other),
+            },
+            other => panic!("expected ConnectionCreationFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn templates_without_incoming_slots_get_the_default_one() {
+        let graph = sample_graph();
+        let incoming = graph.node_templates[CHILD]
+            .incoming_slot_templates()
+            .map(|slot| slot.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(incoming, vec![DEFAULT_INCOMING_SLOT_ID]);
+    }
+
+    #[test]
+    fn templates_with_incoming_slots_keep_only_their_own() {
+        let graph = two_slot_graph();
+        let incoming = graph.node_templates[CHILD]
+            .incoming_slot_templates()
+            .map(|slot| slot.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(incoming, vec!["primary", "spare"]);
+    }
+
+    #[test]
+    fn connections_must_end_at_an_incoming_slot() {
+        let mut graph = two_slot_graph();
+        let result = create_connection(
+            &mut graph,
+            slot_connection("p", CHILDREN_SLOT, "p2", CHILDREN_SLOT),
+        );
+        assert!(rejection(result).contains("must end at an incoming slot"));
+    }
+
+    #[test]
+    fn the_target_slot_is_the_first_incoming_slot_that_accepts() {
+        let mut graph = two_slot_graph();
+        assert_eq!(
+            graph
+                .resolve_target_slot("p", CHILDREN_SLOT, "c")
+                .as_deref(),
+            Some("primary")
+        );
+        // "Primary" only accepts connections from "Children" slots
+        assert_eq!(
+            graph.resolve_target_slot("p", "extras", "c").as_deref(),
+            Some("spare")
+        );
+
+        create_connection(
+            &mut graph,
+            slot_connection("p", CHILDREN_SLOT, "c", "primary"),
+        )
+        .unwrap();
+
+        // and only one of them
+        assert_eq!(
+            graph
+                .resolve_target_slot("p2", CHILDREN_SLOT, "c")
+                .as_deref(),
+            Some("spare")
+        );
+    }
+
+    #[test]
+    fn incoming_maximums_count_connections_from_every_node() {
+        let mut graph = two_slot_graph();
+        create_connection(
+            &mut graph,
+            slot_connection("p", CHILDREN_SLOT, "c", "primary"),
+        )
+        .unwrap();
+
+        let result = create_connection(
+            &mut graph,
+            slot_connection("p2", CHILDREN_SLOT, "c", "primary"),
+        );
+
+        assert!(rejection(result).contains("max incoming connections"));
+        assert_eq!(graph.incoming_connections("c", "primary").len(), 1);
+    }
+
+    #[test]
+    fn incoming_slots_can_limit_the_source_slots() {
+        let mut graph = two_slot_graph();
+        let result = create_connection(&mut graph, slot_connection("p", "extras", "c", "primary"));
+        assert!(rejection(result).contains("from this slot"));
+    }
+
+    #[test]
+    fn incoming_slots_can_limit_the_source_templates() {
+        let mut graph = two_slot_graph();
+        let child = graph.node_templates.get_mut(CHILD).unwrap();
+        child.slot_templates[1].allowed_connections = vec!["Nobody".to_string()];
+
+        let result = create_connection(&mut graph, slot_connection("p", "extras", "c", "spare"));
+
+        assert!(rejection(result).contains("from this node template"));
+    }
+
+    #[test]
+    fn enum_field_types_need_their_options() {
+        let options = vec!["red".to_string(), "blue".to_string()];
+
+        assert_eq!(
+            FieldType::from_name("select", Some(options.clone())).unwrap(),
+            FieldType::Enum { options }
+        );
+        assert!(matches!(
+            FieldType::from_name("enum", None),
+            Err(GraphError::InvalidFieldType { .. })
+        ));
+        assert!(FieldType::from_name("Enum", Some(vec![])).is_err());
     }
 }
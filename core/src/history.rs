@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+
+use crate::{
+    events::{EventSystem, SystemEvent},
+    graph::{Connection, Graph, GraphCommand, NodeInstance},
+};
+
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+// A primitive change to the graph. Undo/redo is expressed in these rather than in
+// GraphCommands so that restoring state never trips over the permission checks a command
+// performs (e.g. re-inserting a node whose template has can_create = false).
+#[derive(Debug, Clone)]
+pub enum HistoryOp {
+    InsertNode(NodeInstance),
+    RemoveNode(String),
+    InsertConnection(Connection),
+    RemoveConnection(Connection),
+    SetFieldValue {
+        node_id: String,
+        field_template_id: String,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub command: GraphCommand,
+    pub forward: Vec<HistoryOp>,
+    pub inverse: Vec<HistoryOp>,
+}
+
+impl HistoryEntry {
+    /// Builds an entry from the node instances before and after `command` ran.
+    /// A removed node is captured whole (including its outgoing connections), and the
+    /// connections other nodes had pointing at it are captured as separate ops so they
+    /// can be reattached once the node is back.
+    pub fn from_diff(
+        command: GraphCommand,
+        before: &HashMap<String, NodeInstance>,
+        after: &HashMap<String, NodeInstance>,
+    ) -> Self {
+        let (forward, inverse) = diff_ops(before, after);
+        Self {
+            command,
+            forward,
+            inverse,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forward.is_empty() && self.inverse.is_empty()
+    }
+}
+
+// The ops that turn `before` into `after`, and the ones that turn it back
+pub(crate) fn diff_ops(
+    before: &HashMap<String, NodeInstance>,
+    after: &HashMap<String, NodeInstance>,
+) -> (Vec<HistoryOp>, Vec<HistoryOp>) {
+    let mut removed_nodes = vec![];
+    let mut added_nodes = vec![];
+    let mut removed_connections = vec![];
+    let mut added_connections = vec![];
+    let mut old_field_values = vec![];
+    let mut new_field_values = vec![];
+
+    for (node_id, old_instance) in before {
+        let Some(new_instance) = after.get(node_id) else {
+            removed_nodes.push(old_instance.clone());
+            continue;
+        };
+        let old_connections = outgoing_connections(old_instance);
+        let new_connections = outgoing_connections(new_instance);
+        removed_connections.extend(
+            old_connections
+                .iter()
+                .filter(|conn| !new_connections.contains(conn))
+                .map(|conn| (*conn).clone()),
+        );
+        added_connections.extend(
+            new_connections
+                .iter()
+                .filter(|conn| !old_connections.contains(conn))
+                .map(|conn| (*conn).clone()),
+        );
+        for new_field in &new_instance.fields {
+            let old_field = old_instance
+                .fields
+                .iter()
+                .find(|f| f.field_template_id == new_field.field_template_id);
+            if let Some(old_field) = old_field {
+                if old_field.value != new_field.value {
+                    old_field_values.push(HistoryOp::SetFieldValue {
+                        node_id: node_id.clone(),
+                        field_template_id: old_field.field_template_id.clone(),
+                        value: old_field.value.clone(),
+                    });
+                    new_field_values.push(HistoryOp::SetFieldValue {
+                        node_id: node_id.clone(),
+                        field_template_id: new_field.field_template_id.clone(),
+                        value: new_field.value.clone(),
+                    });
+                }
+            }
+        }
+    }
+    for (node_id, new_instance) in after {
+        if !before.contains_key(node_id) {
+            added_nodes.push(new_instance.clone());
+        }
+    }
+
+    // Connections are detached before nodes go away and attached after nodes come back,
+    // in both directions.
+    let mut forward = vec![];
+    forward.extend(
+        removed_connections
+            .iter()
+            .cloned()
+            .map(HistoryOp::RemoveConnection),
+    );
+    forward.extend(
+        removed_nodes
+            .iter()
+            .map(|node| HistoryOp::RemoveNode(node.instance_id.clone())),
+    );
+    forward.extend(added_nodes.iter().cloned().map(HistoryOp::InsertNode));
+    forward.extend(
+        added_connections
+            .iter()
+            .cloned()
+            .map(HistoryOp::InsertConnection),
+    );
+    forward.extend(new_field_values);
+
+    let mut inverse = vec![];
+    inverse.extend(
+        added_connections
+            .into_iter()
+            .map(HistoryOp::RemoveConnection),
+    );
+    inverse.extend(
+        added_nodes
+            .into_iter()
+            .map(|node| HistoryOp::RemoveNode(node.instance_id)),
+    );
+    inverse.extend(removed_nodes.into_iter().map(HistoryOp::InsertNode));
+    inverse.extend(
+        removed_connections
+            .into_iter()
+            .map(HistoryOp::InsertConnection),
+    );
+    inverse.extend(old_field_values);
+
+    (forward, inverse)
+}
+
+fn outgoing_connections(instance: &NodeInstance) -> Vec<&Connection> {
+    instance
+        .slots
+        .iter()
+        .flat_map(|slot| slot.connections.iter())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    limit: usize,
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_HISTORY_LIMIT)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            limit,
+        }
+    }
+
+    // Recording a new entry invalidates anything that was undone
+    pub fn record(&mut self, entry: HistoryEntry) {
+        self.undo_stack.push(entry);
+        if self.undo_stack.len() > self.limit {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+impl Graph {
+    /// Reverts the most recent command. Returns false if there was nothing to undo, or if
+    /// the sync adapter rejected it.
+    pub fn undo(&mut self, events: &EventSystem) -> bool {
+        let Some(entry) = self.history.undo_stack.pop() else {
+            return false;
+        };
+        for op in &entry.inverse {
+            self.apply_history_op(op);
+        }
+        if self.sync_edit(events).is_err() {
+            self.history.undo_stack.push(entry);
+            return false;
+        }
+        let command = entry.command.clone();
+        self.history.redo_stack.push(entry);
+        events.emit(SystemEvent::CommandUndone(command));
+        self.emit_history_changed(events);
+        self.refresh_session(true, events);
+        true
+    }
+
+    /// Re-applies the most recently undone command. Returns false if there was nothing to redo,
+    /// or if the sync adapter rejected it.
+    pub fn redo(&mut self, events: &EventSystem) -> bool {
+        let Some(entry) = self.history.redo_stack.pop() else {
+            return false;
+        };
+        for op in &entry.forward {
+            self.apply_history_op(op);
+        }
+        if self.sync_edit(events).is_err() {
+            self.history.redo_stack.push(entry);
+            return false;
+        }
+        let command = entry.command.clone();
+        self.history.undo_stack.push(entry);
+        events.emit(SystemEvent::CommandRedone(command));
+        self.emit_history_changed(events);
+        self.refresh_session(true, events);
+        true
+    }
+
+    // For hosts that swap the whole graph out (e.g. loading a document)
+    pub fn emit_history_changed(&self, events: &EventSystem) {
+        events.emit(SystemEvent::HistoryChanged {
+            can_undo: self.history.can_undo(),
+            can_redo: self.history.can_redo(),
+        });
+    }
+
+    fn apply_history_op(&mut self, op: &HistoryOp) {
+        match op {
+            HistoryOp::InsertNode(instance) => {
+                self.node_instances
+                    .insert(instance.instance_id.clone(), instance.clone());
+            }
+            HistoryOp::RemoveNode(node_id) => {
+                self.node_instances.remove(node_id);
+            }
+            HistoryOp::InsertConnection(connection) => {
+                if let Some(slot) = self
+                    .node_instances
+                    .get_mut(&connection.host_node_id)
+                    .and_then(|instance| {
+                        instance
+                            .slots
+                            .iter_mut()
+                            .find(|s| s.slot_template_id == connection.host_slot_template_id)
+                    })
+                {
+                    if !slot.connections.contains(connection) {
+                        slot.connections.push(connection.clone());
+                    }
+                }
+            }
+            HistoryOp::RemoveConnection(connection) => {
+                if let Some(slot) = self
+                    .node_instances
+                    .get_mut(&connection.host_node_id)
+                    .and_then(|instance| {
+                        instance
+                            .slots
+                            .iter_mut()
+                            .find(|s| s.slot_template_id == connection.host_slot_template_id)
+                    })
+                {
+                    slot.connections.retain(|c| {
+                        !(c.target_node_id == connection.target_node_id
+                            && c.target_slot_template_id == connection.target_slot_template_id)
+                    });
+                }
+            }
+            HistoryOp::SetFieldValue {
+                node_id,
+                field_template_id,
+                value,
+            } => {
+                if let Some(field) = self.node_instances.get_mut(node_id).and_then(|instance| {
+                    instance
+                        .fields
+                        .iter_mut()
+                        .find(|f| f.field_template_id == *field_template_id)
+                }) {
+                    field.value = value.clone();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    // A parent with two children; the second child is also connected from a second parent
+    fn family() -> Graph {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, PARENT, "p2");
+        add_node(&mut graph, CHILD, "c");
+        add_node(&mut graph, CHILD, "c2");
+        connect(&mut graph, "p", "c");
+        connect(&mut graph, "p", "c2");
+        connect(&mut graph, "p2", "c2");
+        graph
+    }
+
+    fn targets(graph: &Graph, node_id: &str) -> Vec<String> {
+        let mut targets = graph
+            .get_node_connections(node_id)
+            .into_iter()
+            .map(|conn| conn.target_node_id)
+            .collect::<Vec<_>>();
+        targets.sort();
+        targets
+    }
+
+    #[test]
+    fn undoing_a_node_deletion_reattaches_its_incoming_connections() {
+        let mut graph = family();
+        let events = EventSystem::new();
+        graph
+            .execute_command(GraphCommand::DeleteNode("c2".to_string()), &events)
+            .unwrap();
+        assert_eq!(targets(&graph, "p"), vec!["c"]);
+        assert!(targets(&graph, "p2").is_empty());
+
+        assert!(graph.undo(&events));
+
+        assert!(graph.node_instances.contains_key("c2"));
+        assert_eq!(targets(&graph, "p"), vec!["c", "c2"]);
+        assert_eq!(targets(&graph, "p2"), vec!["c2"]);
+
+        assert!(graph.redo(&events));
+
+        assert!(!graph.node_instances.contains_key("c2"));
+        assert_eq!(targets(&graph, "p"), vec!["c"]);
+        assert!(targets(&graph, "p2").is_empty());
+    }
+
+    #[test]
+    fn undoing_a_node_deletion_restores_its_outgoing_connections() {
+        let mut graph = family();
+        let events = EventSystem::new();
+        graph
+            .execute_command(GraphCommand::DeleteNode("p".to_string()), &events)
+            .unwrap();
+
+        assert!(graph.undo(&events));
+
+        assert_eq!(targets(&graph, "p"), vec!["c", "c2"]);
+    }
+
+    #[test]
+    fn a_new_command_clears_what_was_undone() {
+        let mut graph = family();
+        let events = EventSystem::new();
+        graph
+            .execute_command(GraphCommand::DeleteNode("c".to_string()), &events)
+            .unwrap();
+        assert!(graph.undo(&events));
+        assert!(graph.history.can_redo());
+
+        add_node(&mut graph, CHILD, "c3");
+
+        assert!(!graph.history.can_redo());
+        assert!(!graph.redo(&events));
+    }
+
+    #[test]
+    fn failed_commands_are_not_recorded() {
+        let mut graph = sample_graph();
+        let (events, names) = record_events();
+
+        let result =
+            graph.execute_command(GraphCommand::DeleteNode("missing".to_string()), &events);
+
+        assert!(result.is_err());
+        assert!(!graph.history.can_undo());
+        assert_eq!(*names.lock().unwrap(), vec!["CommandFailed"]);
+    }
+
+    #[test]
+    fn undo_and_redo_announce_themselves() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        let (events, names) = record_events();
+
+        assert!(graph.undo(&events));
+        assert!(graph.redo(&events));
+
+        assert_eq!(
+            *names.lock().unwrap(),
+            vec![
+                "CommandUndone",
+                "HistoryChanged",
+                "CommandRedone",
+                "HistoryChanged"
+            ]
+        );
+    }
+
+    #[test]
+    fn the_oldest_entries_are_dropped_past_the_limit() {
+        let mut graph = sample_graph();
+        graph.history = CommandHistory::with_limit(2);
+        for id in ["a", "b", "c"] {
+            add_node(&mut graph, CHILD, id);
+        }
+        let events = EventSystem::new();
+
+        assert!(graph.undo(&events));
+        assert!(graph.undo(&events));
+        assert!(!graph.undo(&events));
+
+        assert_eq!(graph.node_instances.len(), 1);
+        assert!(graph.node_instances.contains_key("a"));
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+/// Something a key press can do on the canvas.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub enum KeyAction {
+    DeleteSelection,
+    // Abandons an in-progress connection or box selection, or closes the context menu
+    Cancel,
+    NudgeLeft,
+    NudgeRight,
+    NudgeUp,
+    NudgeDown,
+    ZoomIn,
+    ZoomOut,
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    PointerMode,
+    AddNodeMode,
+}
+
+/// A key as reported by `KeyboardEvent.key` (compared case-insensitively) plus modifiers.
+/// `ctrl` also matches the command key on macOS.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub struct KeyBinding {
+    pub key: String,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    pub fn new(key: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Whether the key press `pressed` triggers this binding.
+    pub fn matches(&self, pressed: &KeyBinding) -> bool {
+        // Shift is part of how symbols like "+" are typed, so it only counts for letters
+        // and named keys
+        let shift_matters =
+            self.key.chars().count() > 1 || self.key.chars().all(|c| c.is_alphanumeric());
+        self.key.eq_ignore_ascii_case(&pressed.key)
+            && self.ctrl == pressed.ctrl
+            && self.alt == pressed.alt
+            && (!shift_matters || self.shift == pressed.shift)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct Keymap {
+    // Ordered so that a chord bound to several actions always resolves the same way
+    pub bindings: BTreeMap<KeyAction, Vec<KeyBinding>>,
+}
+
+impl Keymap {
+    /// The action bound to `pressed`. If several are, the one declared first in
+    /// `KeyAction` wins.
+    pub fn action_for(&self, pressed: &KeyBinding) -> Option<KeyAction> {
+        self.bindings.iter().find_map(|(action, bindings)| {
+            bindings
+                .iter()
+                .any(|binding| binding.matches(pressed))
+                .then_some(*action)
+        })
+    }
+
+    /// Replaces the bindings of every action in `overrides`; other actions keep theirs,
+    /// minus any chord an override takes over. An action overridden with no bindings is
+    /// disabled.
+    pub fn rebind(&mut self, overrides: Keymap) {
+        for (action, bindings) in &mut self.bindings {
+            if overrides.bindings.contains_key(action) {
+                continue;
+            }
+            bindings.retain(|binding| {
+                !overrides
+                    .bindings
+                    .values()
+                    .flatten()
+                    .any(|taken| binding.matches(taken))
+            });
+        }
+        self.bindings.extend(overrides.bindings);
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = [
+            (
+                KeyAction::DeleteSelection,
+                vec![KeyBinding::new("Delete"), KeyBinding::new("Backspace")],
+            ),
+            (KeyAction::Cancel, vec![KeyBinding::new("Escape")]),
+            (KeyAction::NudgeLeft, vec![KeyBinding::new("ArrowLeft")]),
+            (KeyAction::NudgeRight, vec![KeyBinding::new("ArrowRight")]),
+            (KeyAction::NudgeUp, vec![KeyBinding::new("ArrowUp")]),
+            (KeyAction::NudgeDown, vec![KeyBinding::new("ArrowDown")]),
+            (
+                KeyAction::ZoomIn,
+                vec![KeyBinding::new("+"), KeyBinding::new("=")],
+            ),
+            (KeyAction::ZoomOut, vec![KeyBinding::new("-")]),
+            (KeyAction::SelectAll, vec![KeyBinding::new("a").with_ctrl()]),
+            (KeyAction::Copy, vec![KeyBinding::new("c").with_ctrl()]),
+            (KeyAction::Cut, vec![KeyBinding::new("x").with_ctrl()]),
+            (KeyAction::Paste, vec![KeyBinding::new("v").with_ctrl()]),
+            (KeyAction::Undo, vec![KeyBinding::new("z").with_ctrl()]),
+            (
+                KeyAction::Redo,
+                vec![
+                    KeyBinding::new("z").with_ctrl().with_shift(),
+                    KeyBinding::new("y").with_ctrl(),
+                ],
+            ),
+            (KeyAction::PointerMode, vec![KeyBinding::new("v")]),
+            (KeyAction::AddNodeMode, vec![KeyBinding::new("n")]),
+        ];
+        Self {
+            bindings: bindings.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pressed(key: &str) -> KeyBinding {
+        KeyBinding::new(key)
+    }
+
+    #[test]
+    fn default_bindings_resolve() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action_for(&pressed("Delete")),
+            Some(KeyAction::DeleteSelection)
+        );
+        assert_eq!(
+            keymap.action_for(&pressed("z").with_ctrl()),
+            Some(KeyAction::Undo)
+        );
+        assert_eq!(
+            keymap.action_for(&pressed("Z").with_ctrl().with_shift()),
+            Some(KeyAction::Redo)
+        );
+        assert_eq!(
+            keymap.action_for(&pressed("v")),
+            Some(KeyAction::PointerMode)
+        );
+        assert_eq!(keymap.action_for(&pressed("q")), None);
+    }
+
+    #[test]
+    fn shift_is_ignored_for_symbols() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action_for(&pressed("+").with_shift()),
+            Some(KeyAction::ZoomIn)
+        );
+    }
+
+    #[test]
+    fn rebinding_takes_the_chord_from_other_actions() {
+        let mut keymap = Keymap::default();
+        let overrides = Keymap {
+            bindings: BTreeMap::from([(KeyAction::AddNodeMode, vec![pressed("Delete")])]),
+        };
+        keymap.rebind(overrides);
+        assert_eq!(
+            keymap.action_for(&pressed("Delete")),
+            Some(KeyAction::AddNodeMode)
+        );
+        assert_eq!(keymap.action_for(&pressed("n")), None);
+        // Delete's other binding is untouched
+        assert_eq!(
+            keymap.action_for(&pressed("Backspace")),
+            Some(KeyAction::DeleteSelection)
+        );
+    }
+
+    #[test]
+    fn rebinding_to_nothing_disables_an_action() {
+        let mut keymap = Keymap::default();
+        keymap.rebind(Keymap {
+            bindings: BTreeMap::from([(KeyAction::Undo, vec![])]),
+        });
+        assert_eq!(keymap.action_for(&pressed("z").with_ctrl()), None);
+    }
+
+    #[test]
+    fn shared_chords_resolve_in_declaration_order() {
+        let keymap = Keymap {
+            bindings: BTreeMap::from([
+                (KeyAction::Redo, vec![pressed("r")]),
+                (KeyAction::Cancel, vec![pressed("r")]),
+            ]),
+        };
+        for _ in 0..10 {
+            assert_eq!(keymap.action_for(&pressed("r")), Some(KeyAction::Cancel));
+        }
+    }
+}
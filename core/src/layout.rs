@@ -1,9 +1,6 @@
 use std::collections::{HashMap, HashSet, VecDeque};
-use wasm_bindgen::JsCast;
 
-use web_sys::window;
-
-use crate::{graph::Graph, interaction::InteractionState};
+use crate::graph::Graph;
 
 #[derive(Clone, Debug)]
 pub struct NodePosition {
@@ -28,6 +25,33 @@ pub enum LayoutType {
     ForceDirected,
 }
 
+#[derive(Clone, Debug)]
+pub struct ViewTransform {
+    pub pan_x: f64,
+    pub pan_y: f64,
+    pub zoom: f64,
+}
+impl ViewTransform {
+    // Convert screen coordinates to graph coordinates
+    pub fn screen_to_graph(&self, x: f64, y: f64) -> (f64, f64) {
+        // First apply pan, then account for zoom
+        let center_x = x - self.pan_x;
+        let center_y = y - self.pan_y;
+
+        // Convert from screen to graph space (divide by zoom factor)
+        (center_x / self.zoom, center_y / self.zoom)
+    }
+
+    // Convert graph coordinates to screen coordinates
+    pub fn graph_to_screen(&self, x: f64, y: f64) -> (f64, f64) {
+        // First apply zoom, then add pan
+        let zoomed_x = x * self.zoom;
+        let zoomed_y = y * self.zoom;
+
+        (zoomed_x + self.pan_x, zoomed_y + self.pan_y)
+    }
+}
+
 // A struct to represent each view's state
 #[derive(Clone, Debug)]
 pub struct ViewState {
@@ -55,7 +79,9 @@ impl ViewState {
 pub struct LayoutEngine {
     current_view_index: usize, // Which view is currently active (0, 1, or 2)
     views: Vec<ViewState>,     // The three different views
-    canvas_id: String,
+    // Size of the area the graph is shown in, used to scale and center layouts
+    viewport_width: f64,
+    viewport_height: f64,
     // Force simulation state
     force_simulation_active: bool,
     fixed_node_id: Option<String>,
@@ -65,7 +91,7 @@ pub struct LayoutEngine {
 }
 
 impl LayoutEngine {
-    pub fn new(canvas_ref_id: String) -> Self {
+    pub fn new(viewport_width: f64, viewport_height: f64) -> Self {
         // Create initial empty views - snapshots will be generated on first use
         let empty_snapshot = LayoutSnapshot {
             positions: HashMap::new(),
@@ -81,7 +107,8 @@ impl LayoutEngine {
         Self {
             current_view_index: 0, // Start with view 1 (ForceDirected)
             views,
-            canvas_id: canvas_ref_id,
+            viewport_width,
+            viewport_height,
             force_simulation_active: false,
             fixed_node_id: None,
             simulation_iteration: 0,
@@ -90,6 +117,12 @@ impl LayoutEngine {
         }
     }
 
+    // Called by the host whenever the drawing area is resized
+    pub fn set_viewport(&mut self, width: f64, height: f64) {
+        self.viewport_width = width;
+        self.viewport_height = height;
+    }
+
     pub fn switch_layout(&mut self, layout_type: LayoutType, graph: &mut Graph) {
         // Save current state of current view (keeping the current view index)
         self.save_current_view_state(graph);
@@ -128,7 +161,7 @@ impl LayoutEngine {
         &mut self,
         view_index: usize,
         graph: &mut Graph,
-        ix: &mut InteractionState,
+        view_transform: &mut ViewTransform,
     ) {
         if view_index >= self.views.len() {
             return; // Invalid view index
@@ -144,10 +177,10 @@ impl LayoutEngine {
         // Apply the view's state
         self.apply_snapshot(graph, &current_view.snapshot);
 
-        // Set interaction state from view
-        ix.view_transform.pan_x = current_view.pan_x;
-        ix.view_transform.pan_y = current_view.pan_y;
-        ix.view_transform.zoom = current_view.zoom;
+        // Set the host's transform from view
+        view_transform.pan_x = current_view.pan_x;
+        view_transform.pan_y = current_view.pan_y;
+        view_transform.zoom = current_view.zoom;
     }
 
     // Check if physics is enabled for the current view
@@ -162,7 +195,7 @@ impl LayoutEngine {
         current_view.physics_enabled
     }
 
-    pub fn reset_current_layout(&mut self, graph: &mut Graph, ix: &mut InteractionState) {
+    pub fn reset_current_layout(&mut self, graph: &mut Graph, view_transform: &mut ViewTransform) {
         // Regenerate the current layout snapshot based on the current view's layout type
         let layout_type = self.views[self.current_view_index].layout_type.clone();
         let new_snapshot = match layout_type {
@@ -181,15 +214,30 @@ impl LayoutEngine {
         current_view.pan_y = 0.0;
         current_view.zoom = 1.0;
 
-        // Reset interaction state
-        ix.view_transform.pan_x = 0.0;
-        ix.view_transform.pan_y = 0.0;
-        ix.view_transform.zoom = 1.0;
+        // Reset the host's transform
+        view_transform.pan_x = 0.0;
+        view_transform.pan_y = 0.0;
+        view_transform.zoom = 1.0;
 
         // Apply the new snapshot
         self.apply_snapshot(graph, &new_snapshot);
     }
 
+    // Places nodes that arrived without positions, e.g. a graph rebuilt from exported
+    // molecule_schema instances. The other layouts start from the current positions, so the
+    // hierarchical one is used whatever the current view is.
+    pub fn arrange_unplaced_graph(&mut self, graph: &mut Graph) {
+        let snapshot = self.generate_hierarchical_layout(graph);
+        self.apply_snapshot(graph, &snapshot);
+    }
+
+    // Drop the saved node positions of every view, e.g. after a different graph was loaded
+    pub fn clear_snapshots(&mut self) {
+        for view in &mut self.views {
+            view.snapshot.positions.clear();
+        }
+    }
+
     fn save_current_view_state(&mut self, graph: &Graph) {
         // Create a snapshot of current node positions
         let mut positions = HashMap::new();
@@ -278,18 +326,9 @@ impl LayoutEngine {
         // STEP 6: Optimize horizontal positions for better distribution
         self.optimize_horizontal_positions(&mut nodes_by_level, &temp_dependencies, &level_keys);
 
-        // Get canvas dimensions for layout scaling and centering
-        let canvas = window()
-            .unwrap()
-            .document()
-            .unwrap()
-            .get_element_by_id(&self.canvas_id)
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap();
-
-        let canvas_width = canvas.get_bounding_client_rect().width();
-        let canvas_height = canvas.get_bounding_client_rect().height();
+        // Use the viewport dimensions for layout scaling and centering
+        let canvas_width = self.viewport_width;
+        let canvas_height = self.viewport_height;
 
         // Calculate bounds of the current layout
         let mut min_x = f64::MAX;
@@ -656,17 +695,8 @@ impl LayoutEngine {
             return;
         }
 
-        // Get canvas dimensions for simulation bounds
-        let canvas = window()
-            .unwrap()
-            .document()
-            .unwrap()
-            .get_element_by_id(&self.canvas_id)
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap();
-
-        let canvas_width = canvas.get_bounding_client_rect().width();
+        // Use the viewport dimensions for simulation bounds
+        let canvas_width = self.viewport_width;
 
         // Initialize simulation parameters
         self.force_simulation_active = true;
@@ -684,12 +714,12 @@ impl LayoutEngine {
         self.fixed_node_id = None;
     }
 
-    // Save view transform state from interaction to current view
-    pub fn save_view_transform(&mut self, ix: &InteractionState) {
+    // Save the host's view transform to current view
+    pub fn save_view_transform(&mut self, view_transform: &ViewTransform) {
         let current_view = &mut self.views[self.current_view_index];
-        current_view.pan_x = ix.view_transform.pan_x;
-        current_view.pan_y = ix.view_transform.pan_y;
-        current_view.zoom = ix.view_transform.zoom;
+        current_view.pan_x = view_transform.pan_x;
+        current_view.pan_y = view_transform.pan_y;
+        current_view.zoom = view_transform.zoom;
     }
 
     // Build graph of node connections for force calculation
@@ -719,28 +749,20 @@ impl LayoutEngine {
         }
     }
 
-    // Run a single iteration of the force simulation while a node is being dragged
-    pub fn run_simulation_step(&mut self, graph: &mut Graph) {
+    // Run a single iteration of the force simulation while a node is being dragged.
+    // Returns false when the simulation is not running, so nothing moved.
+    pub fn run_simulation_step(&mut self, graph: &mut Graph) -> bool {
         // Check both that simulation is active and physics is enabled for the current view
         if !self.force_simulation_active
             || self.fixed_node_id.is_none()
             || !self.views[self.current_view_index].physics_enabled
         {
-            return;
+            return false;
         }
 
-        // Get canvas dimensions for simulation bounds
-        let canvas = window()
-            .unwrap()
-            .document()
-            .unwrap()
-            .get_element_by_id(&self.canvas_id)
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap();
-
-        let canvas_width = canvas.get_bounding_client_rect().width();
-        let canvas_height = canvas.get_bounding_client_rect().height();
+        // Use the viewport dimensions for simulation bounds
+        let canvas_width = self.viewport_width;
+        let canvas_height = self.viewport_height;
 
         // Extract current positions from graph
         let mut positions: HashMap<String, NodePosition> = HashMap::new();
@@ -882,6 +904,7 @@ impl LayoutEngine {
         if self.simulation_iteration > 10000 {
             self.force_simulation_active = false;
         }
+        true
     }
 
     pub fn generate_force_directed_layout(&self, graph: &Graph) -> LayoutSnapshot {
@@ -898,18 +921,9 @@ impl LayoutEngine {
             );
         }
 
-        // Get canvas dimensions for positioning
-        let canvas = window()
-            .unwrap()
-            .document()
-            .unwrap()
-            .get_element_by_id(&self.canvas_id)
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap();
-
-        let canvas_width = canvas.get_bounding_client_rect().width();
-        let canvas_height = canvas.get_bounding_client_rect().height();
+        // Use the viewport dimensions for positioning
+        let canvas_width = self.viewport_width;
+        let canvas_height = self.viewport_height;
 
         // Create a map of node connections for force calculation
         let mut connections: HashMap<String, Vec<String>> = HashMap::new();
@@ -1052,3 +1066,85 @@ impl LayoutEngine {
         LayoutSnapshot { positions }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn family() -> Graph {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, CHILD, "c");
+        add_node(&mut graph, CHILD, "c2");
+        connect(&mut graph, "p", "c");
+        connect(&mut graph, "p", "c2");
+        graph
+    }
+
+    fn position(graph: &Graph, node_id: &str) -> (f64, f64) {
+        let node = &graph.node_instances[node_id];
+        (node.x, node.y)
+    }
+
+    #[test]
+    fn view_transforms_convert_both_ways() {
+        let transform = ViewTransform {
+            pan_x: 20.0,
+            pan_y: -10.0,
+            zoom: 2.0,
+        };
+        assert_eq!(transform.graph_to_screen(5.0, 5.0), (30.0, 0.0));
+        assert_eq!(transform.screen_to_graph(30.0, 0.0), (5.0, 5.0));
+    }
+
+    #[test]
+    fn unplaced_graphs_are_laid_out_in_levels() {
+        let mut graph = family();
+        LayoutEngine::new(800.0, 600.0).arrange_unplaced_graph(&mut graph);
+
+        let (parent_x, parent_y) = position(&graph, "p");
+        let (child_x, child_y) = position(&graph, "c");
+        let (other_x, other_y) = position(&graph, "c2");
+        // Levels run left to right, with siblings stacked in a column
+        assert!(parent_x < child_x);
+        assert_eq!(child_x, other_x);
+        assert_ne!(child_y, other_y);
+        assert!(parent_y.is_finite());
+    }
+
+    #[test]
+    fn each_view_keeps_its_own_positions() {
+        let mut graph = family();
+        let mut engine = LayoutEngine::new(800.0, 600.0);
+        let mut transform = ViewTransform {
+            pan_x: 0.0,
+            pan_y: 0.0,
+            zoom: 1.0,
+        };
+        engine.switch_to_view(2, &mut graph, &mut transform);
+        graph.node_instances.get_mut("p").unwrap().x = 123.0;
+
+        engine.switch_to_view(1, &mut graph, &mut transform);
+        engine.reset_current_layout(&mut graph, &mut transform);
+        assert_ne!(position(&graph, "p").0, 123.0);
+
+        engine.switch_to_view(2, &mut graph, &mut transform);
+        assert_eq!(position(&graph, "p").0, 123.0);
+    }
+
+    #[test]
+    fn the_simulation_only_runs_while_a_node_is_dragged() {
+        let mut graph = family();
+        let mut engine = LayoutEngine::new(800.0, 600.0);
+        assert!(!engine.run_simulation_step(&mut graph));
+
+        engine.start_force_simulation(&graph, "p");
+        let dragged = position(&graph, "p");
+        assert!(engine.run_simulation_step(&mut graph));
+        assert_eq!(position(&graph, "p"), dragged);
+
+        engine.stop_force_simulation();
+        assert!(!engine.run_simulation_step(&mut graph));
+    }
+}
@@ -0,0 +1,51 @@
+//! The graph model, command execution, history, persistence and layout algorithms behind
+//! `graph_canvas`, without any dependency on the DOM. The canvas crate is a thin adapter on
+//! top of this one; the same code can run natively for tests, servers or CLI tools.
+
+pub mod clipboard;
+pub mod common;
+pub mod config;
+pub mod document;
+pub mod errors;
+pub mod events;
+pub mod graph;
+pub mod history;
+pub mod keymap;
+pub mod layout;
+pub mod molecule_schema;
+pub mod session;
+pub mod sync;
+#[cfg(test)]
+mod test_support;
+pub mod validation;
+
+pub use clipboard::ClipboardPayload;
+pub use config::GraphCanvasConfig;
+pub use config::InitialConnection;
+pub use config::InitialFieldValue;
+pub use config::InitialNode;
+pub use config::TemplateGroup;
+pub use config::TemplateIdentifier;
+pub use document::GraphDocument;
+pub use document::CURRENT_DOCUMENT_VERSION;
+pub use errors::{GraphError, GraphResult};
+pub use events::{ContextMenuTarget, EventSystem, SystemEvent};
+pub use graph::Connection;
+pub use graph::FieldConstraints;
+pub use graph::FieldTemplate;
+pub use graph::FieldType;
+pub use graph::Graph;
+pub use graph::GraphCommand;
+pub use graph::NodeInstance;
+pub use graph::NodeTemplate;
+pub use graph::SlotInstance;
+pub use graph::SlotPosition;
+pub use graph::SlotTemplate;
+pub use graph::SlotType;
+pub use history::CommandHistory;
+pub use keymap::{KeyAction, KeyBinding, Keymap};
+pub use layout::{LayoutEngine, LayoutType, ViewTransform};
+pub use molecule_schema::{import_molecule_schema, ImportedSchema};
+pub use session::{EditingSession, PendingConstraint, PendingConstraintKind, SessionState};
+pub use sync::{GraphSync, GraphSyncAdapter, InMemorySyncAdapter, SyncMode};
+pub use validation::{ValidationCode, ValidationIssue, ValidationReport, ValidationSeverity};
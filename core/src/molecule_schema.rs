@@ -0,0 +1,913 @@
+//! Imports node templates from a [molecule_schema](https://github.com/reedwoodruff/molecule_schema)
+//! constraint schema serialized as JSON, so a schema is defined once and shared with the canvas.
+//!
+//! Every library operative becomes a node template and every library template becomes a
+//! template group holding its operatives. An operative's slots become outgoing slots that accept
+//! the operatives the slot names (directly or through traits), and its fields become fields of
+//! the matching primitive type. Constructs the canvas cannot represent are reported rather than
+//! dropped: operative inheritance, pre-slotted instances, locked fields, "or zero" slot bounds
+//! and non-primitive field types.
+//!
+//! Going the other way, a finished graph is exported in molecule_schema's own format: the
+//! constraint schema comes back with its `instance_library` holding one library instance per
+//! node, naming its operative, with its field values and the instances slotted into each slot.
+//! Such a document loads back onto a graph holding the schema's templates.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+
+use crate::{
+    config::{GraphCanvasConfig, TemplateGroup},
+    errors::{GraphError, GraphResult},
+    graph::{
+        Connection, FieldConstraints, FieldTemplate, FieldType, Graph, NodeInstance, NodeTemplate,
+        NodeTemplateInfo, SlotPosition, SlotTemplate, SlotType,
+    },
+};
+
+// molecule_schema identifies everything by a u128
+type Uid = u128;
+
+#[derive(Deserialize)]
+struct ConstraintSchema {
+    #[serde(default)]
+    template_library: BTreeMap<Uid, LibraryTemplate>,
+    #[serde(default)]
+    operative_library: BTreeMap<Uid, LibraryOperative>,
+    #[serde(default)]
+    instance_library: BTreeMap<Uid, LibraryInstance>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Tag {
+    id: Uid,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct LibraryTemplate {
+    tag: Tag,
+    #[serde(default)]
+    field_constraints: BTreeMap<Uid, FieldConstraint>,
+    #[serde(default)]
+    operative_slots: BTreeMap<Uid, OperativeSlot>,
+    #[serde(default)]
+    trait_impls: HashMap<Uid, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct FieldConstraint {
+    tag: Tag,
+    // A variant of the schema's primitive type enum, e.g. "String" or {"Option": "I32"}
+    value_type: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct OperativeSlot {
+    tag: Tag,
+    operative_descriptor: OperativeVariants,
+    bounds: SlotBounds,
+}
+
+#[derive(Deserialize)]
+enum OperativeVariants {
+    LibraryOperative(Uid),
+    TraitOperative(TraitOperative),
+}
+
+#[derive(Deserialize)]
+struct TraitOperative {
+    trait_ids: Vec<Uid>,
+}
+
+#[derive(Deserialize)]
+enum SlotBounds {
+    Single,
+    LowerBound(usize),
+    UpperBound(usize),
+    Range(usize, usize),
+    LowerBoundOrZero(usize),
+    RangeOrZero(usize, usize),
+}
+
+#[derive(Deserialize)]
+struct LibraryOperative {
+    tag: Tag,
+    template_id: Uid,
+    #[serde(default)]
+    parent_operative_id: Option<Uid>,
+    #[serde(default)]
+    slotted_instances: HashMap<Uid, serde_json::Value>,
+    #[serde(default)]
+    locked_fields: HashMap<Uid, serde_json::Value>,
+    #[serde(default)]
+    trait_impls: HashMap<Uid, serde_json::Value>,
+}
+
+// A node of a finished graph, as molecule_schema serializes it in `instance_library`
+#[derive(Serialize, Deserialize)]
+struct LibraryInstance {
+    tag: Tag,
+    template_id: Uid,
+    parent_operative_id: Uid,
+    #[serde(default)]
+    slotted_instances: BTreeMap<Uid, SlottedInstances>,
+    #[serde(default)]
+    data: BTreeMap<Uid, FulfilledFieldConstraint>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SlottedInstances {
+    operative_slot_id: Uid,
+    fulfilling_instance_ids: Vec<Uid>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FulfilledFieldConstraint {
+    constraint_tag: Tag,
+    value_type: serde_json::Value,
+    // Tagged with its primitive type, e.g. {"U8": 30}
+    value: serde_json::Value,
+}
+
+/// Templates and groups read from a molecule_schema constraint schema.
+#[derive(Debug, Clone, Default)]
+pub struct ImportedSchema {
+    pub node_templates: Vec<NodeTemplate>,
+    pub template_groups: Vec<TemplateGroup>,
+}
+
+/// Converts a molecule_schema constraint schema (as JSON) into node templates and template
+/// groups. Ids become UUID strings, the same way `TemplateIdentifier::from(u128)` does.
+pub fn import_molecule_schema(json: &str) -> GraphResult<ImportedSchema> {
+    // Library instances are a finished graph, read by `Graph::with_molecule_instances`
+    let schema = parse_schema(json)?;
+    let mut unsupported = vec![];
+
+    // Slots name the nodes they accept by template name, so names must not collide
+    let mut names = HashSet::new();
+    for operative in schema.operative_library.values() {
+        if !names.insert(operative.tag.name.as_str()) {
+            return Err(GraphError::InvalidSchema(format!(
+                "More than one operative is named {}",
+                operative.tag.name
+            )));
+        }
+    }
+
+    let mut node_templates = vec![];
+    for operative in schema.operative_library.values() {
+        let Some(template) = schema.template_library.get(&operative.template_id) else {
+            return Err(GraphError::InvalidSchema(format!(
+                "Operative {} uses unknown template {}",
+                operative.tag.name, operative.template_id
+            )));
+        };
+        if operative.parent_operative_id.is_some() {
+            unsupported.push(unsupported_construct(
+                &operative.tag.name,
+                "operatives derived from other operatives are not supported",
+            ));
+        }
+        if !operative.slotted_instances.is_empty() {
+            unsupported.push(unsupported_construct(
+                &operative.tag.name,
+                "pre-slotted instances are not supported",
+            ));
+        }
+        if !operative.locked_fields.is_empty() {
+            unsupported.push(unsupported_construct(
+                &operative.tag.name,
+                "locked fields are not supported",
+            ));
+        }
+
+        let mut node_template = NodeTemplate::new(&operative.tag.name);
+        node_template.template_id = uid_string(operative.tag.id);
+        for field in template.field_constraints.values() {
+            match field_template(field) {
+                Ok(field_template) => node_template.field_templates.push(field_template),
+                Err(reason) => unsupported.push(unsupported_construct(
+                    &format!("{}.{}", operative.tag.name, field.tag.name),
+                    &reason,
+                )),
+            }
+        }
+        for slot in template.operative_slots.values() {
+            let item = format!("{}.{}", operative.tag.name, slot.tag.name);
+            match slot_template(slot, &item, &schema) {
+                Ok(slot_template) => node_template.slot_templates.push(slot_template),
+                Err(err @ GraphError::UnsupportedSchemaConstruct { .. }) => unsupported.push(err),
+                Err(err) => return Err(err),
+            }
+        }
+        node_templates.push(node_template);
+    }
+
+    match unsupported.len() {
+        0 => {}
+        1 => return Err(unsupported.remove(0)),
+        _ => return Err(GraphError::ListOfErrors(unsupported)),
+    }
+
+    let template_groups = schema
+        .template_library
+        .values()
+        .map(|template| {
+            let mut group = TemplateGroup::new(&uid_string(template.tag.id), &template.tag.name);
+            group.templates = schema
+                .operative_library
+                .values()
+                .filter(|operative| operative.template_id == template.tag.id)
+                .map(|operative| uid_string(operative.tag.id))
+                .collect();
+            group
+        })
+        .filter(|group| !group.templates.is_empty())
+        .collect();
+
+    Ok(ImportedSchema {
+        node_templates,
+        template_groups,
+    })
+}
+
+impl GraphCanvasConfig {
+    /// Adds the templates and groups of a molecule_schema constraint schema to this config, and
+    /// keeps the schema for exporting finished graphs. Nothing is added if any part of the
+    /// schema cannot be imported.
+    pub fn import_molecule_schema(&mut self, json: &str) -> GraphResult<()> {
+        let imported = import_molecule_schema(json)?;
+        self.node_templates.extend(imported.node_templates);
+        self.template_groups.extend(imported.template_groups);
+        self.molecule_schema = Some(json.to_string());
+        Ok(())
+    }
+}
+
+impl Graph {
+    /// Exports the graph in molecule_schema's format. `schema` is the constraint schema the
+    /// templates were imported from, as JSON; it is returned with its `instance_library`
+    /// replaced by one library instance per node. The graph has to pass `check_conformity`,
+    /// and every node id has to be a UUID, which becomes the instance id.
+    pub fn to_molecule_instances(&self, schema: &str) -> GraphResult<String> {
+        self.check_conformity()?;
+        let parsed = parse_schema(schema)?;
+        let not_from_schema = |item: &str| {
+            unsupported_construct(item, "it was not imported from this molecule_schema schema")
+        };
+
+        let mut instance_library = BTreeMap::new();
+        for (node_id, node) in &self.node_instances {
+            let template = self
+                .node_templates
+                .get(&node.template_id)
+                .ok_or_else(|| GraphError::TemplateNotFound(node.template_id.clone()))?;
+            let (operative_id, operative) = parse_uid(&template.template_id)
+                .and_then(|id| parsed.operative_library.get(&id).map(|op| (id, op)))
+                .ok_or_else(|| not_from_schema(&template.name))?;
+            let library_template = parsed
+                .template_library
+                .get(&operative.template_id)
+                .ok_or_else(|| {
+                    GraphError::InvalidSchema(format!(
+                        "Operative {} uses unknown template {}",
+                        operative.tag.name, operative.template_id
+                    ))
+                })?;
+
+            let mut data = BTreeMap::new();
+            for field in &node.fields {
+                let Some(field_template) = template
+                    .field_templates
+                    .iter()
+                    .find(|ft| ft.id == field.field_template_id)
+                else {
+                    continue;
+                };
+                let item = format!("{}.{}", template.name, field_template.name);
+                let (field_id, constraint) = parse_uid(&field_template.id)
+                    .and_then(|id| library_template.field_constraints.get(&id).map(|c| (id, c)))
+                    .ok_or_else(|| not_from_schema(&item))?;
+                let type_name = constraint
+                    .value_type
+                    .as_str()
+                    .ok_or_else(|| not_from_schema(&item))?;
+                let mut value = serde_json::Map::new();
+                value.insert(
+                    type_name.to_string(),
+                    field_json_value(&field_template.field_type, &field.value),
+                );
+                data.insert(
+                    field_id,
+                    FulfilledFieldConstraint {
+                        constraint_tag: Tag {
+                            id: field_id,
+                            name: constraint.tag.name.clone(),
+                        },
+                        value_type: constraint.value_type.clone(),
+                        value: serde_json::Value::Object(value),
+                    },
+                );
+            }
+
+            let mut slotted_instances = BTreeMap::new();
+            for slot in &node.slots {
+                let Some(slot_template) = template.get_slot_template(&slot.slot_template_id) else {
+                    continue;
+                };
+                // Incoming slots are the canvas's own; schemas only describe outgoing ones
+                if slot_template.slot_type != SlotType::Outgoing {
+                    continue;
+                }
+                let slot_id = parse_uid(&slot_template.id)
+                    .filter(|id| library_template.operative_slots.contains_key(id))
+                    .ok_or_else(|| {
+                        not_from_schema(&format!("{}.{}", template.name, slot_template.name))
+                    })?;
+                let mut fulfilling_instance_ids = vec![];
+                for connection in &slot.connections {
+                    let target = self
+                        .node_instances
+                        .get(&connection.target_node_id)
+                        .ok_or_else(|| {
+                            GraphError::NodeNotFound(connection.target_node_id.clone())
+                        })?;
+                    let target_template = self
+                        .node_templates
+                        .get(&target.template_id)
+                        .ok_or_else(|| GraphError::TemplateNotFound(target.template_id.clone()))?;
+                    // Loading connects to this slot again, so it has to be the only choice
+                    instance_slot(target_template)?;
+                    fulfilling_instance_ids.push(instance_uid(&connection.target_node_id)?);
+                }
+                slotted_instances.insert(
+                    slot_id,
+                    SlottedInstances {
+                        operative_slot_id: slot_id,
+                        fulfilling_instance_ids,
+                    },
+                );
+            }
+
+            let instance_id = instance_uid(node_id)?;
+            instance_library.insert(
+                instance_id,
+                LibraryInstance {
+                    tag: Tag {
+                        id: instance_id,
+                        name: operative.tag.name.clone(),
+                    },
+                    template_id: operative.template_id,
+                    parent_operative_id: operative_id,
+                    slotted_instances,
+                    data,
+                },
+            );
+        }
+        with_instance_library(schema, &instance_library)
+    }
+
+    /// A graph with this graph's templates holding the library instances of a constraint schema,
+    /// as exported by `to_molecule_instances`. Every node starts at the origin; lay the result
+    /// out with the layout engine.
+    pub fn with_molecule_instances(&self, schema: &str) -> GraphResult<Graph> {
+        let parsed = parse_schema(schema)?;
+        let mut graph = Graph::new();
+        graph.node_templates = self.node_templates.clone();
+
+        for (instance_id, instance) in &parsed.instance_library {
+            let template = graph
+                .node_templates
+                .get(&uid_string(instance.parent_operative_id))
+                .ok_or_else(|| {
+                    GraphError::InvalidSchema(format!(
+                        "Instance {} uses unknown operative {}",
+                        instance_id, instance.parent_operative_id
+                    ))
+                })?;
+            let node_id = uid_string(*instance_id);
+            let mut node = NodeInstance::new(template, node_id.clone(), 0.0, 0.0);
+
+            for (field_id, fulfilled) in &instance.data {
+                let field_template_id = uid_string(*field_id);
+                let field_template = template
+                    .field_templates
+                    .iter()
+                    .find(|ft| ft.id == field_template_id)
+                    .ok_or_else(|| {
+                        GraphError::InvalidSchema(format!(
+                            "Instance {} has field {} which {} does not declare",
+                            instance_id, field_id, template.name
+                        ))
+                    })?;
+                // Drop the primitive type tag, e.g. {"U8": 30} holds 30
+                let value = fulfilled
+                    .value
+                    .as_object()
+                    .and_then(|tagged| tagged.values().next())
+                    .unwrap_or(&fulfilled.value);
+                if let Some(field) = node
+                    .fields
+                    .iter_mut()
+                    .find(|field| field.field_template_id == field_template_id)
+                {
+                    field.value = field_string_value(&field_template.field_type, value);
+                }
+            }
+
+            for (slot_id, slotted) in &instance.slotted_instances {
+                let slot_template_id = uid_string(*slot_id);
+                let slot = node
+                    .slots
+                    .iter_mut()
+                    .find(|slot| slot.slot_template_id == slot_template_id)
+                    .ok_or_else(|| {
+                        GraphError::InvalidSchema(format!(
+                            "Instance {} has slot {} which {} does not declare",
+                            instance_id, slot_id, template.name
+                        ))
+                    })?;
+                for target_id in &slotted.fulfilling_instance_ids {
+                    let target_template = parsed
+                        .instance_library
+                        .get(target_id)
+                        .and_then(|target| {
+                            graph
+                                .node_templates
+                                .get(&uid_string(target.parent_operative_id))
+                        })
+                        .ok_or_else(|| {
+                            GraphError::InvalidSchema(format!(
+                                "Instance {} slots unknown instance {}",
+                                instance_id, target_id
+                            ))
+                        })?;
+                    slot.connections.push(Connection {
+                        can_delete: true,
+                        host_node_id: node_id.clone(),
+                        host_slot_template_id: slot_template_id.clone(),
+                        target_node_id: uid_string(*target_id),
+                        target_slot_template_id: instance_slot(target_template)?.to_string(),
+                    });
+                }
+            }
+            graph.node_instances.insert(node_id, node);
+        }
+        Ok(graph)
+    }
+}
+
+fn parse_schema(json: &str) -> GraphResult<ConstraintSchema> {
+    serde_json::from_str(json).map_err(|err| GraphError::SchemaParseFailed(err.to_string()))
+}
+
+// `schema` with its instance library replaced, keeping everything else as it was written
+fn with_instance_library(
+    schema: &str,
+    instance_library: &BTreeMap<Uid, LibraryInstance>,
+) -> GraphResult<String> {
+    let failed = |err: serde_json::Error| GraphError::SchemaParseFailed(err.to_string());
+    let mut document: BTreeMap<String, Box<RawValue>> =
+        serde_json::from_str(schema).map_err(failed)?;
+    let instances = serde_json::to_string(instance_library).map_err(failed)?;
+    document.insert(
+        "instance_library".to_string(),
+        RawValue::from_string(instances).map_err(failed)?,
+    );
+    serde_json::to_string(&document).map_err(failed)
+}
+
+// Node ids become instance ids, so only UUIDs can be exported
+fn instance_uid(node_id: &str) -> GraphResult<Uid> {
+    parse_uid(node_id).ok_or_else(|| {
+        unsupported_construct(
+            &format!("Node {}", node_id),
+            "only nodes with UUID ids can become library instances",
+        )
+    })
+}
+
+// The incoming slot that instances slotted into a node of this template connect to.
+// molecule_schema only records which instance is slotted, so there must be exactly one.
+fn instance_slot(template: &NodeTemplate) -> GraphResult<&str> {
+    let mut incoming = template.incoming_slot_templates();
+    match (incoming.next(), incoming.next()) {
+        (Some(slot), None) => Ok(&slot.id),
+        _ => Err(unsupported_construct(
+            &template.name,
+            "instances can only be slotted into templates with a single incoming slot",
+        )),
+    }
+}
+
+fn uid_string(id: Uid) -> String {
+    uuid::Uuid::from_u128(id).to_string()
+}
+
+// The schema id behind a template, field, slot or node id created by this module
+fn parse_uid(id: &str) -> Option<Uid> {
+    uuid::Uuid::parse_str(id).ok().map(|uuid| uuid.as_u128())
+}
+
+fn field_json_value(field_type: &FieldType, value: &str) -> serde_json::Value {
+    let parsed = match field_type {
+        FieldType::Boolean => value.parse::<bool>().ok().map(serde_json::Value::from),
+        FieldType::Integer => value.parse::<i64>().ok().map(serde_json::Value::from),
+        FieldType::Float { .. } => value
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(serde_json::Value::Number),
+        FieldType::Json => serde_json::from_str(value).ok(),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| serde_json::Value::String(value.to_string()))
+}
+
+fn field_string_value(field_type: &FieldType, value: &serde_json::Value) -> String {
+    match (field_type, value) {
+        // A JSON field holding a string must keep its quotes
+        (FieldType::Json, value) => value.to_string(),
+        (_, serde_json::Value::String(value)) => value.clone(),
+        (_, value) => value.to_string(),
+    }
+}
+
+fn unsupported_construct(item: &str, reason: &str) -> GraphError {
+    GraphError::UnsupportedSchemaConstruct {
+        item: item.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn field_template(field: &FieldConstraint) -> Result<FieldTemplate, String> {
+    let Some(type_name) = field.value_type.as_str() else {
+        return Err(format!(
+            "field type {} is not a primitive type",
+            field.value_type
+        ));
+    };
+    // Ranges of the fixed-size integer types, enforced through constraints
+    let integer_range = |min: f64, max: f64| FieldConstraints {
+        min: Some(min),
+        max: Some(max),
+        ..Default::default()
+    };
+    let (field_type, default_value, constraints) = match type_name {
+        "Bool" => (FieldType::Boolean, "false", FieldConstraints::default()),
+        "I8" => (FieldType::Integer, "0", integer_range(-128.0, 127.0)),
+        "I16" => (FieldType::Integer, "0", integer_range(-32768.0, 32767.0)),
+        "I32" => (
+            FieldType::Integer,
+            "0",
+            integer_range(i32::MIN.into(), i32::MAX.into()),
+        ),
+        "U8" => (FieldType::Integer, "0", integer_range(0.0, 255.0)),
+        "U16" => (FieldType::Integer, "0", integer_range(0.0, 65535.0)),
+        "U32" => (FieldType::Integer, "0", integer_range(0.0, u32::MAX.into())),
+        "I64" | "Isize" => (FieldType::Integer, "0", FieldConstraints::default()),
+        "U64" | "Usize" => (
+            FieldType::Integer,
+            "0",
+            FieldConstraints {
+                min: Some(0.0),
+                ..Default::default()
+            },
+        ),
+        "F32" | "F64" => (
+            FieldType::Float {
+                min: None,
+                max: None,
+                step: None,
+            },
+            "0",
+            FieldConstraints::default(),
+        ),
+        "String" => (FieldType::String, "", FieldConstraints::default()),
+        "Char" => (
+            FieldType::String,
+            "",
+            FieldConstraints {
+                max_length: Some(1),
+                ..Default::default()
+            },
+        ),
+        other => return Err(format!("field type {} has no canvas equivalent", other)),
+    };
+    Ok(FieldTemplate {
+        id: uid_string(field.tag.id),
+        name: field.tag.name.clone(),
+        field_type,
+        default_value: default_value.to_string(),
+        constraints,
+    })
+}
+
+// Fails with `InvalidSchema` for dangling references and `UnsupportedSchemaConstruct` for
+// bounds the canvas cannot express
+fn slot_template(
+    slot: &OperativeSlot,
+    item: &str,
+    schema: &ConstraintSchema,
+) -> GraphResult<SlotTemplate> {
+    let (min_connections, max_connections) = match slot.bounds {
+        SlotBounds::Single => (1, Some(1)),
+        SlotBounds::LowerBound(min) => (min, None),
+        SlotBounds::UpperBound(max) => (0, Some(max)),
+        SlotBounds::Range(min, max) => (min, Some(max)),
+        // Slots have a single lower bound, so "none or at least n" cannot be expressed
+        SlotBounds::LowerBoundOrZero(min) => {
+            return Err(unsupported_construct(
+                item,
+                &format!("slot bounds of zero or at least {} are not supported", min),
+            ))
+        }
+        SlotBounds::RangeOrZero(min, max) => {
+            return Err(unsupported_construct(
+                item,
+                &format!(
+                    "slot bounds of zero or {} to {} are not supported",
+                    min, max
+                ),
+            ))
+        }
+    };
+
+    let allowed_connections = match &slot.operative_descriptor {
+        OperativeVariants::LibraryOperative(operative_id) => {
+            let operative = schema.operative_library.get(operative_id).ok_or_else(|| {
+                GraphError::InvalidSchema(format!(
+                    "{} accepts unknown operative {}",
+                    item, operative_id
+                ))
+            })?;
+            vec![operative.tag.name.clone()]
+        }
+        // Any operative implementing every listed trait, itself or through its template
+        OperativeVariants::TraitOperative(trait_operative) => schema
+            .operative_library
+            .values()
+            .filter(|operative| {
+                let template_traits = schema
+                    .template_library
+                    .get(&operative.template_id)
+                    .map(|template| &template.trait_impls);
+                trait_operative.trait_ids.iter().all(|trait_id| {
+                    operative.trait_impls.contains_key(trait_id)
+                        || template_traits.is_some_and(|traits| traits.contains_key(trait_id))
+                })
+            })
+            .map(|operative| operative.tag.name.clone())
+            .collect(),
+    };
+
+    Ok(SlotTemplate {
+        id: uid_string(slot.tag.id),
+        name: slot.tag.name.clone(),
+        position: SlotPosition::Right,
+        slot_type: SlotType::Outgoing,
+        allowed_connections,
+        allowed_source_slots: vec![],
+        min_connections,
+        max_connections,
+        can_modify_connections: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventSystem,
+        graph::{GraphCommand, DEFAULT_INCOMING_SLOT_ID},
+        test_support::add_node,
+    };
+
+    // Owners have an age and up to two pets; dogs and cats are animals
+    const SCHEMA: &str = r#"{
+        "template_library": {
+            "1": {
+                "tag": {"id": 1, "name": "Person"},
+                "field_constraints": {
+                    "10": {"tag": {"id": 10, "name": "age"}, "value_type": "U8"}
+                },
+                "operative_slots": {
+                    "20": {
+                        "tag": {"id": 20, "name": "pets"},
+                        "operative_descriptor": {"TraitOperative": {"trait_ids": [5]}},
+                        "bounds": {"UpperBound": 2}
+                    }
+                }
+            },
+            "2": {"tag": {"id": 2, "name": "Animal"}, "trait_impls": {"5": {}}}
+        },
+        "operative_library": {
+            "100": {"tag": {"id": 100, "name": "Owner"}, "template_id": 1},
+            "101": {"tag": {"id": 101, "name": "Dog"}, "template_id": 2},
+            "102": {"tag": {"id": 102, "name": "Cat"}, "template_id": 2}
+        }
+    }"#;
+
+    fn template<'a>(imported: &'a ImportedSchema, name: &str) -> &'a NodeTemplate {
+        imported
+            .node_templates
+            .iter()
+            .find(|template| template.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn operatives_become_node_templates() {
+        let imported = import_molecule_schema(SCHEMA).unwrap();
+
+        let owner = template(&imported, "Owner");
+        assert_eq!(owner.template_id, uid_string(100));
+        assert_eq!(owner.field_templates[0].id, uid_string(10));
+        assert_eq!(owner.field_templates[0].field_type, FieldType::Integer);
+        assert_eq!(owner.field_templates[0].constraints.max, Some(255.0));
+        let pets = &owner.slot_templates[0];
+        assert_eq!(pets.id, uid_string(20));
+        assert_eq!(pets.allowed_connections, vec!["Dog", "Cat"]);
+        assert_eq!((pets.min_connections, pets.max_connections), (0, Some(2)));
+        assert!(template(&imported, "Dog").slot_templates.is_empty());
+    }
+
+    #[test]
+    fn library_templates_become_groups_of_their_operatives() {
+        let imported = import_molecule_schema(SCHEMA).unwrap();
+
+        let groups = imported
+            .template_groups
+            .iter()
+            .map(|group| (group.name.as_str(), group.templates.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec![("Person", 1), ("Animal", 2)]);
+    }
+
+    #[test]
+    fn unsupported_constructs_are_all_reported() {
+        let schema = SCHEMA
+            .replace(r#""value_type": "U8""#, r#""value_type": {"Option": "U8"}"#)
+            .replace(r#"{"UpperBound": 2}"#, r#"{"LowerBoundOrZero": 1}"#);
+
+        let result = import_molecule_schema(&schema);
+
+        let Err(GraphError::ListOfErrors(errors)) = result else {
+            panic!("expected ListOfErrors");
+        };
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|err| matches!(err, GraphError::UnsupportedSchemaConstruct { .. })));
+    }
+
+    #[test]
+    fn dangling_references_make_the_schema_invalid() {
+        let unknown_template = SCHEMA.replace(r#""template_id": 2"#, r#""template_id": 3"#);
+        assert!(matches!(
+            import_molecule_schema(&unknown_template),
+            Err(GraphError::InvalidSchema(_))
+        ));
+
+        let duplicate_name = SCHEMA.replace(r#""name": "Cat""#, r#""name": "Dog""#);
+        assert!(matches!(
+            import_molecule_schema(&duplicate_name),
+            Err(GraphError::InvalidSchema(_))
+        ));
+    }
+
+    // Node ids that stand for instance ids
+    const OWNER: Uid = 1000;
+    const DOG: Uid = 1001;
+    const CAT: Uid = 1002;
+
+    // An owner aged 30 with a dog and a cat
+    fn household() -> Graph {
+        let mut graph = Graph::new();
+        for template in import_molecule_schema(SCHEMA).unwrap().node_templates {
+            graph.register_template(template);
+        }
+        add_node(&mut graph, &uid_string(100), &uid_string(OWNER));
+        add_node(&mut graph, &uid_string(101), &uid_string(DOG));
+        add_node(&mut graph, &uid_string(102), &uid_string(CAT));
+        let events = EventSystem::new();
+        for pet in [DOG, CAT] {
+            let connection = Connection {
+                can_delete: true,
+                host_node_id: uid_string(OWNER),
+                host_slot_template_id: uid_string(20),
+                target_node_id: uid_string(pet),
+                target_slot_template_id: DEFAULT_INCOMING_SLOT_ID.to_string(),
+            };
+            graph
+                .execute_command(GraphCommand::CreateConnection(connection), &events)
+                .unwrap();
+        }
+        graph
+            .execute_command(
+                GraphCommand::UpdateField {
+                    node_id: uid_string(OWNER),
+                    field_template_id: uid_string(10),
+                    new_value: "30".to_string(),
+                },
+                &events,
+            )
+            .unwrap();
+        graph
+    }
+
+    #[test]
+    fn nodes_are_exported_as_library_instances() {
+        let exported = household().to_molecule_instances(SCHEMA).unwrap();
+        let document: serde_json::Value = serde_json::from_str(&exported).unwrap();
+
+        assert_eq!(document["instance_library"].as_object().unwrap().len(), 3);
+        let owner = &document["instance_library"][OWNER.to_string()];
+        assert_eq!(owner["parent_operative_id"], 100);
+        assert_eq!(owner["template_id"], 1);
+        assert_eq!(owner["data"]["10"]["value_type"], "U8");
+        assert_eq!(owner["data"]["10"]["value"], serde_json::json!({"U8": 30}));
+        assert_eq!(
+            owner["slotted_instances"]["20"]["fulfilling_instance_ids"],
+            serde_json::json!([DOG as u64, CAT as u64])
+        );
+        // The rest of the schema comes back untouched
+        assert_eq!(document["operative_library"]["101"]["tag"]["name"], "Dog");
+    }
+
+    #[test]
+    fn exported_instances_load_back_with_their_connections() {
+        let graph = household();
+        let exported = graph.to_molecule_instances(SCHEMA).unwrap();
+
+        let loaded = graph.with_molecule_instances(&exported).unwrap();
+
+        assert!(loaded.check_conformity().is_ok());
+        assert_eq!(loaded.to_molecule_instances(SCHEMA).unwrap(), exported);
+        // The exported document is still a schema the templates can be imported from
+        assert!(import_molecule_schema(&exported).is_ok());
+    }
+
+    #[test]
+    fn only_conforming_graphs_are_exported() {
+        let mut graph = household();
+        let owner = graph.node_instances.get_mut(&uid_string(OWNER)).unwrap();
+        owner.fields[0].value = "300".to_string();
+
+        assert!(matches!(
+            graph.to_molecule_instances(SCHEMA),
+            Err(GraphError::ValidationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn templates_without_schema_ids_cannot_be_exported() {
+        let mut graph = household();
+        let mut plain = NodeTemplate::new("Plain");
+        plain.template_id = "plain".to_string();
+        graph.register_template(plain);
+        add_node(&mut graph, "plain", &uid_string(1003));
+
+        assert!(matches!(
+            graph.to_molecule_instances(SCHEMA),
+            Err(GraphError::UnsupportedSchemaConstruct { item, .. }) if item == "Plain"
+        ));
+    }
+
+    #[test]
+    fn nodes_without_uuid_ids_cannot_be_exported() {
+        let mut graph = household();
+        add_node(&mut graph, &uid_string(101), "rex");
+
+        assert!(matches!(
+            graph.to_molecule_instances(SCHEMA),
+            Err(GraphError::UnsupportedSchemaConstruct { item, .. }) if item == "Node rex"
+        ));
+    }
+
+    #[test]
+    fn slotting_into_a_template_with_several_incoming_slots_is_ambiguous() {
+        let mut graph = household();
+        let exported = graph.to_molecule_instances(SCHEMA).unwrap();
+        // Give dogs a second incoming slot
+        let dog_template = graph.node_templates.get_mut(&uid_string(101)).unwrap();
+        let mut second = dog_template.slot_templates[0].clone();
+        second.id = "second".to_string();
+        dog_template.slot_templates.push(second);
+        let dog = graph.node_instances.get_mut(&uid_string(DOG)).unwrap();
+        let mut second = dog.slots[0].clone();
+        second.slot_template_id = "second".to_string();
+        dog.slots.push(second);
+
+        let is_ambiguous = |err: GraphError| matches!(err, GraphError::UnsupportedSchemaConstruct { item, .. } if item == "Dog");
+        assert!(is_ambiguous(
+            graph.to_molecule_instances(SCHEMA).unwrap_err()
+        ));
+        assert!(is_ambiguous(
+            graph.with_molecule_instances(&exported).unwrap_err()
+        ));
+    }
+}
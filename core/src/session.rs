@@ -0,0 +1,315 @@
+//! Draft editing sessions. Building a graph usually passes through states that break its
+//! constraints (a slot that needs two connections gets them one at a time), so a session
+//! tracks which minimums are still unmet and only lets the graph be committed once it
+//! conforms. Hosts can use the session state to tell drafts apart from finished graphs.
+
+use std::collections::HashMap;
+
+use crate::{
+    errors::{GraphError, GraphResult},
+    events::{EventSystem, SystemEvent},
+    graph::{Graph, NodeTemplateInfo, SlotType},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub enum SessionState {
+    // Constraints may be unmet while the graph is being built
+    Draft,
+    // The graph conformed when it was committed and has not been edited since
+    Committed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub enum PendingConstraintKind {
+    // A slot has fewer connections than its min_connections
+    SlotConnections,
+    // A template has fewer nodes than its min_instances
+    TemplateInstances,
+}
+
+/// One item of remaining work: a minimum the graph does not reach yet.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub struct PendingConstraint {
+    pub kind: PendingConstraintKind,
+    pub template_id: String,
+    // Only set for slot constraints
+    pub node_id: Option<String>,
+    pub slot_template_id: Option<String>,
+    pub required: usize,
+    pub current: usize,
+    pub message: String,
+}
+
+impl PendingConstraint {
+    pub fn remaining(&self) -> usize {
+        self.required.saturating_sub(self.current)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct EditingSession {
+    pub state: SessionState,
+    // Refreshed after every change to the graph
+    pub pending: Vec<PendingConstraint>,
+}
+
+impl EditingSession {
+    pub fn is_draft(&self) -> bool {
+        self.state == SessionState::Draft
+    }
+}
+
+impl Graph {
+    /// Starts tracking the graph as a draft. Restarting an existing session puts it back
+    /// into the draft state.
+    pub fn begin_session(&mut self, events: &EventSystem) {
+        let session = EditingSession {
+            state: SessionState::Draft,
+            pending: self.pending_constraints(),
+        };
+        events.emit(SystemEvent::SessionChanged(session.clone()));
+        self.session = Some(session);
+    }
+
+    /// Marks the session as committed, after flushing any sync adapter. Fails with the
+    /// validation report while the graph does not conform, leaving the session a draft.
+    pub fn commit_session(&mut self, events: &EventSystem) -> GraphResult<()> {
+        if self.session.is_none() {
+            return Err(GraphError::NoEditingSession);
+        }
+        self.check_conformity()?;
+        self.flush_sync(events)?;
+        let session = EditingSession {
+            state: SessionState::Committed,
+            pending: vec![],
+        };
+        events.emit(SystemEvent::SessionChanged(session.clone()));
+        self.session = Some(session);
+        Ok(())
+    }
+
+    pub fn session(&self) -> Option<&EditingSession> {
+        self.session.as_ref()
+    }
+
+    pub fn is_draft(&self) -> bool {
+        self.session.as_ref().is_some_and(EditingSession::is_draft)
+    }
+
+    /// Every slot and template minimum that is not met yet, templates first and then nodes
+    /// by id. Unlike `validate` this ignores problems that adding more nodes or connections
+    /// cannot fix.
+    pub fn pending_constraints(&self) -> Vec<PendingConstraint> {
+        let mut pending = vec![];
+
+        let mut instance_counts: HashMap<&str, usize> = HashMap::new();
+        for instance in self.node_instances.values() {
+            *instance_counts.entry(&instance.template_id).or_default() += 1;
+        }
+        let mut template_ids = self.node_templates.keys().collect::<Vec<_>>();
+        template_ids.sort();
+        for template_id in template_ids {
+            let template = &self.node_templates[template_id];
+            let count = instance_counts
+                .get(template_id.as_str())
+                .copied()
+                .unwrap_or(0);
+            if let Some(min) = template.min_instances.filter(|min| count < *min) {
+                pending.push(PendingConstraint {
+                    kind: PendingConstraintKind::TemplateInstances,
+                    template_id: template_id.clone(),
+                    node_id: None,
+                    slot_template_id: None,
+                    required: min,
+                    current: count,
+                    message: format!("Add {} more {} node(s)", min - count, template.name),
+                });
+            }
+        }
+
+        let mut node_ids = self.node_instances.keys().collect::<Vec<_>>();
+        node_ids.sort();
+        for node_id in node_ids {
+            let instance = &self.node_instances[node_id];
+            let Some(template) = self.node_templates.get(&instance.template_id) else {
+                continue;
+            };
+            for slot in &instance.slots {
+                let Some(slot_template) = template.get_slot_template(&slot.slot_template_id) else {
+                    continue;
+                };
+                let count = match slot_template.slot_type {
+                    SlotType::Incoming => {
+                        self.incoming_connections(node_id, &slot_template.id).len()
+                    }
+                    SlotType::Outgoing => slot.connections.len(),
+                };
+                if count < slot_template.min_connections {
+                    pending.push(PendingConstraint {
+                        kind: PendingConstraintKind::SlotConnections,
+                        template_id: template.template_id.clone(),
+                        node_id: Some(node_id.clone()),
+                        slot_template_id: Some(slot_template.id.clone()),
+                        required: slot_template.min_connections,
+                        current: count,
+                        message: format!(
+                            "Connect {} more to {} on {}",
+                            slot_template.min_connections - count,
+                            slot_template.name,
+                            template.name
+                        ),
+                    });
+                }
+            }
+        }
+
+        pending
+    }
+
+    // Called after anything that may have changed the graph. An edit always turns a
+    // committed session back into a draft.
+    pub(crate) fn refresh_session(&mut self, edited: bool, events: &EventSystem) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let state = if edited {
+            SessionState::Draft
+        } else {
+            session.state
+        };
+        let pending = self.pending_constraints();
+        if session.state == state && session.pending == pending {
+            return;
+        }
+        let session = EditingSession { state, pending };
+        events.emit(SystemEvent::SessionChanged(session.clone()));
+        self.session = Some(session);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graph::GraphCommand, test_support::*};
+
+    // Each parent needs one child, and the graph needs one parent
+    fn demanding_graph() -> Graph {
+        let mut graph = sample_graph();
+        let parent = graph.node_templates.get_mut(PARENT).unwrap();
+        parent.min_instances = Some(1);
+        parent.slot_templates[0].min_connections = 1;
+        graph
+    }
+
+    fn kinds(graph: &Graph) -> Vec<PendingConstraintKind> {
+        graph
+            .pending_constraints()
+            .iter()
+            .map(|pending| pending.kind)
+            .collect()
+    }
+
+    #[test]
+    fn committing_needs_a_session() {
+        let mut graph = sample_graph();
+        assert!(matches!(
+            graph.commit_session(&EventSystem::new()),
+            Err(GraphError::NoEditingSession)
+        ));
+    }
+
+    #[test]
+    fn pending_constraints_track_the_remaining_minimums() {
+        let mut graph = demanding_graph();
+        assert_eq!(
+            kinds(&graph),
+            vec![PendingConstraintKind::TemplateInstances]
+        );
+
+        add_node(&mut graph, PARENT, "p");
+        let pending = graph.pending_constraints();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].kind, PendingConstraintKind::SlotConnections);
+        assert_eq!(pending[0].node_id.as_deref(), Some("p"));
+        assert_eq!(pending[0].remaining(), 1);
+
+        add_node(&mut graph, CHILD, "c");
+        connect(&mut graph, "p", "c");
+        assert!(graph.pending_constraints().is_empty());
+    }
+
+    #[test]
+    fn problems_more_nodes_cannot_fix_are_not_pending() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, CHILD, "c");
+        add_node(&mut graph, CHILD, "c2");
+        graph.node_templates.get_mut(CHILD).unwrap().max_instances = Some(1);
+
+        assert!(graph.pending_constraints().is_empty());
+        assert!(!graph.validate().is_valid());
+    }
+
+    #[test]
+    fn a_draft_is_only_committed_once_it_conforms() {
+        let mut graph = demanding_graph();
+        let (events, names) = record_events();
+        graph.begin_session(&events);
+        add_node(&mut graph, PARENT, "p");
+
+        assert!(matches!(
+            graph.commit_session(&events),
+            Err(GraphError::ValidationFailed(_))
+        ));
+        assert!(graph.is_draft());
+
+        add_node(&mut graph, CHILD, "c");
+        connect(&mut graph, "p", "c");
+        graph.commit_session(&events).unwrap();
+
+        assert_eq!(graph.session().unwrap().state, SessionState::Committed);
+        assert_eq!(names.lock().unwrap().last(), Some(&"SessionChanged"));
+    }
+
+    #[test]
+    fn editing_a_committed_graph_makes_it_a_draft_again() {
+        let mut graph = sample_graph();
+        let events = EventSystem::new();
+        graph.begin_session(&events);
+        graph.commit_session(&events).unwrap();
+        assert!(!graph.is_draft());
+
+        graph
+            .execute_command(create_node(CHILD, "c"), &events)
+            .unwrap();
+
+        assert!(graph.is_draft());
+    }
+
+    #[test]
+    fn commands_that_change_nothing_keep_the_session_committed() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        let (events, names) = record_events();
+        graph.begin_session(&events);
+        graph.commit_session(&events).unwrap();
+        names.lock().unwrap().clear();
+
+        graph
+            .execute_command(
+                GraphCommand::DeleteSlotConnections {
+                    node_id: "p".to_string(),
+                    slot_template_id: CHILDREN_SLOT.to_string(),
+                },
+                &events,
+            )
+            .unwrap();
+
+        assert!(!graph.is_draft());
+        assert_eq!(*names.lock().unwrap(), vec!["CommandExecuted"]);
+    }
+}
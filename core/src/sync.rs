@@ -0,0 +1,422 @@
+//! Mirroring edits into a graph store the host owns. An adapter is told about every node,
+//! connection and field change, either as each command runs, on a timer, or when an editing
+//! session is committed. An adapter that returns an error rejects the changes, and the
+//! graph is rolled back to what the adapter last accepted.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    errors::{GraphError, GraphResult},
+    events::{EventSystem, SystemEvent},
+    graph::{Connection, Graph, NodeInstance},
+    history::{diff_ops, HistoryOp},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub enum SyncMode {
+    // After every command, undo and redo. A rejection undoes just that command.
+    Immediate,
+    // Whatever changed since the last flush, every `interval_ms`
+    Batched { interval_ms: u32 },
+    // Only when an editing session is committed
+    OnCommit,
+}
+
+/// Receives the changes made on the canvas. Positions are not synced.
+///
+/// Each sync is wrapped in `begin_sync` and `finish_sync`. Returning an error from any
+/// callback rejects the whole sync; `abort_sync` is then called so the store can drop
+/// whatever it already applied.
+pub trait GraphSyncAdapter: Send {
+    fn begin_sync(&mut self) -> GraphResult<()> {
+        Ok(())
+    }
+    // A node arrives together with its outgoing connections
+    fn node_added(&mut self, node: &NodeInstance) -> GraphResult<()>;
+    fn node_removed(&mut self, node_id: &str) -> GraphResult<()>;
+    fn connection_added(&mut self, connection: &Connection) -> GraphResult<()>;
+    fn connection_removed(&mut self, connection: &Connection) -> GraphResult<()>;
+    fn field_changed(
+        &mut self,
+        node_id: &str,
+        field_template_id: &str,
+        value: &str,
+    ) -> GraphResult<()>;
+    fn finish_sync(&mut self) -> GraphResult<()> {
+        Ok(())
+    }
+    fn abort_sync(&mut self) {}
+}
+
+/// The adapter attached to a graph and the state it was last synced to.
+#[derive(Clone)]
+pub struct GraphSync {
+    adapter: Arc<Mutex<dyn GraphSyncAdapter>>,
+    pub mode: SyncMode,
+    // The node instances as the adapter last accepted them
+    synced: HashMap<String, NodeInstance>,
+}
+
+impl fmt::Debug for GraphSync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphSync")
+            .field("mode", &self.mode)
+            .field("synced_nodes", &self.synced.len())
+            .finish()
+    }
+}
+
+impl Graph {
+    /// Starts mirroring edits into `adapter`. The adapter is assumed to already hold the
+    /// graph as it is now, so only later changes are sent. Replaces any previous adapter.
+    pub fn set_sync_adapter(&mut self, adapter: Arc<Mutex<dyn GraphSyncAdapter>>, mode: SyncMode) {
+        self.sync = Some(GraphSync {
+            adapter,
+            mode,
+            synced: self.node_instances.clone(),
+        });
+    }
+
+    pub fn clear_sync_adapter(&mut self) {
+        self.sync = None;
+    }
+
+    pub fn sync_mode(&self) -> Option<SyncMode> {
+        self.sync.as_ref().map(|sync| sync.mode)
+    }
+
+    /// Sends everything that changed since the last sync. If the adapter rejects it, the
+    /// nodes, connections and fields go back to the last synced state and the error is
+    /// returned (and emitted as `SyncRejected`).
+    pub fn flush_sync(&mut self, events: &EventSystem) -> GraphResult<()> {
+        let Some(sync) = &self.sync else {
+            return Ok(());
+        };
+        let (changes, _) = diff_ops(&sync.synced, &self.node_instances);
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let result = match sync.adapter.lock() {
+            Ok(mut adapter) => send_changes(&mut *adapter, &changes),
+            Err(_) => Err(GraphError::LockFailed("sync adapter".to_string())),
+        };
+        match result {
+            Ok(()) => {
+                let synced = self.node_instances.clone();
+                if let Some(sync) = &mut self.sync {
+                    sync.synced = synced;
+                }
+                Ok(())
+            }
+            Err(reason) => {
+                self.restore_synced(events);
+                let error = GraphError::SyncRejected {
+                    reason: Box::new(reason),
+                };
+                events.emit(SystemEvent::SyncRejected(error.clone()));
+                Err(error)
+            }
+        }
+    }
+
+    // Called after each command, undo and redo
+    pub(crate) fn sync_edit(&mut self, events: &EventSystem) -> GraphResult<()> {
+        if self.sync_mode() == Some(SyncMode::Immediate) {
+            self.flush_sync(events)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Nodes keep their current positions, since those are never synced. In Immediate mode
+    // only the rejected edit is lost, so the history still lines up; otherwise it may refer
+    // to edits that are gone and is cleared.
+    fn restore_synced(&mut self, events: &EventSystem) {
+        let Some(sync) = &self.sync else {
+            return;
+        };
+        let mut restored = sync.synced.clone();
+        for (node_id, node) in &mut restored {
+            if let Some(current) = self.node_instances.get(node_id) {
+                node.x = current.x;
+                node.y = current.y;
+            }
+        }
+        let immediate = sync.mode == SyncMode::Immediate;
+        self.node_instances = restored;
+        if !immediate {
+            self.history.clear();
+            self.emit_history_changed(events);
+            self.refresh_session(true, events);
+        }
+    }
+}
+
+fn send_changes(adapter: &mut dyn GraphSyncAdapter, changes: &[HistoryOp]) -> GraphResult<()> {
+    let result = adapter.begin_sync().and_then(|_| {
+        for change in changes {
+            match change {
+                HistoryOp::InsertNode(node) => adapter.node_added(node)?,
+                HistoryOp::RemoveNode(node_id) => adapter.node_removed(node_id)?,
+                HistoryOp::InsertConnection(connection) => adapter.connection_added(connection)?,
+                HistoryOp::RemoveConnection(connection) => {
+                    adapter.connection_removed(connection)?
+                }
+                HistoryOp::SetFieldValue {
+                    node_id,
+                    field_template_id,
+                    value,
+                } => adapter.field_changed(node_id, field_template_id, value)?,
+            }
+        }
+        adapter.finish_sync()
+    });
+    if result.is_err() {
+        adapter.abort_sync();
+    }
+    result
+}
+
+/// Keeps a copy of the synced nodes in memory. Meant for tests and as a starting point for
+/// real adapters.
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySyncAdapter {
+    pub nodes: HashMap<String, NodeInstance>,
+    // Number of syncs accepted so far
+    pub syncs: usize,
+    // Rejects the next sync with this reason
+    pub reject_next: Option<String>,
+    // The nodes before the current sync, restored if it is aborted
+    rollback: Option<HashMap<String, NodeInstance>>,
+}
+
+impl InMemorySyncAdapter {
+    /// Starts out holding `graph`'s nodes, matching what `set_sync_adapter` assumes.
+    pub fn from_graph(graph: &Graph) -> Self {
+        Self {
+            nodes: graph.node_instances.clone(),
+            ..Self::default()
+        }
+    }
+
+    fn slot_connections(&mut self, connection: &Connection) -> GraphResult<&mut Vec<Connection>> {
+        self.nodes
+            .get_mut(&connection.host_node_id)
+            .and_then(|node| {
+                node.slots
+                    .iter_mut()
+                    .find(|slot| slot.slot_template_id == connection.host_slot_template_id)
+            })
+            .map(|slot| &mut slot.connections)
+            .ok_or_else(|| GraphError::SlotNotFound {
+                node_id: connection.host_node_id.clone(),
+                slot_id: connection.host_slot_template_id.clone(),
+            })
+    }
+}
+
+impl GraphSyncAdapter for InMemorySyncAdapter {
+    fn begin_sync(&mut self) -> GraphResult<()> {
+        if let Some(reason) = self.reject_next.take() {
+            return Err(GraphError::Other(reason));
+        }
+        self.rollback = Some(self.nodes.clone());
+        Ok(())
+    }
+
+    fn node_added(&mut self, node: &NodeInstance) -> GraphResult<()> {
+        self.nodes.insert(node.instance_id.clone(), node.clone());
+        Ok(())
+    }
+
+    fn node_removed(&mut self, node_id: &str) -> GraphResult<()> {
+        self.nodes
+            .remove(node_id)
+            .map(|_| ())
+            .ok_or_else(|| GraphError::NodeNotFound(node_id.to_string()))
+    }
+
+    fn connection_added(&mut self, connection: &Connection) -> GraphResult<()> {
+        let connections = self.slot_connections(connection)?;
+        if !connections.contains(connection) {
+            connections.push(connection.clone());
+        }
+        Ok(())
+    }
+
+    fn connection_removed(&mut self, connection: &Connection) -> GraphResult<()> {
+        self.slot_connections(connection)?
+            .retain(|existing| existing != connection);
+        Ok(())
+    }
+
+    fn field_changed(
+        &mut self,
+        node_id: &str,
+        field_template_id: &str,
+        value: &str,
+    ) -> GraphResult<()> {
+        let field = self
+            .nodes
+            .get_mut(node_id)
+            .ok_or_else(|| GraphError::NodeNotFound(node_id.to_string()))?
+            .fields
+            .iter_mut()
+            .find(|field| field.field_template_id == field_template_id)
+            .ok_or_else(|| GraphError::Other(format!("Unknown field {}", field_template_id)))?;
+        field.value = value.to_string();
+        Ok(())
+    }
+
+    fn finish_sync(&mut self) -> GraphResult<()> {
+        self.rollback = None;
+        self.syncs += 1;
+        Ok(())
+    }
+
+    fn abort_sync(&mut self) {
+        if let Some(nodes) = self.rollback.take() {
+            self.nodes = nodes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graph::GraphCommand, test_support::*};
+
+    // A parent and a child, already held by the adapter
+    fn synced_graph(mode: SyncMode) -> (Graph, Arc<Mutex<InMemorySyncAdapter>>) {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, CHILD, "c");
+        let adapter = Arc::new(Mutex::new(InMemorySyncAdapter::from_graph(&graph)));
+        graph.set_sync_adapter(adapter.clone(), mode);
+        (graph, adapter)
+    }
+
+    fn stored_connections(adapter: &Mutex<InMemorySyncAdapter>) -> usize {
+        adapter.lock().unwrap().nodes["p"].slots[0]
+            .connections
+            .len()
+    }
+
+    fn reject_next(adapter: &Mutex<InMemorySyncAdapter>) {
+        adapter.lock().unwrap().reject_next = Some("store is read-only".to_string());
+    }
+
+    fn connect_child(graph: &mut Graph, events: &EventSystem) -> GraphResult<()> {
+        graph.execute_command(
+            GraphCommand::CreateConnection(child_connection("p", "c")),
+            events,
+        )
+    }
+
+    #[test]
+    fn immediate_mode_sends_each_command() {
+        let (mut graph, adapter) = synced_graph(SyncMode::Immediate);
+        let events = EventSystem::new();
+
+        connect_child(&mut graph, &events).unwrap();
+        assert_eq!(stored_connections(&adapter), 1);
+
+        assert!(graph.undo(&events));
+        assert_eq!(stored_connections(&adapter), 0);
+        assert_eq!(adapter.lock().unwrap().syncs, 2);
+    }
+
+    #[test]
+    fn a_rejected_command_is_rolled_back() {
+        let (mut graph, adapter) = synced_graph(SyncMode::Immediate);
+        let (events, names) = record_events();
+        reject_next(&adapter);
+
+        let result = connect_child(&mut graph, &events);
+
+        assert!(matches!(result, Err(GraphError::SyncRejected { .. })));
+        assert!(graph.get_node_connections("p").is_empty());
+        assert_eq!(
+            *names.lock().unwrap(),
+            vec!["SyncRejected", "CommandFailed"]
+        );
+        // Only the rejected command is lost
+        assert!(graph.history.can_undo());
+        assert_eq!(adapter.lock().unwrap().syncs, 0);
+    }
+
+    #[test]
+    fn a_rejected_undo_stays_undoable() {
+        let (mut graph, adapter) = synced_graph(SyncMode::Immediate);
+        let events = EventSystem::new();
+        connect_child(&mut graph, &events).unwrap();
+        reject_next(&adapter);
+
+        assert!(!graph.undo(&events));
+
+        assert_eq!(graph.get_node_connections("p").len(), 1);
+        assert!(graph.undo(&events));
+        assert!(graph.get_node_connections("p").is_empty());
+    }
+
+    #[test]
+    fn an_adapter_that_fails_midway_drops_what_it_applied() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        // The adapter never heard of "p", so connecting from it fails after "c" was added
+        let adapter = Arc::new(Mutex::new(InMemorySyncAdapter::default()));
+        graph.set_sync_adapter(adapter.clone(), SyncMode::Immediate);
+        let batch = GraphCommand::Batch(vec![
+            create_node(CHILD, "c"),
+            GraphCommand::CreateConnection(child_connection("p", "c")),
+        ]);
+
+        assert!(graph.execute_command(batch, &EventSystem::new()).is_err());
+
+        assert!(adapter.lock().unwrap().nodes.is_empty());
+        assert!(!graph.node_instances.contains_key("c"));
+    }
+
+    #[test]
+    fn batched_changes_wait_for_a_flush() {
+        let (mut graph, adapter) = synced_graph(SyncMode::Batched { interval_ms: 1000 });
+        let events = EventSystem::new();
+        connect_child(&mut graph, &events).unwrap();
+        add_node(&mut graph, CHILD, "c2");
+        assert_eq!(stored_connections(&adapter), 0);
+
+        graph.flush_sync(&events).unwrap();
+
+        assert_eq!(stored_connections(&adapter), 1);
+        assert!(adapter.lock().unwrap().nodes.contains_key("c2"));
+        assert_eq!(adapter.lock().unwrap().syncs, 1);
+    }
+
+    #[test]
+    fn a_rejected_commit_restores_the_synced_graph() {
+        let (mut graph, adapter) = synced_graph(SyncMode::OnCommit);
+        let events = EventSystem::new();
+        graph.begin_session(&events);
+        connect_child(&mut graph, &events).unwrap();
+        graph.node_instances.get_mut("p").unwrap().x = 40.0;
+        reject_next(&adapter);
+
+        assert!(graph.commit_session(&events).is_err());
+
+        assert!(graph.get_node_connections("p").is_empty());
+        // Positions are never synced, so they are kept
+        assert_eq!(graph.node_instances["p"].x, 40.0);
+        assert!(!graph.history.can_undo());
+        assert!(graph.is_draft());
+
+        connect_child(&mut graph, &events).unwrap();
+        graph.commit_session(&events).unwrap();
+        assert_eq!(stored_connections(&adapter), 1);
+    }
+}
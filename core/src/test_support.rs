@@ -0,0 +1,84 @@
+// Fixtures shared by the unit tests. Templates get fixed ids so tests can refer to them.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    events::EventSystem,
+    graph::{
+        Connection, Graph, GraphCommand, NodeTemplate, SlotTemplate, DEFAULT_INCOMING_SLOT_ID,
+    },
+};
+
+pub(crate) const PARENT: &str = "parent";
+pub(crate) const CHILD: &str = "child";
+pub(crate) const CHILDREN_SLOT: &str = "children";
+
+// "Parent" has an outgoing "Children" slot that accepts "Child" nodes
+pub(crate) fn parent_template() -> NodeTemplate {
+    let mut children = SlotTemplate::new("Children");
+    children.id = CHILDREN_SLOT.to_string();
+    children.allowed_connections = vec!["Child".to_string()];
+    let mut template = NodeTemplate::new("Parent");
+    template.template_id = PARENT.to_string();
+    template.slot_templates.push(children);
+    template
+}
+
+pub(crate) fn child_template() -> NodeTemplate {
+    let mut template = NodeTemplate::new("Child");
+    template.template_id = CHILD.to_string();
+    template
+}
+
+pub(crate) fn sample_graph() -> Graph {
+    let mut graph = Graph::new();
+    graph.register_template(parent_template());
+    graph.register_template(child_template());
+    graph
+}
+
+pub(crate) fn create_node(template_id: &str, id: &str) -> GraphCommand {
+    GraphCommand::CreateNode {
+        template_id: template_id.to_string(),
+        x: 0.0,
+        y: 0.0,
+        id: Some(id.to_string()),
+    }
+}
+
+pub(crate) fn add_node(graph: &mut Graph, template_id: &str, id: &str) {
+    graph
+        .execute_command(create_node(template_id, id), &EventSystem::new())
+        .unwrap();
+}
+
+// From a parent's "Children" slot to a child's default incoming slot
+pub(crate) fn child_connection(parent_id: &str, child_id: &str) -> Connection {
+    Connection {
+        can_delete: true,
+        host_node_id: parent_id.to_string(),
+        host_slot_template_id: CHILDREN_SLOT.to_string(),
+        target_node_id: child_id.to_string(),
+        target_slot_template_id: DEFAULT_INCOMING_SLOT_ID.to_string(),
+    }
+}
+
+pub(crate) fn connect(graph: &mut Graph, parent_id: &str, child_id: &str) {
+    graph
+        .execute_command(
+            GraphCommand::CreateConnection(child_connection(parent_id, child_id)),
+            &EventSystem::new(),
+        )
+        .unwrap();
+}
+
+// An event system that records the name of every event emitted on it
+pub(crate) fn record_events() -> (EventSystem, Arc<Mutex<Vec<&'static str>>>) {
+    let names = Arc::new(Mutex::new(vec![]));
+    let sink = names.clone();
+    let mut events = EventSystem::new();
+    events.subscribe(Box::new(move |event| {
+        sink.lock().unwrap().push(event.name());
+    }));
+    (events, names)
+}
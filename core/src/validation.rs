@@ -0,0 +1,1024 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use regex::Regex;
+
+use crate::{
+    errors::{GraphError, GraphResult},
+    graph::{
+        FieldTemplate, FieldType, Graph, NodeInstance, NodeTemplate, NodeTemplateInfo, SlotType,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub enum ValidationSeverity {
+    // The graph cannot be saved while any of these remain
+    Error,
+    // Worth surfacing, but the graph is still usable
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub enum ValidationCode {
+    UnknownTemplate,
+    UnknownSlot,
+    MissingSlot,
+    SlotBelowMinimum,
+    SlotAboveMaximum,
+    DanglingConnection,
+    ConnectionNotAllowed,
+    TooFewInstances,
+    TooManyInstances,
+    UnknownField,
+    MissingField,
+    InvalidFieldValue,
+    FieldConstraintViolated,
+    DuplicateFieldValue,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+pub struct ValidationIssue {
+    pub severity: ValidationSeverity,
+    pub code: ValidationCode,
+    // Set for template-wide issues such as instance counts
+    pub template_id: Option<String>,
+    pub node_id: Option<String>,
+    pub slot_template_id: Option<String>,
+    pub field_template_id: Option<String>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn error(code: ValidationCode, message: String) -> Self {
+        Self {
+            severity: ValidationSeverity::Error,
+            code,
+            template_id: None,
+            node_id: None,
+            slot_template_id: None,
+            field_template_id: None,
+            message,
+        }
+    }
+
+    fn warning(code: ValidationCode, message: String) -> Self {
+        Self {
+            severity: ValidationSeverity::Warning,
+            ..Self::error(code, message)
+        }
+    }
+
+    fn on_template(mut self, template_id: &str) -> Self {
+        self.template_id = Some(template_id.to_string());
+        self
+    }
+
+    fn on_node(mut self, node_id: &str) -> Self {
+        self.node_id = Some(node_id.to_string());
+        self
+    }
+
+    fn on_slot(mut self, slot_template_id: &str) -> Self {
+        self.slot_template_id = Some(slot_template_id.to_string());
+        self
+    }
+
+    fn on_field(mut self, field_template_id: &str) -> Self {
+        self.field_template_id = Some(field_template_id.to_string());
+        self
+    }
+}
+
+/// Every rule violation found in a graph, in a stable order (templates, then nodes by id).
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "js", derive(tsify::Tsify))]
+#[cfg_attr(feature = "js", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    // Warnings do not make a graph invalid
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Warning)
+    }
+
+    pub fn issues_for_node<'a>(
+        &'a self,
+        node_id: &'a str,
+    ) -> impl Iterator<Item = &'a ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(move |issue| issue.node_id.as_deref() == Some(node_id))
+    }
+
+    pub fn issues_for_slot<'a>(
+        &'a self,
+        node_id: &'a str,
+        slot_template_id: &'a str,
+    ) -> impl Iterator<Item = &'a ValidationIssue> {
+        self.issues_for_node(node_id)
+            .filter(move |issue| issue.slot_template_id.as_deref() == Some(slot_template_id))
+    }
+
+    // Template-wide issues (instance counts) that are not tied to any single node
+    pub fn issues_for_template<'a>(
+        &'a self,
+        template_id: &'a str,
+    ) -> impl Iterator<Item = &'a ValidationIssue> {
+        self.issues.iter().filter(move |issue| {
+            issue.node_id.is_none() && issue.template_id.as_deref() == Some(template_id)
+        })
+    }
+}
+
+impl FieldType {
+    /// Checks that `value` can be stored in a field of this type.
+    pub fn validate_value(&self, value: &str) -> Result<(), String> {
+        match self {
+            FieldType::Boolean => {
+                if value != "true" && value != "false" {
+                    return Err("Invalid boolean value".to_string());
+                }
+            }
+            FieldType::Integer => {
+                if value.parse::<i64>().is_err() {
+                    return Err("Invalid integer value".to_string());
+                }
+            }
+            FieldType::String | FieldType::MultilineText => {
+                // All string values are valid
+            }
+            FieldType::Float { min, max, step } => {
+                let number = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .ok_or("Invalid number value".to_string())?;
+                if let Some(min) = min.filter(|min| number < *min) {
+                    return Err(format!("Value must be at least {}", min));
+                }
+                if let Some(max) = max.filter(|max| number > *max) {
+                    return Err(format!("Value must be at most {}", max));
+                }
+                if let Some(step) = step.filter(|step| *step > 0.0) {
+                    let steps = (number - min.unwrap_or(0.0)) / step;
+                    // Allow for rounding in values like 0.1 + 0.2
+                    if (steps - steps.round()).abs() > 1e-9 * steps.abs().max(1.0) {
+                        return Err(format!("Value must be a multiple of {}", step));
+                    }
+                }
+            }
+            FieldType::Enum { options } => {
+                if !options.iter().any(|option| option == value) {
+                    return Err(format!("Value must be one of: {}", options.join(", ")));
+                }
+            }
+            FieldType::Color => {
+                let hex = value.strip_prefix('#').unwrap_or_default();
+                if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err("Invalid color value, expected #rrggbb".to_string());
+                }
+            }
+            FieldType::Date => {
+                if !is_valid_date(value) {
+                    return Err("Invalid date value, expected YYYY-MM-DD".to_string());
+                }
+            }
+            FieldType::Json => {
+                if let Err(err) = serde_json::from_str::<serde_json::Value>(value) {
+                    return Err(format!("Invalid JSON value: {}", err));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FieldTemplate {
+    /// Checks `value` against this field's type and constraints. Uniqueness depends on the
+    /// other nodes, so it is left to `Graph::check_field_unique`.
+    pub fn check_value(&self, value: &str) -> GraphResult<()> {
+        self.check_value_with(value, &PatternCache::default())
+    }
+
+    // `check_value`, reusing the patterns a graph has already compiled
+    pub(crate) fn check_value_with(&self, value: &str, patterns: &PatternCache) -> GraphResult<()> {
+        let constraints = &self.constraints;
+        if constraints.required && value.trim().is_empty() {
+            return Err(GraphError::FieldRequired {
+                field_name: self.name.clone(),
+            });
+        }
+        self.field_type
+            .validate_value(value)
+            .map_err(|reason| GraphError::InvalidFieldValue {
+                field_name: self.name.clone(),
+                value: value.to_string(),
+                reason,
+            })?;
+        // Optional fields may be left empty whatever their pattern or length limits
+        if value.is_empty() {
+            return Ok(());
+        }
+
+        let length = value.chars().count();
+        if constraints.min_length.is_some_and(|min| length < min)
+            || constraints.max_length.is_some_and(|max| length > max)
+        {
+            return Err(GraphError::FieldLengthOutOfRange {
+                field_name: self.name.clone(),
+                min: constraints.min_length,
+                max: constraints.max_length,
+                actual: length,
+            });
+        }
+
+        // The type check above guarantees numeric fields parse
+        let number = match self.field_type {
+            FieldType::Integer | FieldType::Float { .. } => value.parse::<f64>().ok(),
+            _ => None,
+        };
+        if let Some(number) = number {
+            if constraints.min.is_some_and(|min| number < min)
+                || constraints.max.is_some_and(|max| number > max)
+            {
+                return Err(GraphError::FieldValueOutOfRange {
+                    field_name: self.name.clone(),
+                    min: constraints.min,
+                    max: constraints.max,
+                    value: number,
+                });
+            }
+        }
+
+        if let Some(pattern) = &constraints.pattern {
+            let regex =
+                patterns
+                    .compiled(pattern)
+                    .map_err(|err| GraphError::InvalidFieldPattern {
+                        field_name: self.name.clone(),
+                        pattern: pattern.clone(),
+                        reason: err.to_string(),
+                    })?;
+            if !regex.is_match(value) {
+                return Err(GraphError::FieldPatternMismatch {
+                    field_name: self.name.clone(),
+                    pattern: pattern.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// Patterns a graph has compiled, since every edit and paste checks field values again.
+// Templates can be swapped at runtime, so the cache starts over once it holds this many.
+const MAX_CACHED_PATTERNS: usize = 64;
+
+#[derive(Debug, Default)]
+pub(crate) struct PatternCache(Mutex<HashMap<String, Regex>>);
+
+impl Clone for PatternCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.lock().clone()))
+    }
+}
+
+impl PatternCache {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Regex>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn compiled(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let mut patterns = self.lock();
+        if let Some(regex) = patterns.get(pattern) {
+            return Ok(regex.clone());
+        }
+        // Anchored so the pattern has to match the whole value, as in HTML forms
+        let regex = Regex::new(&format!("^(?:{})$", pattern))?;
+        if patterns.len() >= MAX_CACHED_PATTERNS {
+            patterns.clear();
+        }
+        patterns.insert(pattern.to_string(), regex.clone());
+        Ok(regex)
+    }
+}
+
+// A real calendar date written as YYYY-MM-DD
+fn is_valid_date(value: &str) -> bool {
+    let parts = value.split('-').collect::<Vec<_>>();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    let all_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    if !all_digits(year) || !all_digits(month) || !all_digits(day) {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (
+        year.parse::<u32>(),
+        month.parse::<u32>(),
+        day.parse::<u32>(),
+    ) else {
+        return false;
+    };
+    let is_leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+impl Graph {
+    /// Fails if `value` is already used by another node of the same template and the field
+    /// is marked unique. Empty values never clash.
+    pub fn check_field_unique(
+        &self,
+        node: &NodeInstance,
+        field_template: &FieldTemplate,
+        value: &str,
+    ) -> GraphResult<()> {
+        if !field_template.constraints.unique || value.is_empty() {
+            return Ok(());
+        }
+        // Lowest id, so the same node is named every time
+        let duplicate = self
+            .node_instances
+            .values()
+            .filter(|other| {
+                other.instance_id != node.instance_id && other.template_id == node.template_id
+            })
+            .filter(|other| {
+                other.fields.iter().any(|field| {
+                    field.field_template_id == field_template.id && field.value == value
+                })
+            })
+            .map(|other| &other.instance_id)
+            .min();
+        match duplicate {
+            Some(other_id) => Err(GraphError::DuplicateFieldValue {
+                field_name: field_template.name.clone(),
+                value: value.to_string(),
+                node_id: other_id.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the whole graph against its templates without modifying it.
+    pub fn validate(&self) -> ValidationReport {
+        let mut issues = vec![];
+
+        let mut template_ids = self.node_templates.keys().collect::<Vec<_>>();
+        template_ids.sort();
+        let mut instance_counts: HashMap<&str, usize> = HashMap::new();
+        for instance in self.node_instances.values() {
+            *instance_counts.entry(&instance.template_id).or_default() += 1;
+        }
+        for template_id in template_ids {
+            let template = &self.node_templates[template_id];
+            let count = instance_counts
+                .get(template_id.as_str())
+                .copied()
+                .unwrap_or(0);
+            if template.min_instances.is_some_and(|min| count < min) {
+                issues.push(
+                    ValidationIssue::error(
+                        ValidationCode::TooFewInstances,
+                        format!(
+                            "{} needs at least {} nodes, found {}",
+                            template.name,
+                            template.min_instances.unwrap(),
+                            count
+                        ),
+                    )
+                    .on_template(template_id),
+                );
+            }
+            if template.max_instances.is_some_and(|max| count > max) {
+                issues.push(
+                    ValidationIssue::error(
+                        ValidationCode::TooManyInstances,
+                        format!(
+                            "{} allows at most {} nodes, found {}",
+                            template.name,
+                            template.max_instances.unwrap(),
+                            count
+                        ),
+                    )
+                    .on_template(template_id),
+                );
+            }
+        }
+
+        let mut node_ids = self.node_instances.keys().collect::<Vec<_>>();
+        node_ids.sort();
+        for node_id in node_ids {
+            let instance = &self.node_instances[node_id];
+            let Some(template) = self.node_templates.get(&instance.template_id) else {
+                issues.push(
+                    ValidationIssue::error(
+                        ValidationCode::UnknownTemplate,
+                        format!("Node uses unknown template {}", instance.template_id),
+                    )
+                    .on_node(node_id),
+                );
+                continue;
+            };
+            self.validate_slots(instance, template, &mut issues);
+            self.validate_fields(instance, template, &mut issues);
+        }
+
+        ValidationReport { issues }
+    }
+
+    fn validate_slots(
+        &self,
+        instance: &NodeInstance,
+        template: &NodeTemplate,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        let node_id = instance.instance_id.as_str();
+        for slot_template in &template.slot_templates {
+            if !instance
+                .slots
+                .iter()
+                .any(|slot| slot.slot_template_id == slot_template.id)
+            {
+                issues.push(
+                    ValidationIssue::error(
+                        ValidationCode::MissingSlot,
+                        format!("Slot {} is missing", slot_template.name),
+                    )
+                    .on_node(node_id)
+                    .on_slot(&slot_template.id),
+                );
+            }
+        }
+
+        for slot in &instance.slots {
+            let Some(slot_template) = template.get_slot_template(&slot.slot_template_id) else {
+                issues.push(
+                    ValidationIssue::error(
+                        ValidationCode::UnknownSlot,
+                        format!(
+                            "Slot {} is not part of template {}",
+                            slot.slot_template_id, template.name
+                        ),
+                    )
+                    .on_node(node_id)
+                    .on_slot(&slot.slot_template_id),
+                );
+                continue;
+            };
+
+            let connection_count = match slot_template.slot_type {
+                SlotType::Incoming => self.incoming_connections(node_id, &slot_template.id).len(),
+                SlotType::Outgoing => slot.connections.len(),
+            };
+            if connection_count < slot_template.min_connections {
+                issues.push(
+                    ValidationIssue::error(
+                        ValidationCode::SlotBelowMinimum,
+                        format!(
+                            "{} needs at least {} connections, has {}",
+                            slot_template.name, slot_template.min_connections, connection_count
+                        ),
+                    )
+                    .on_node(node_id)
+                    .on_slot(&slot_template.id),
+                );
+            }
+            if slot_template
+                .max_connections
+                .is_some_and(|max| connection_count > max)
+            {
+                issues.push(
+                    ValidationIssue::error(
+                        ValidationCode::SlotAboveMaximum,
+                        format!(
+                            "{} allows at most {} connections, has {}",
+                            slot_template.name,
+                            slot_template.max_connections.unwrap(),
+                            connection_count
+                        ),
+                    )
+                    .on_node(node_id)
+                    .on_slot(&slot_template.id),
+                );
+            }
+
+            for connection in &slot.connections {
+                let target_template = self
+                    .node_instances
+                    .get(&connection.target_node_id)
+                    .and_then(|target| self.node_templates.get(&target.template_id));
+                let Some(target_template) = target_template.filter(|target_template| {
+                    target_template
+                        .get_slot_template(&connection.target_slot_template_id)
+                        .is_some()
+                }) else {
+                    issues.push(
+                        ValidationIssue::error(
+                            ValidationCode::DanglingConnection,
+                            format!(
+                                "{} connects to missing slot {}:{}",
+                                slot_template.name,
+                                connection.target_node_id,
+                                connection.target_slot_template_id
+                            ),
+                        )
+                        .on_node(node_id)
+                        .on_slot(&slot_template.id),
+                    );
+                    continue;
+                };
+                let rejection = if !slot_template
+                    .allowed_connections
+                    .contains(&target_template.name)
+                {
+                    Some("This slot cannot connect to the target node template")
+                } else {
+                    self.target_slot_rejection(connection)
+                };
+                if let Some(reason) = rejection {
+                    issues.push(
+                        ValidationIssue::error(
+                            ValidationCode::ConnectionNotAllowed,
+                            format!(
+                                "{} to {}: {}",
+                                slot_template.name, target_template.name, reason
+                            ),
+                        )
+                        .on_node(node_id)
+                        .on_slot(&slot_template.id),
+                    );
+                }
+            }
+        }
+    }
+
+    fn validate_fields(
+        &self,
+        instance: &NodeInstance,
+        template: &NodeTemplate,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        let node_id = instance.instance_id.as_str();
+        for field_template in &template.field_templates {
+            if !instance
+                .fields
+                .iter()
+                .any(|field| field.field_template_id == field_template.id)
+            {
+                issues.push(
+                    ValidationIssue::warning(
+                        ValidationCode::MissingField,
+                        format!("Field {} is missing", field_template.name),
+                    )
+                    .on_node(node_id)
+                    .on_field(&field_template.id),
+                );
+            }
+        }
+        for field in &instance.fields {
+            let Some(field_template) = template
+                .field_templates
+                .iter()
+                .find(|ft| ft.id == field.field_template_id)
+            else {
+                issues.push(
+                    ValidationIssue::warning(
+                        ValidationCode::UnknownField,
+                        format!(
+                            "Field {} is not part of template {}",
+                            field.field_template_id, template.name
+                        ),
+                    )
+                    .on_node(node_id)
+                    .on_field(&field.field_template_id),
+                );
+                continue;
+            };
+            let result = field_template
+                .check_value_with(&field.value, &self.patterns)
+                .and_then(|()| self.check_field_unique(instance, field_template, &field.value));
+            if let Err(err) = result {
+                let code = match err {
+                    GraphError::InvalidFieldValue { .. } => ValidationCode::InvalidFieldValue,
+                    GraphError::DuplicateFieldValue { .. } => ValidationCode::DuplicateFieldValue,
+                    _ => ValidationCode::FieldConstraintViolated,
+                };
+                issues.push(
+                    ValidationIssue::error(code, err.to_string())
+                        .on_node(node_id)
+                        .on_field(&field_template.id),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventSystem,
+        graph::{Connection, FieldConstraints, GraphCommand, DEFAULT_INCOMING_SLOT_ID},
+        test_support::*,
+    };
+
+    fn codes(report: &ValidationReport) -> Vec<ValidationCode> {
+        report.issues.iter().map(|issue| issue.code).collect()
+    }
+
+    #[test]
+    fn a_graph_that_follows_its_templates_is_valid() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, CHILD, "c");
+        connect(&mut graph, "p", "c");
+
+        assert!(graph.validate().issues.is_empty());
+        assert!(graph.check_conformity().is_ok());
+    }
+
+    #[test]
+    fn instance_counts_are_reported_on_the_template() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, CHILD, "c");
+        add_node(&mut graph, CHILD, "c2");
+        graph.node_templates.get_mut(PARENT).unwrap().min_instances = Some(1);
+        graph.node_templates.get_mut(CHILD).unwrap().max_instances = Some(1);
+
+        let report = graph.validate();
+
+        assert_eq!(
+            codes(&report),
+            vec![
+                ValidationCode::TooManyInstances,
+                ValidationCode::TooFewInstances
+            ]
+        );
+        assert_eq!(report.issues_for_template(PARENT).count(), 1);
+        assert!(matches!(
+            graph.check_conformity(),
+            Err(GraphError::ValidationFailed(failed)) if failed == report
+        ));
+    }
+
+    #[test]
+    fn slot_minimums_are_reported_on_the_slot() {
+        let mut graph = sample_graph();
+        let parent = graph.node_templates.get_mut(PARENT).unwrap();
+        parent.slot_templates[0].min_connections = 1;
+        add_node(&mut graph, PARENT, "p");
+
+        let report = graph.validate();
+
+        assert_eq!(codes(&report), vec![ValidationCode::SlotBelowMinimum]);
+        assert_eq!(report.issues_for_slot("p", CHILDREN_SLOT).count(), 1);
+        assert_eq!(report.issues_for_node("p").count(), 1);
+    }
+
+    #[test]
+    fn incoming_slot_maximums_count_every_connection_ending_there() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, PARENT, "p2");
+        add_node(&mut graph, CHILD, "c");
+        connect(&mut graph, "p", "c");
+        connect(&mut graph, "p2", "c");
+        let child = graph.node_templates.get_mut(CHILD).unwrap();
+        child.slot_templates[0].max_connections = Some(1);
+
+        let report = graph.validate();
+
+        assert_eq!(codes(&report), vec![ValidationCode::SlotAboveMaximum]);
+        assert_eq!(
+            report
+                .issues_for_slot("c", DEFAULT_INCOMING_SLOT_ID)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn broken_connections_and_nodes_are_reported() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, PARENT, "p");
+        add_node(&mut graph, PARENT, "p2");
+        let connections = &mut graph.node_instances.get_mut("p").unwrap().slots[0].connections;
+        // Parents do not accept parents, and "missing" does not exist
+        connections.push(Connection {
+            target_node_id: "p2".to_string(),
+            ..child_connection("p", "c")
+        });
+        connections.push(child_connection("p", "missing"));
+        let mut orphan = graph.node_instances["p2"].clone();
+        orphan.instance_id = "x".to_string();
+        orphan.template_id = "unknown".to_string();
+        graph.node_instances.insert("x".to_string(), orphan);
+
+        let report = graph.validate();
+
+        assert_eq!(
+            codes(&report),
+            vec![
+                ValidationCode::ConnectionNotAllowed,
+                ValidationCode::DanglingConnection,
+                ValidationCode::UnknownTemplate
+            ]
+        );
+    }
+
+    #[test]
+    fn warnings_do_not_make_a_graph_invalid() {
+        let mut graph = sample_graph();
+        add_node(&mut graph, CHILD, "c");
+        graph
+            .node_templates
+            .get_mut(CHILD)
+            .unwrap()
+            .field_templates
+            .push(FieldTemplate::new("Name", FieldType::String, ""));
+
+        let report = graph.validate();
+
+        assert_eq!(codes(&report), vec![ValidationCode::MissingField]);
+        assert_eq!(report.warnings().count(), 1);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn floats_respect_their_range_and_step() {
+        let float = FieldType::Float {
+            min: Some(0.0),
+            max: Some(1.0),
+            step: Some(0.1),
+        };
+        assert!(float.validate_value("0.3").is_ok());
+        assert!(float.validate_value("1").is_ok());
+        assert!(float.validate_value("0.25").is_err());
+        assert!(float.validate_value("1.1").is_err());
+        assert!(float.validate_value("-0.1").is_err());
+        assert!(float.validate_value("NaN").is_err());
+        assert!(float.validate_value("").is_err());
+    }
+
+    #[test]
+    fn enums_only_take_their_options() {
+        let options = FieldType::Enum {
+            options: vec!["low".to_string(), "high".to_string()],
+        };
+        assert!(options.validate_value("high").is_ok());
+        assert!(options.validate_value("High").is_err());
+    }
+
+    #[test]
+    fn colors_are_hex_triplets() {
+        for valid in ["#abc", "#A1B2C3"] {
+            assert!(FieldType::Color.validate_value(valid).is_ok(), "{}", valid);
+        }
+        for invalid in ["abc", "#abcd", "#ggg", "red"] {
+            assert!(
+                FieldType::Color.validate_value(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn dates_must_exist_on_the_calendar() {
+        for valid in ["2024-02-29", "2000-02-29", "2023-12-31"] {
+            assert!(FieldType::Date.validate_value(valid).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "2023-02-29",
+            "1900-02-29",
+            "2024-13-01",
+            "2024-1-01",
+            "today",
+        ] {
+            assert!(
+                FieldType::Date.validate_value(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn other_types_parse_their_values() {
+        assert!(FieldType::Boolean.validate_value("true").is_ok());
+        assert!(FieldType::Boolean.validate_value("yes").is_err());
+        assert!(FieldType::Integer.validate_value("-12").is_ok());
+        assert!(FieldType::Integer.validate_value("1.5").is_err());
+        assert!(FieldType::Json.validate_value(r#"{"a": [1]}"#).is_ok());
+        assert!(FieldType::Json.validate_value("{a}").is_err());
+        assert!(FieldType::MultilineText.validate_value("a\nb").is_ok());
+    }
+
+    fn constrained(field_type: FieldType, constraints: FieldConstraints) -> FieldTemplate {
+        FieldTemplate::new("Name", field_type, "").with_constraints(constraints)
+    }
+
+    #[test]
+    fn required_fields_reject_blank_values() {
+        let field = constrained(
+            FieldType::String,
+            FieldConstraints {
+                required: true,
+                ..FieldConstraints::default()
+            },
+        );
+        assert!(matches!(
+            field.check_value("  "),
+            Err(GraphError::FieldRequired { .. })
+        ));
+        assert!(field.check_value("a").is_ok());
+    }
+
+    #[test]
+    fn optional_fields_may_stay_empty() {
+        let field = constrained(
+            FieldType::String,
+            FieldConstraints {
+                pattern: Some("[a-z]+".to_string()),
+                min_length: Some(2),
+                ..FieldConstraints::default()
+            },
+        );
+        assert!(field.check_value("").is_ok());
+    }
+
+    #[test]
+    fn lengths_are_counted_in_characters() {
+        let field = constrained(
+            FieldType::String,
+            FieldConstraints {
+                min_length: Some(2),
+                max_length: Some(5),
+                ..FieldConstraints::default()
+            },
+        );
+        assert!(field.check_value("héllo").is_ok());
+        assert!(matches!(
+            field.check_value("a"),
+            Err(GraphError::FieldLengthOutOfRange { actual: 1, .. })
+        ));
+        assert!(field.check_value("héllos").is_err());
+    }
+
+    #[test]
+    fn numeric_fields_respect_min_and_max() {
+        let field = constrained(
+            FieldType::Integer,
+            FieldConstraints {
+                min: Some(1.0),
+                max: Some(10.0),
+                ..FieldConstraints::default()
+            },
+        );
+        assert!(field.check_value("10").is_ok());
+        assert!(matches!(
+            field.check_value("11"),
+            Err(GraphError::FieldValueOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn patterns_must_match_the_whole_value() {
+        let field = constrained(
+            FieldType::String,
+            FieldConstraints {
+                pattern: Some("[a-z]+|[0-9]+".to_string()),
+                ..FieldConstraints::default()
+            },
+        );
+        assert!(field.check_value("abc").is_ok());
+        assert!(field.check_value("123").is_ok());
+        assert!(matches!(
+            field.check_value("abc1"),
+            Err(GraphError::FieldPatternMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn the_pattern_cache_stays_bounded() {
+        let patterns = PatternCache::default();
+        for n in 0..MAX_CACHED_PATTERNS + 1 {
+            let regex = patterns.compiled(&format!("a{{{}}}", n)).unwrap();
+            assert!(regex.is_match(&"a".repeat(n)));
+        }
+        assert_eq!(patterns.lock().len(), 1);
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported() {
+        let field = constrained(
+            FieldType::String,
+            FieldConstraints {
+                pattern: Some("(".to_string()),
+                ..FieldConstraints::default()
+            },
+        );
+        assert!(matches!(
+            field.check_value("a"),
+            Err(GraphError::InvalidFieldPattern { .. })
+        ));
+    }
+
+    // Children have a unique name of at most five characters
+    fn named_children() -> Graph {
+        let mut name = constrained(
+            FieldType::String,
+            FieldConstraints {
+                max_length: Some(5),
+                unique: true,
+                ..FieldConstraints::default()
+            },
+        );
+        name.id = "name".to_string();
+        let mut child = child_template();
+        child.field_templates.push(name);
+        let mut graph = Graph::new();
+        graph.register_template(child);
+        add_node(&mut graph, CHILD, "c");
+        add_node(&mut graph, CHILD, "c2");
+        graph
+    }
+
+    fn set_name(graph: &mut Graph, node_id: &str, name: &str) -> GraphResult<()> {
+        graph.execute_command(
+            GraphCommand::UpdateField {
+                node_id: node_id.to_string(),
+                field_template_id: "name".to_string(),
+                new_value: name.to_string(),
+            },
+            &EventSystem::new(),
+        )
+    }
+
+    #[test]
+    fn updates_that_break_a_constraint_are_rejected() {
+        let mut graph = named_children();
+        set_name(&mut graph, "c", "ann").unwrap();
+
+        assert!(matches!(
+            set_name(&mut graph, "c2", "ann"),
+            Err(GraphError::DuplicateFieldValue { node_id, .. }) if node_id == "c"
+        ));
+        assert!(set_name(&mut graph, "c2", "annabel").is_err());
+        assert_eq!(graph.node_instances["c2"].fields[0].value, "");
+        assert!(set_name(&mut graph, "c2", "bob").is_ok());
+    }
+
+    #[test]
+    fn field_problems_are_reported_by_kind() {
+        let mut graph = named_children();
+        for (node_id, name) in [("c", "ann"), ("c2", "ann")] {
+            graph.node_instances.get_mut(node_id).unwrap().fields[0].value = name.to_string();
+        }
+        add_node(&mut graph, CHILD, "c3");
+        graph.node_instances.get_mut("c3").unwrap().fields[0].value = "annabel".to_string();
+
+        let report = graph.validate();
+
+        assert_eq!(
+            codes(&report),
+            vec![
+                ValidationCode::DuplicateFieldValue,
+                ValidationCode::DuplicateFieldValue,
+                ValidationCode::FieldConstraintViolated
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::cell::RefCell;
+
+use graph_canvas_core::clipboard::ClipboardPayload;
+
+use crate::{
+    errors::{GraphError, GraphResult},
+    graph::GraphCommand,
+    GraphCanvas,
+};
+
+// How far each successive paste is shifted so copies do not stack exactly on the originals
+const PASTE_OFFSET: f64 = 30.0;
+
+// One clipboard per page, so nodes can be copied from one canvas and pasted into another.
+// The payload is kept serialized, the same form hosts pass around with `paste_json`.
+struct SharedClipboard {
+    payload: Option<String>,
+    paste_count: u32,
+}
+
+thread_local! {
+    static CLIPBOARD: RefCell<SharedClipboard> = const {
+        RefCell::new(SharedClipboard {
+            payload: None,
+            paste_count: 0,
+        })
+    };
+}
+
+/// Copy, cut and paste through the clipboard shared by every canvas on the page.
+impl GraphCanvas {
+    /// Copies the selected nodes, the connections among them and their field values to the
+    /// page clipboard, and returns the serialized payload.
+    pub fn copy_selection(&self) -> GraphResult<String> {
+        let selected = self.selected_nodes()?;
+        let payload = self
+            .graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .copy_nodes(&selected)?;
+        let json = payload.to_json()?;
+        CLIPBOARD.with(|clipboard| {
+            let mut clipboard = clipboard.borrow_mut();
+            clipboard.payload = Some(json.clone());
+            clipboard.paste_count = 0;
+        });
+        Ok(json)
+    }
+
+    /// Copies the selection, then deletes it as one undoable command.
+    pub fn cut_selection(&self) -> GraphResult<String> {
+        let json = self.copy_selection()?;
+        self.delete_selection()?;
+        Ok(json)
+    }
+
+    /// Pastes the page clipboard and selects the new nodes. Returns their ids.
+    pub fn paste(&self) -> GraphResult<Vec<String>> {
+        let (json, paste_count) = CLIPBOARD.with(|clipboard| {
+            let mut clipboard = clipboard.borrow_mut();
+            clipboard.paste_count += 1;
+            (clipboard.payload.clone(), clipboard.paste_count)
+        });
+        let Some(json) = json else {
+            return Ok(vec![]);
+        };
+        let offset = PASTE_OFFSET * paste_count as f64;
+        self.paste_payload(&ClipboardPayload::from_json(&json)?, offset)
+    }
+
+    /// Pastes a payload produced by `copy_selection` (possibly on another canvas).
+    pub fn paste_json(&self, json: &str) -> GraphResult<Vec<String>> {
+        self.paste_payload(&ClipboardPayload::from_json(json)?, PASTE_OFFSET)
+    }
+
+    fn paste_payload(&self, payload: &ClipboardPayload, offset: f64) -> GraphResult<Vec<String>> {
+        if payload.is_empty() {
+            return Ok(vec![]);
+        }
+        let nodes = self
+            .graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .resolve_clipboard(payload)
+            .instantiate(offset, offset);
+        let ids = nodes
+            .iter()
+            .map(|node| node.instance_id.clone())
+            .collect::<Vec<_>>();
+        self.execute(GraphCommand::PasteNodes(nodes))?;
+        self.set_selection(ids.clone())?;
+        Ok(ids)
+    }
+}
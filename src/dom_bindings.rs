@@ -0,0 +1,265 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use wasm_bindgen::{convert::FromWasmAbi, prelude::*, JsCast};
+use web_sys::{window, Element, EventTarget, ResizeObserver};
+
+// The frame callback refers to itself so it can schedule the next frame
+pub(crate) type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+thread_local! {
+    // Event listeners must be Send, so they cannot hold the bindings. They look them up here
+    // by canvas id instead.
+    static REDRAW_TARGETS: RefCell<HashMap<String, Weak<RefCell<DomBindings>>>> =
+        RefCell::new(HashMap::new());
+}
+
+pub(crate) fn register_redraw_target(canvas_id: &str, bindings: &Rc<RefCell<DomBindings>>) {
+    REDRAW_TARGETS.with(|targets| {
+        targets
+            .borrow_mut()
+            .insert(canvas_id.to_string(), Rc::downgrade(bindings));
+    });
+}
+
+pub(crate) fn unregister_redraw_target(canvas_id: &str) {
+    REDRAW_TARGETS.with(|targets| {
+        targets.borrow_mut().remove(canvas_id);
+    });
+}
+
+/// Schedules a frame for the canvas with this id, if it is still alive.
+pub(crate) fn request_redraw_for(canvas_id: &str) {
+    let bindings = REDRAW_TARGETS.with(|targets| {
+        targets
+            .borrow()
+            .get(canvas_id)
+            .and_then(|bindings| bindings.upgrade())
+    });
+    if let Some(bindings) = bindings {
+        // Already borrowed means a frame is being scheduled right now anyway
+        if let Ok(mut bindings) = bindings.try_borrow_mut() {
+            bindings.request_frame();
+        }
+    }
+}
+
+pub(crate) struct InlineEditor {
+    pub element: web_sys::HtmlElement,
+    pub node_id: String,
+    pub field_template_id: String,
+}
+
+struct Listener {
+    target: EventTarget,
+    event: String,
+    callback: js_sys::Function,
+    // Keeps the Rust side of `callback` alive until the listener is removed
+    _closure: Box<dyn Any>,
+}
+
+impl Listener {
+    fn remove(self) {
+        let _ = self
+            .target
+            .remove_event_listener_with_callback(&self.event, &self.callback);
+    }
+}
+
+/// Everything a canvas has hooked into the page, kept so `GraphCanvas::destroy` can
+/// unhook it again instead of leaking closures for the life of the page.
+#[derive(Default)]
+pub(crate) struct DomBindings {
+    listeners: Vec<Listener>,
+    // Inputs in the toolbar's field editor, rebuilt whenever another node is selected
+    field_editor_listeners: Vec<Listener>,
+    // The overlay for editing a field on the canvas, while one is open
+    inline_editor: Option<InlineEditor>,
+    inline_editor_listeners: Vec<Listener>,
+    resize_observer: Option<ResizeObserver>,
+    resize_callback: Option<Closure<dyn FnMut(js_sys::Array)>>,
+    frame_callback: Option<FrameCallback>,
+    // Id of the frame already requested, so requests between frames collapse into one
+    animation_frame: Option<i32>,
+    // Flushes a batched sync adapter, as (interval id, callback)
+    sync_interval: Option<(i32, Closure<dyn FnMut()>)>,
+    // Top-level elements added to the host's container
+    elements: Vec<Element>,
+    destroyed: bool,
+}
+
+impl DomBindings {
+    pub fn listen<T: FromWasmAbi + 'static>(
+        &mut self,
+        target: &EventTarget,
+        event: &str,
+        closure: Closure<dyn FnMut(T)>,
+    ) -> Result<(), JsValue> {
+        let listener = Self::add_listener(target, event, closure)?;
+        self.listeners.push(listener);
+        Ok(())
+    }
+
+    pub fn listen_field_editor<T: FromWasmAbi + 'static>(
+        &mut self,
+        target: &EventTarget,
+        event: &str,
+        closure: Closure<dyn FnMut(T)>,
+    ) -> Result<(), JsValue> {
+        let listener = Self::add_listener(target, event, closure)?;
+        self.field_editor_listeners.push(listener);
+        Ok(())
+    }
+
+    fn add_listener<T: FromWasmAbi + 'static>(
+        target: &EventTarget,
+        event: &str,
+        closure: Closure<dyn FnMut(T)>,
+    ) -> Result<Listener, JsValue> {
+        let callback = closure.as_ref().unchecked_ref::<js_sys::Function>().clone();
+        target.add_event_listener_with_callback(event, &callback)?;
+        Ok(Listener {
+            target: target.clone(),
+            event: event.to_string(),
+            callback,
+            _closure: Box::new(closure),
+        })
+    }
+
+    // Called before the field editor's inputs are replaced
+    pub fn clear_field_editor(&mut self) {
+        for listener in self.field_editor_listeners.drain(..) {
+            listener.remove();
+        }
+    }
+
+    pub fn listen_inline_editor<T: FromWasmAbi + 'static>(
+        &mut self,
+        target: &EventTarget,
+        event: &str,
+        closure: Closure<dyn FnMut(T)>,
+    ) -> Result<(), JsValue> {
+        let listener = Self::add_listener(target, event, closure)?;
+        self.inline_editor_listeners.push(listener);
+        Ok(())
+    }
+
+    pub fn set_inline_editor(&mut self, editor: InlineEditor) {
+        self.inline_editor = Some(editor);
+    }
+
+    // The open editor, if it is still on the page. An editor removes itself when it commits
+    // or cancels, but its listeners stay here until the next editor opens.
+    pub fn inline_editor(&self) -> Option<&InlineEditor> {
+        self.inline_editor
+            .as_ref()
+            .filter(|editor| editor.element.is_connected())
+    }
+
+    // Must not be called from the editor's own listeners, since it drops them. They are
+    // detached before the element goes so its blur handler does not commit on the way out.
+    pub fn close_inline_editor(&mut self) {
+        for listener in self.inline_editor_listeners.drain(..) {
+            listener.remove();
+        }
+        if let Some(editor) = self.inline_editor.take() {
+            editor.element.remove();
+        }
+    }
+
+    pub fn set_resize_observer(
+        &mut self,
+        observer: ResizeObserver,
+        closure: Closure<dyn FnMut(js_sys::Array)>,
+    ) {
+        self.resize_observer = Some(observer);
+        self.resize_callback = Some(closure);
+    }
+
+    pub fn set_frame_callback(&mut self, frame_callback: FrameCallback) {
+        self.frame_callback = Some(frame_callback);
+    }
+
+    /// Asks the browser for one frame, unless one is already on its way.
+    pub fn request_frame(&mut self) {
+        if self.destroyed || self.animation_frame.is_some() {
+            return;
+        }
+        let (Some(window), Some(frame_callback)) = (window(), &self.frame_callback) else {
+            return;
+        };
+        if let Some(callback) = frame_callback.borrow().as_ref() {
+            if let Ok(request_id) =
+                window.request_animation_frame(callback.as_ref().unchecked_ref())
+            {
+                self.animation_frame = Some(request_id);
+            }
+        }
+    }
+
+    // Called at the top of the frame callback so redraws requested while drawing get a
+    // frame of their own
+    pub fn frame_started(&mut self) {
+        self.animation_frame = None;
+    }
+
+    pub fn set_sync_interval(&mut self, interval_ms: u32, callback: Closure<dyn FnMut()>) {
+        self.clear_sync_interval();
+        if self.destroyed {
+            return;
+        }
+        let Some(window) = window() else {
+            return;
+        };
+        if let Ok(interval_id) = window.set_interval_with_callback_and_timeout_and_arguments_0(
+            callback.as_ref().unchecked_ref(),
+            interval_ms as i32,
+        ) {
+            self.sync_interval = Some((interval_id, callback));
+        }
+    }
+
+    pub fn clear_sync_interval(&mut self) {
+        if let Some((interval_id, _)) = self.sync_interval.take() {
+            if let Some(window) = window() {
+                window.clear_interval_with_handle(interval_id);
+            }
+        }
+    }
+
+    pub fn add_element(&mut self, element: Element) {
+        self.elements.push(element);
+    }
+
+    /// Removes listeners and elements, disconnects the resize observer and stops the render
+    /// loop. Safe to call more than once.
+    pub fn release(&mut self) {
+        self.destroyed = true;
+        self.clear_field_editor();
+        self.close_inline_editor();
+        for listener in self.listeners.drain(..) {
+            listener.remove();
+        }
+        if let Some(observer) = self.resize_observer.take() {
+            observer.disconnect();
+        }
+        self.resize_callback = None;
+        self.clear_sync_interval();
+        if let Some(request_id) = self.animation_frame.take() {
+            if let Some(window) = window() {
+                let _ = window.cancel_animation_frame(request_id);
+            }
+        }
+        // The frame callback holds a clone of the canvas; dropping it breaks that cycle
+        if let Some(frame_callback) = self.frame_callback.take() {
+            frame_callback.borrow_mut().take();
+        }
+        for element in self.elements.drain(..) {
+            element.remove();
+        }
+    }
+}
@@ -1,17 +1,25 @@
-use std::{cell::RefCell, collections::HashMap, f64::consts::PI, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet},
+    f64::consts::PI,
+    rc::Rc,
+    sync::atomic::Ordering,
+};
 use wasm_bindgen::prelude::*;
 use web_sys::{window, CanvasRenderingContext2d};
 
 use crate::{
+    dom_bindings::{register_redraw_target, FrameCallback},
     errors::GraphError,
+    events::ContextMenuTarget,
     graph::{
-        Connection, FieldType, Graph, NodeInstance, SlotInstance, SlotPosition, SlotTemplate,
-        SlotType,
-    },
-    interaction::{
-        ContextMenu, ContextMenuAction, ContextMenuItem, ContextMenuTarget, InteractionState,
-        Rectangle,
+        Connection, FieldInstance, FieldTemplate, FieldType, Graph, NodeInstance, NodeTemplate,
+        NodeTemplateInfo, SlotInstance, SlotPosition, SlotTemplate, SlotType,
     },
+    interaction::{ContextMenu, ContextMenuAction, ContextMenuItem, InteractionState, Rectangle},
+    layout::ViewTransform,
+    log,
+    validation::{ValidationIssue, ValidationSeverity},
     GraphCanvas,
 };
 
@@ -22,6 +30,7 @@ impl GraphCanvas {
         &self,
         context: &CanvasRenderingContext2d,
         menu: &mut ContextMenu,
+        selection: &BTreeSet<String>,
         graph: &Graph,
     ) -> Result<(), JsValue> {
         const PADDING: f64 = 10.0;
@@ -29,7 +38,7 @@ impl GraphCanvas {
         const TITLE_HEIGHT: f64 = 25.0;
 
         // Get menu items based on target type
-        let mut items = self.get_context_menu_items(&menu.target_type, graph)?;
+        let mut items = self.get_context_menu_items(&menu.target_type, selection, graph)?;
         let title = menu.target_type.get_title(graph);
 
         let menu_height = TITLE_HEIGHT + (items.len() as f64 * ITEM_HEIGHT) + (PADDING * 2.0);
@@ -95,8 +104,9 @@ impl GraphCanvas {
                     .slots
                     .iter()
                     .find(|s| s.slot_template_id == *connection_drag.from_slot)
+                    .and_then(|s| s.capabilities(graph).ok())
                 {
-                    let slot_template = slot.capabilities(graph).template;
+                    let slot_template = slot.template;
                     let (start_x, start_y) =
                         self.calculate_slot_position(slot_template, node.instance, graph);
 
@@ -149,7 +159,7 @@ impl GraphCanvas {
         let radius = instance.radius - 2.0; // Slightly smaller to account for stroke
 
         // Selected Effect
-        if ix.currently_selected_node_instance.as_ref() == Some(&instance.instance_id) {
+        if ix.selected_nodes.contains(&instance.instance_id) {
             context.set_shadow_color("green");
             context.set_shadow_blur(20.0);
             context.set_shadow_offset_x(0.0);
@@ -176,38 +186,40 @@ impl GraphCanvas {
         context.set_shadow_color("transparent");
         context.set_shadow_blur(0.0);
 
+        // Flag nodes that break their template's rules
+        let node_issues = ix
+            .validation_report
+            .issues_for_node(&instance.instance_id)
+            .filter(|issue| issue.slot_template_id.is_none())
+            .chain(
+                ix.validation_report
+                    .issues_for_template(&instance.template_id),
+            );
+        if let Some(color) = Self::validation_color(node_issues) {
+            context.begin_path();
+            context.set_stroke_style_str(color);
+            context.set_line_width(3.0);
+            context.arc(center_x, center_y, radius + 4.0, 0.0, 2.0 * PI)?;
+            context.stroke();
+            context.set_line_width(1.0);
+            context.set_stroke_style_str("#000000");
+        }
+
         // Draw node title - move it up to make room for fields
         context.set_font("16px Arial");
         context.set_text_align("center");
         context.set_fill_style_str("#000000");
 
-        // Adjust y position based on number of fields (if any)
-        let title_y = if !instance.fields.is_empty() {
-            center_y - (instance.fields.len() as f64 * 15.0) / 2.0 - 10.0
-        } else {
-            center_y
-        };
-
-        context.fill_text(&template.name, center_x, title_y)?;
+        context.fill_text(&template.name, center_x, Self::node_title_y(instance))?;
 
         // Draw fields below the title
         if !instance.fields.is_empty() {
             context.set_font("12px Arial");
             context.set_text_align("center");
 
-            let mut y_offset = title_y + 20.0; // Start below the title
-
-            for field_instance in &instance.fields {
-                // Get the field template to access name and type
-                if let Some(field_template) = template
-                    .field_templates
-                    .iter()
-                    .find(|ft| ft.id == field_instance.field_template_id)
-                {
-                    let field_text = format!("{}: {}", field_template.name, field_instance.value);
-                    context.fill_text(&field_text, center_x, y_offset)?;
-                    y_offset += 15.0; // Move down for next field
-                }
+            for (field_instance, field_template, y) in Self::field_rows(instance, template) {
+                let field_text = format!("{}: {}", field_template.name, field_instance.value);
+                context.fill_text(&field_text, center_x, y)?;
             }
         }
 
@@ -322,6 +334,20 @@ impl GraphCanvas {
         context.set_shadow_color("transparent");
         context.set_shadow_blur(0.0);
 
+        // Outline slots that are under-filled, over-full or hold disallowed connections
+        if let Some(color) = Self::validation_color(
+            ix.validation_report
+                .issues_for_slot(&node.instance_id, &slot_instance.slot_template_id),
+        ) {
+            context.begin_path();
+            context.set_stroke_style_str(color);
+            context.set_line_width(2.0);
+            context.arc(x, y, self.config.slot_radius + 3.0, 0.0, 2.0 * PI)?;
+            context.stroke();
+            context.set_line_width(1.0);
+            context.set_stroke_style_str("#000000");
+        }
+
         // Draw slot label (dynamically positioned based on slot angle from center)
         context.set_font("12px Arial");
         context.set_fill_style_str("#000000");
@@ -348,10 +374,138 @@ impl GraphCanvas {
         Ok(())
     }
 
-    // Storage for slot position history to reduce flickering
-    thread_local! {
-        static POSITION_HISTORY: std::cell::RefCell<HashMap<String, HashMap<String, f64>>> =
-            std::cell::RefCell::new(HashMap::new());
+    // Errors win over warnings; None when there is nothing to flag
+    // Background grid over the visible area, drawn in graph coordinates so it pans and
+    // zooms with the nodes
+    fn draw_grid(
+        &self,
+        context: &CanvasRenderingContext2d,
+        canvas: &web_sys::HtmlCanvasElement,
+        view_transform: &ViewTransform,
+    ) {
+        let mut spacing = self.config.grid_size;
+        if spacing <= 0.0 {
+            return;
+        }
+        // Zoomed far out, skip lines rather than fill the screen with them
+        while spacing * view_transform.zoom < 8.0 {
+            spacing *= 2.0;
+        }
+
+        let (left, top) = view_transform.screen_to_graph(0.0, 0.0);
+        let (right, bottom) =
+            view_transform.screen_to_graph(canvas.width() as f64, canvas.height() as f64);
+
+        context.begin_path();
+        let mut x = (left / spacing).floor() * spacing;
+        while x <= right {
+            context.move_to(x, top);
+            context.line_to(x, bottom);
+            x += spacing;
+        }
+        let mut y = (top / spacing).floor() * spacing;
+        while y <= bottom {
+            context.move_to(left, y);
+            context.line_to(right, y);
+            y += spacing;
+        }
+        context.set_stroke_style_str("#e8e8e8");
+        // One screen pixel wide at any zoom
+        context.set_line_width(1.0 / view_transform.zoom);
+        context.stroke();
+        context.set_line_width(1.0);
+        context.set_stroke_style_str("#000000");
+    }
+
+    fn validation_color<'a>(
+        issues: impl Iterator<Item = &'a ValidationIssue>,
+    ) -> Option<&'static str> {
+        let mut color = None;
+        for issue in issues {
+            match issue.severity {
+                ValidationSeverity::Error => return Some("#d32f2f"),
+                ValidationSeverity::Warning => color = Some("#f9a825"),
+            }
+        }
+        color
+    }
+
+    fn draw_validation_tooltip(
+        &self,
+        context: &CanvasRenderingContext2d,
+        graph: &Graph,
+        ix: &InteractionState,
+    ) -> Result<(), JsValue> {
+        const PADDING: f64 = 6.0;
+        const LINE_HEIGHT: f64 = 16.0;
+
+        let report = &ix.validation_report;
+        let (messages, anchor_x, anchor_y): (Vec<&str>, f64, f64) =
+            if let Some((node_id, slot_id)) = &ix.hovered_slot {
+                let Some(node) = graph.node_instances.get(node_id) else {
+                    return Ok(());
+                };
+                let Some(slot_template) = graph
+                    .node_templates
+                    .get(&node.template_id)
+                    .and_then(|template| template.get_slot_template(slot_id))
+                else {
+                    return Ok(());
+                };
+                let (x, y) = self.calculate_slot_position(slot_template, node, graph);
+                (
+                    report
+                        .issues_for_slot(node_id, slot_id)
+                        .map(|issue| issue.message.as_str())
+                        .collect(),
+                    x + self.config.slot_radius * 2.0,
+                    y + self.config.slot_radius * 2.0,
+                )
+            } else if let Some(node_id) = &ix.hovered_node {
+                let Some(node) = graph.node_instances.get(node_id) else {
+                    return Ok(());
+                };
+                (
+                    report
+                        .issues_for_node(node_id)
+                        .chain(report.issues_for_template(&node.template_id))
+                        .map(|issue| issue.message.as_str())
+                        .collect(),
+                    node.x + node.radius * 2.0 + 10.0,
+                    node.y,
+                )
+            } else {
+                return Ok(());
+            };
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        context.set_font("12px Arial");
+        context.set_text_align("left");
+        let mut width: f64 = 0.0;
+        for message in &messages {
+            width = width.max(context.measure_text(message)?.width());
+        }
+        let height = messages.len() as f64 * LINE_HEIGHT + PADDING * 2.0;
+
+        context.begin_path();
+        context.set_fill_style_str("#fff8e1");
+        context.set_stroke_style_str("#d32f2f");
+        context.rect(anchor_x, anchor_y, width + PADDING * 2.0, height);
+        context.fill();
+        context.stroke();
+        context.set_stroke_style_str("#000000");
+
+        context.set_fill_style_str("#000000");
+        for (i, message) in messages.iter().enumerate() {
+            context.fill_text(
+                message,
+                anchor_x + PADDING,
+                anchor_y + PADDING + (i as f64 + 1.0) * LINE_HEIGHT - 4.0,
+            )?;
+        }
+        Ok(())
     }
 
     pub(crate) fn get_bezier_point(
@@ -377,15 +531,27 @@ impl GraphCanvas {
     pub fn get_context_menu_items(
         &self,
         target: &ContextMenuTarget,
+        selection: &BTreeSet<String>,
         graph: &Graph,
     ) -> Result<Vec<ContextMenuItem>, JsValue> {
         match target {
-            ContextMenuTarget::Node(_) => Ok(vec![ContextMenuItem {
-                label: "Delete Node".to_string(),
-                action: ContextMenuAction::Delete,
-                color: "#ff0000".to_string(),
-                bounds: None,
-            }]),
+            ContextMenuTarget::Node(node_id) => {
+                let mut items = vec![ContextMenuItem {
+                    label: "Delete Node".to_string(),
+                    action: ContextMenuAction::Delete,
+                    color: "#ff0000".to_string(),
+                    bounds: None,
+                }];
+                if selection.len() > 1 && selection.contains(node_id) {
+                    items.push(ContextMenuItem {
+                        label: format!("Delete {} Selected Nodes", selection.len()),
+                        action: ContextMenuAction::DeleteSelection,
+                        color: "#ff0000".to_string(),
+                        bounds: None,
+                    });
+                }
+                Ok(items)
+            }
             ContextMenuTarget::Connection { .. } => Ok(vec![ContextMenuItem {
                 label: "Delete Connection".to_string(),
                 action: ContextMenuAction::Delete,
@@ -424,7 +590,7 @@ impl GraphCanvas {
                     .ok_or_else(|| JsValue::from_str("Field not found"))?;
 
                 // Return different menu items based on field type
-                match field_template.field_type {
+                match &field_template.field_type {
                     FieldType::Boolean => Ok(vec![
                         ContextMenuItem {
                             label: "Set True".to_string(),
@@ -441,7 +607,7 @@ impl GraphCanvas {
                     ]),
                     FieldType::Integer => {
                         // For integers, we provide some increment/decrement options
-                        let current_value = field_instance.value.parse::<i32>().unwrap_or(0);
+                        let current_value = field_instance.value.parse::<i64>().unwrap_or(0);
                         Ok(vec![
                             ContextMenuItem {
                                 label: format!("Current: {}", current_value),
@@ -463,11 +629,74 @@ impl GraphCanvas {
                             },
                         ])
                     }
-                    FieldType::String => {
-                        // For strings we just show the current value and edit option
+                    FieldType::Float { min, max, step } => {
+                        // Step within the declared range
+                        let current_value = field_instance.value.parse::<f64>().unwrap_or(0.0);
+                        let step = step.unwrap_or(1.0);
+                        let clamp = |value: f64| {
+                            value
+                                .max(min.unwrap_or(f64::MIN))
+                                .min(max.unwrap_or(f64::MAX))
+                        };
+                        Ok(vec![
+                            ContextMenuItem {
+                                label: format!("Current: {}", current_value),
+                                action: ContextMenuAction::EditField,
+                                color: "#444444".to_string(),
+                                bounds: None,
+                            },
+                            ContextMenuItem {
+                                label: format!("Increase (+{})", step),
+                                action: ContextMenuAction::SetFloatField(clamp(
+                                    current_value + step,
+                                )),
+                                color: "#0077ff".to_string(),
+                                bounds: None,
+                            },
+                            ContextMenuItem {
+                                label: format!("Decrease (-{})", step),
+                                action: ContextMenuAction::SetFloatField(clamp(
+                                    current_value - step,
+                                )),
+                                color: "#0077ff".to_string(),
+                                bounds: None,
+                            },
+                        ])
+                    }
+                    FieldType::Enum { options } => {
+                        // One item per option, the current one greyed out
+                        Ok(options
+                            .iter()
+                            .map(|option| ContextMenuItem {
+                                label: option.clone(),
+                                action: ContextMenuAction::SetStringField(option.clone()),
+                                color: if *option == field_instance.value {
+                                    "#444444".to_string()
+                                } else {
+                                    "#0077ff".to_string()
+                                },
+                                bounds: None,
+                            })
+                            .collect())
+                    }
+                    FieldType::Color => Ok(vec![ContextMenuItem {
+                        label: format!("Current: {}", field_instance.value),
+                        action: ContextMenuAction::EditField,
+                        // Shown in the color itself
+                        color: field_instance.value.clone(),
+                        bounds: None,
+                    }]),
+                    FieldType::String
+                    | FieldType::Date
+                    | FieldType::MultilineText
+                    | FieldType::Json => {
+                        // For text-like fields we just show the current value and edit option
                         Ok(vec![
                             ContextMenuItem {
-                                label: format!("Current: {}", field_instance.value),
+                                label: format!(
+                                    "Current: {}",
+                                    Self::field_value_preview(&field_instance.value)
+                                ),
                                 action: ContextMenuAction::EditField,
                                 color: "#444444".to_string(),
                                 bounds: None,
@@ -485,6 +714,57 @@ impl GraphCanvas {
         }
     }
 
+    // Baseline of the node's title, moved up to make room for its fields
+    fn node_title_y(instance: &NodeInstance) -> f64 {
+        let center_y = instance.y + instance.radius;
+        if instance.fields.is_empty() {
+            center_y
+        } else {
+            center_y - (instance.fields.len() as f64 * 15.0) / 2.0 - 10.0
+        }
+    }
+
+    /// The fields drawn on a node with the baseline of each row, top to bottom. Fields whose
+    /// template is missing are not drawn and take up no row.
+    pub(crate) fn field_rows<'a>(
+        instance: &'a NodeInstance,
+        template: &'a NodeTemplate,
+    ) -> impl Iterator<Item = (&'a FieldInstance, &'a FieldTemplate, f64)> + 'a {
+        let first_row_y = Self::node_title_y(instance) + 20.0;
+        instance
+            .fields
+            .iter()
+            .filter_map(|field_instance| {
+                template
+                    .field_templates
+                    .iter()
+                    .find(|ft| ft.id == field_instance.field_template_id)
+                    .map(|field_template| (field_instance, field_template))
+            })
+            .enumerate()
+            .map(move |(row, (field_instance, field_template))| {
+                (
+                    field_instance,
+                    field_template,
+                    first_row_y + row as f64 * 15.0,
+                )
+            })
+    }
+
+    // First line of a field value, shortened to fit a menu item
+    fn field_value_preview(value: &str) -> String {
+        const MAX_CHARS: usize = 30;
+        let first_line = value.lines().next().unwrap_or_default();
+        if first_line.chars().count() > MAX_CHARS || first_line.len() < value.trim_end().len() {
+            format!(
+                "{}…",
+                first_line.chars().take(MAX_CHARS).collect::<String>()
+            )
+        } else {
+            first_line.to_string()
+        }
+    }
+
     pub(crate) fn distance_to_bezier_curve(
         &self,
         point: (f64, f64),
@@ -529,7 +809,7 @@ impl GraphCanvas {
 
 // New struct to store slot position data with explicit initialization state
 #[derive(Clone, Debug)]
-struct SlotPositionCache {
+pub(crate) struct SlotPositionCache {
     // Tracks whether positions have been initialized
     initialized: bool,
     // Stores slot angles for each node
@@ -539,7 +819,7 @@ struct SlotPositionCache {
 }
 
 impl SlotPositionCache {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         SlotPositionCache {
             initialized: false,
             node_angles: HashMap::new(),
@@ -581,6 +861,12 @@ impl SlotPositionCache {
         self.initialized
     }
 
+    fn clear(&mut self) {
+        self.initialized = false;
+        self.node_angles.clear();
+        self.node_positions.clear();
+    }
+
     fn clear_node(&mut self, node_id: &str) {
         self.node_angles.remove(node_id);
         self.node_positions.remove(node_id);
@@ -593,8 +879,7 @@ impl GraphCanvas {
     // Create a new initialization method to pre-calculate all slot positions
     pub fn initialize_slot_positions(&self, graph: &Graph) {
         // Check initialization status first
-        let already_initialized =
-            Self::POSITION_CACHE.with(|cache| cache.borrow().is_initialized());
+        let already_initialized = self.slot_positions.borrow().is_initialized();
 
         if already_initialized {
             return;
@@ -610,44 +895,55 @@ impl GraphCanvas {
         }
 
         // Mark as initialized - separate operation to avoid borrow conflicts
-        Self::POSITION_CACHE.with(|cache| {
-            let mut cache_ref = cache.borrow_mut();
-            cache_ref.mark_initialized();
-        });
+        self.slot_positions.borrow_mut().mark_initialized();
     }
 
+    // Forget all cached slot positions so they are recomputed from scratch on the next frame
+    pub(crate) fn reset_slot_positions(&self) {
+        self.slot_positions.borrow_mut().clear();
+    }
+
+    /// Draws a frame whenever something requests one (see `request_redraw`) and idles
+    /// otherwise.
     pub fn start_render_loop(&self) -> Result<(), GraphError> {
         // Initialize positions first if needed
         if let Ok(graph) = self.graph.try_lock() {
             self.initialize_slot_positions(&graph);
         }
 
-        let f: Rc<RefCell<Option<Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
-        let g = f.clone();
+        let f: FrameCallback = Rc::new(RefCell::new(None));
         let canvas = self.clone();
 
-        *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
-            // Render the graph using the cloned self
-            if let Ok(_) = canvas.render() {
-                // Schedule next frame
-                window()
-                    .unwrap()
-                    .request_animation_frame(f.borrow().as_ref().unwrap().as_ref().unchecked_ref())
-                    .unwrap();
-            } else {
-                return;
+        *f.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+            canvas.dom_bindings.borrow_mut().frame_started();
+            match canvas.render_frame() {
+                // Something is still moving without further input
+                Ok(true) => canvas.request_redraw(),
+                Ok(false) => {}
+                Err(err) => log(&format!("Render failed: {:?}", err.as_string())),
             }
         }) as Box<dyn FnMut()>));
 
-        window()
-            .unwrap()
-            .request_animation_frame(g.borrow().as_ref().unwrap().as_ref().unchecked_ref())
-            .map_err(GraphError::SetupFailed)?;
+        register_redraw_target(&self.canvas_id, &self.dom_bindings);
+        self.dom_bindings.borrow_mut().set_frame_callback(f);
+        self.request_redraw();
         Ok(())
     }
 
-    // Rendering - skips if locked
+    /// Schedules a redraw on the next animation frame. Requests made before that frame are
+    /// merged, so call this freely after anything that changes what is on screen.
+    pub fn request_redraw(&self) {
+        if let Ok(mut dom_bindings) = self.dom_bindings.try_borrow_mut() {
+            dom_bindings.request_frame();
+        }
+    }
+
     pub fn render(&self) -> Result<(), JsValue> {
+        self.render_frame().map(|_| ())
+    }
+
+    // Rendering - skips if locked. Returns whether another frame is needed.
+    fn render_frame(&self) -> Result<bool, JsValue> {
         let element = window()
             .unwrap()
             .document()
@@ -669,16 +965,17 @@ impl GraphCanvas {
             (self.graph.try_lock(), &mut self.interaction.try_lock())
         {
             // Check if we need to initialize positions
-            Self::POSITION_CACHE.with(|cache| {
-                if !cache.borrow().is_initialized() {
-                    self.initialize_slot_positions(&graph);
-                }
-            });
+            if !self.slot_positions.borrow().is_initialized() {
+                self.initialize_slot_positions(&graph);
+            }
 
-            self.do_render(&context, &mut graph, interaction)?;
+            // A held node keeps the force simulation stepping even when the pointer is still;
+            // without physics, pointer moves request their own frames
+            self.do_render(&context, &mut graph, interaction)
+        } else {
+            // Someone else holds the state; try again next frame
+            Ok(true)
         }
-
-        Ok(())
     }
 
     // Helper method to separate rendering logic
@@ -687,14 +984,19 @@ impl GraphCanvas {
         context: &CanvasRenderingContext2d,
         graph: &mut Graph,
         interaction: &mut InteractionState,
-    ) -> Result<(), JsValue> {
+    ) -> Result<bool, JsValue> {
         // Run a simulation step if we're holding a node and physics is enabled
+        let mut simulating = false;
         if interaction.is_dragging_node {
             if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
                 // The physics check is now inside run_simulation_step
-                layout_engine.run_simulation_step(graph);
+                simulating = layout_engine.run_simulation_step(graph);
             }
         }
+        if self.validation_stale.swap(false, Ordering::Relaxed) {
+            interaction.validation_report = graph.validate();
+        }
+
         let canvas = window()
             .unwrap()
             .document()
@@ -721,6 +1023,10 @@ impl GraphCanvas {
             interaction.view_transform.zoom,
         )?;
 
+        if interaction.snap_to_grid {
+            self.draw_grid(context, &canvas, &interaction.view_transform);
+        }
+
         // CHANGED ORDER: Draw nodes first, then connections
         // This ensures slot positions are calculated before drawing connections
         for instance in graph.node_instances.values() {
@@ -730,26 +1036,40 @@ impl GraphCanvas {
         // Now that nodes and slots are drawn, draw connections with updated positions
         self.draw_connections(context, graph, interaction)?;
 
+        // Describe any problems with the hovered node or slot
+        self.draw_validation_tooltip(context, graph, interaction)?;
+
+        // Keep any open inline field editor over its field
+        self.position_inline_editor(graph, &interaction.view_transform);
+
+        // Keep the toolbar's remaining work checklist in step with the graph
+        self.refresh_session_checklist(graph, &interaction.validation_report);
+
         // Draw context menu if it exists
         if let Some(menu) = &mut interaction.context_menu {
-            self.draw_context_menu(context, menu, graph)?;
+            self.draw_context_menu(context, menu, &interaction.selected_nodes, graph)?;
         }
 
         // Draw dragging connection if it exists
         self.draw_dragging_connection(context, interaction, graph);
 
+        // Draw the box selection in progress
+        if let Some(selection_box) = &interaction.selection_box {
+            let bounds = selection_box.bounds();
+            context.set_fill_style_str("rgba(33, 150, 243, 0.1)");
+            context.set_stroke_style_str("#2196f3");
+            context.fill_rect(bounds.x, bounds.y, bounds.width, bounds.height);
+            context.stroke_rect(bounds.x, bounds.y, bounds.width, bounds.height);
+            context.set_stroke_style_str("#000000");
+        }
+
         // Restore the original transform
         context.restore();
-        Ok(())
+        Ok(simulating)
     }
 
     // ... [other methods remain the same] ...
 
-    // Replace thread_local with a more robust structure
-    thread_local! {
-        static POSITION_CACHE: RefCell<SlotPositionCache> = RefCell::new(SlotPositionCache::new());
-    }
-
     // Calculate dynamic slot positions based on connections
     fn calculate_slot_positions(
         &self,
@@ -757,7 +1077,10 @@ impl GraphCanvas {
         graph: &Graph,
         is_initialization: bool,
     ) -> HashMap<String, (f64, f64)> {
-        let node_template = node.capabilities(graph).template;
+        let Ok(node_capabilities) = node.capabilities(graph) else {
+            return HashMap::new();
+        };
+        let node_template = node_capabilities.template;
         let center_x = node.x + node.radius;
         let center_y = node.y + node.radius;
         let radius = node.radius;
@@ -773,15 +1096,13 @@ impl GraphCanvas {
         // Copy all data we need to local variables to avoid borrow issues
         let prev_angles: HashMap<String, f64> = {
             let mut angles = HashMap::new();
-            Self::POSITION_CACHE.with(|cache| {
-                // Use a limited scope for the borrow to ensure it's dropped
-                let cache_ref = cache.borrow();
-                if let Some(node_angles) = cache_ref.node_angles.get(&node_id) {
-                    for (slot_id, angle) in node_angles {
-                        angles.insert(slot_id.clone(), *angle);
-                    }
+            // Use a limited scope for the borrow to ensure it's dropped
+            let cache_ref = self.slot_positions.borrow();
+            if let Some(node_angles) = cache_ref.node_angles.get(&node_id) {
+                for (slot_id, angle) in node_angles {
+                    angles.insert(slot_id.clone(), *angle);
                 }
-            });
+            }
             angles
         };
 
@@ -837,36 +1158,39 @@ impl GraphCanvas {
             }
         }
 
-        // Check for incoming connections from other nodes
-        let incoming_slot_id = "incoming";
-        let mut incoming_connections = Vec::new();
+        // Position each incoming slot towards the nodes connecting into it
+        for incoming_slot in node_template.incoming_slot_templates() {
+            let mut incoming_connections = Vec::new();
 
-        // Find all nodes that connect to this node
-        for (other_id, other_node) in &graph.node_instances {
-            if other_id == &node.instance_id {
-                continue; // Skip self
-            }
+            // Find all nodes that connect to this slot
+            for (other_id, other_node) in &graph.node_instances {
+                if other_id == &node.instance_id {
+                    continue; // Skip self
+                }
 
-            for other_slot in &other_node.slots {
-                for conn in &other_slot.connections {
-                    if conn.target_node_id == node.instance_id {
-                        let other_x = other_node.x + other_node.radius;
-                        let other_y = other_node.y + other_node.radius;
-                        let angle = (other_y - center_y).atan2(other_x - center_x);
-                        incoming_connections.push(angle);
+                for other_slot in &other_node.slots {
+                    for conn in &other_slot.connections {
+                        if conn.target_node_id == node.instance_id
+                            && conn.target_slot_template_id == incoming_slot.id
+                        {
+                            let other_x = other_node.x + other_node.radius;
+                            let other_y = other_node.y + other_node.radius;
+                            let angle = (other_y - center_y).atan2(other_x - center_x);
+                            incoming_connections.push(angle);
+                        }
                     }
                 }
             }
-        }
 
-        // If there are incoming connections, position the incoming slot
-        if !incoming_connections.is_empty() {
+            if incoming_connections.is_empty() {
+                continue;
+            }
             let sin_sum: f64 = incoming_connections.iter().map(|a| a.sin()).sum();
             let cos_sum: f64 = incoming_connections.iter().map(|a| a.cos()).sum();
             let mean_angle = sin_sum.atan2(cos_sum);
 
             // Apply smoothing if we have a previous position
-            let final_angle = if let Some(prev_angle) = prev_angles.get(incoming_slot_id) {
+            let final_angle = if let Some(prev_angle) = prev_angles.get(&incoming_slot.id) {
                 // Increased alpha for faster stabilization during initialization
                 let alpha = if is_initialization { 0.6 } else { 0.3 };
                 let mut angle_diff = mean_angle - prev_angle;
@@ -884,72 +1208,48 @@ impl GraphCanvas {
                 mean_angle
             };
 
-            slot_angles.insert(incoming_slot_id.to_string(), final_angle);
-            slot_weights.insert(
-                incoming_slot_id.to_string(),
-                incoming_connections.len() * 10,
-            );
+            slot_angles.insert(incoming_slot.id.clone(), final_angle);
+            slot_weights.insert(incoming_slot.id.clone(), incoming_connections.len() * 10);
         }
 
         // Create a list of unassigned slots (those without connections)
         let mut unassigned_slots = Vec::new();
-
-        // Include the incoming slot if it's not already assigned
-        if !slot_angles.contains_key(incoming_slot_id) {
-            unassigned_slots.push(incoming_slot_id);
-        }
-
-        // Add all unassigned template slots
         for template in &node_template.slot_templates {
             if !slot_angles.contains_key(&template.id) {
-                unassigned_slots.push(&template.id);
+                unassigned_slots.push(template);
             }
         }
 
         // Assign initial positions to unassigned slots
-        if !unassigned_slots.is_empty() {
-            // For each unassigned slot, generate initial positions
-            for slot_id in &unassigned_slots {
-                // If we have a previous position, use it for stability
-                // Otherwise use a default based on its original position
-                let initial_angle = if let Some(prev_angle) = prev_angles.get(*slot_id) {
-                    *prev_angle
-                } else {
-                    // Generate an initial angle based on the slot template's position
-                    match *slot_id {
-                        "incoming" => std::f64::consts::PI, // Left side
-                        _ => {
-                            // Find the template for this slot
-                            if let Some(template) = node_template
-                                .slot_templates
-                                .iter()
-                                .find(|t| &t.id == slot_id)
-                            {
-                                match template.position {
-                                    SlotPosition::Right => 0.0,
-                                    SlotPosition::Bottom => std::f64::consts::PI / 2.0,
-                                    SlotPosition::Left => std::f64::consts::PI,
-                                    SlotPosition::Top => 3.0 * std::f64::consts::PI / 2.0,
-                                }
-                            } else {
-                                // Default to right side if template not found (shouldn't happen)
-                                0.0
-                            }
-                        }
-                    }
-                };
+        for template in &unassigned_slots {
+            // If we have a previous position, use it for stability
+            // Otherwise use a default based on the slot template's position
+            let initial_angle = if let Some(prev_angle) = prev_angles.get(&template.id) {
+                *prev_angle
+            } else {
+                match template.position {
+                    SlotPosition::Right => 0.0,
+                    SlotPosition::Bottom => std::f64::consts::PI / 2.0,
+                    SlotPosition::Left => std::f64::consts::PI,
+                    SlotPosition::Top => 3.0 * std::f64::consts::PI / 2.0,
+                }
+            };
 
-                slot_angles.insert((*slot_id).to_string(), initial_angle);
-                slot_weights.insert((*slot_id).to_string(), 1); // Minimal weight for empty slots
-            }
+            slot_angles.insert(template.id.clone(), initial_angle);
+            slot_weights.insert(template.id.clone(), 1); // Minimal weight for empty slots
         }
 
         // Use our gas-like model algorithm to distribute slots
         // Increase stabilization iterations during initialization
         let iterations = if is_initialization { 50 } else { 30 };
+        let incoming_slot_ids = node_template
+            .incoming_slot_templates()
+            .map(|st| st.id.clone())
+            .collect::<HashSet<_>>();
         self.distribute_slots_gas_model(
             &mut slot_angles,
             &slot_weights,
+            &incoming_slot_ids,
             unassigned_slots.len(),
             iterations,
         );
@@ -963,8 +1263,8 @@ impl GraphCanvas {
 
         // Store all data at once - AFTER all calculations are complete
         // This avoids recursive borrowing issues
-        Self::POSITION_CACHE.with(|cache| {
-            let mut cache_ref = cache.borrow_mut();
+        {
+            let mut cache_ref = self.slot_positions.borrow_mut();
             // Clear old data for this node
             cache_ref.clear_node(&node_id);
 
@@ -977,7 +1277,7 @@ impl GraphCanvas {
             for (id, (x, y)) in &slot_positions {
                 cache_ref.set_position(&node_id, id, (*x, *y));
             }
-        });
+        }
 
         slot_positions
     }
@@ -987,13 +1287,13 @@ impl GraphCanvas {
         &self,
         slot_angles: &mut HashMap<String, f64>,
         slot_weights: &HashMap<String, usize>,
+        incoming_slot_ids: &HashSet<String>,
         empty_slot_count: usize,
         iterations: usize, // Allow customization of iteration count
     ) {
         // Parameters for simulation
         let repulsion_strength = 0.01;
         let min_angle_separation = 0.4;
-        let incoming_slot_id = "incoming";
 
         // Empty slots should get more evenly distributed
         let empty_slot_base_spacing = if empty_slot_count > 0 {
@@ -1008,13 +1308,6 @@ impl GraphCanvas {
             .map(|(id, angle)| (id.clone(), *angle, *slot_weights.get(id).unwrap_or(&1)))
             .collect();
 
-        // Handle special case where the incoming slot needs special treatment
-        let incoming_idx = slots.iter().position(|(id, _, _)| id == incoming_slot_id);
-        let _has_active_incoming = incoming_idx.is_some()
-            && slots
-                .get(incoming_idx.unwrap())
-                .map_or(false, |(_, _, w)| *w > 1);
-
         // Check if all slots are empty (no connections)
         let all_slots_empty = slots.iter().all(|(_, _, weight)| *weight <= 1);
 
@@ -1043,10 +1336,10 @@ impl GraphCanvas {
 
         // Sort by weight (heaviest/most connected slots first)
         slots.sort_by(|a, b| {
-            // Give special priority to incoming slot with connections
-            if a.0 == incoming_slot_id && a.2 > 1 {
+            // Give special priority to incoming slots with connections
+            if incoming_slot_ids.contains(&a.0) && a.2 > 1 {
                 std::cmp::Ordering::Less
-            } else if b.0 == incoming_slot_id && b.2 > 1 {
+            } else if incoming_slot_ids.contains(&b.0) && b.2 > 1 {
                 std::cmp::Ordering::Greater
             } else {
                 // Normal weight-based comparison
@@ -1062,7 +1355,7 @@ impl GraphCanvas {
             for i in 0..slots.len() {
                 let (id_i, angle_i, weight_i) = &slots[i];
                 let is_empty_i = *weight_i <= 1; // No connections = empty
-                let is_incoming_i = id_i == incoming_slot_id;
+                let is_incoming_i = incoming_slot_ids.contains(id_i);
 
                 // Each slot tries to maintain a preferred distance from others
                 for j in 0..slots.len() {
@@ -1072,7 +1365,7 @@ impl GraphCanvas {
 
                     let (id_j, angle_j, weight_j) = &slots[j];
                     let is_empty_j = *weight_j <= 1; // No connections = empty
-                    let is_incoming_j = id_j == incoming_slot_id;
+                    let is_incoming_j = incoming_slot_ids.contains(id_j);
 
                     // Calculate circular distance between angles
                     let mut diff = angle_i - angle_j;
@@ -1143,11 +1436,16 @@ impl GraphCanvas {
                         .iter()
                         .filter(|(other_id, _, other_weight)| {
                             *other_id != *id_i &&
-                            // Only consider populated slots and the incoming slot as barriers
-                            (*other_weight > 1 || *other_id == incoming_slot_id)
+                            // Only consider populated slots and incoming slots as barriers
+                            (*other_weight > 1 || incoming_slot_ids.contains(other_id))
                         })
                         .map(|(other_id, a, w)| {
-                            (*a, *w > 1, other_id == incoming_slot_id, other_id.clone())
+                            (
+                                *a,
+                                *w > 1,
+                                incoming_slot_ids.contains(other_id),
+                                other_id.clone(),
+                            )
                         })
                         .collect();
 
@@ -1205,8 +1503,8 @@ impl GraphCanvas {
             for i in 0..slots.len() {
                 let (id, ref mut angle, weight) = &mut slots[i];
 
-                // Special handling for incoming slot with connections
-                let resistance = if id == incoming_slot_id && *weight > 1 {
+                // Special handling for incoming slots with connections
+                let resistance = if incoming_slot_ids.contains(id.as_str()) && *weight > 1 {
                     // Connected incoming slots are harder to move (2x resistance)
                     1.0 + (*weight as f64 * 0.4)
                 } else {
@@ -1235,7 +1533,9 @@ impl GraphCanvas {
                         diff = diff.min(2.0 * std::f64::consts::PI - diff);
 
                         // Required separation depends on slot types
-                        let required_sep = if id_i == incoming_slot_id || id_j == incoming_slot_id {
+                        let required_sep = if incoming_slot_ids.contains(id_i)
+                            || incoming_slot_ids.contains(id_j)
+                        {
                             min_angle_separation * 1.2 // Incoming slots need more space
                         } else {
                             min_angle_separation * 0.9 // Regular slots
@@ -1277,13 +1577,7 @@ impl GraphCanvas {
         let slot_id = &slot_template.id;
 
         // Get cached position without holding a borrow
-        let cached_position = {
-            let mut position = None;
-            Self::POSITION_CACHE.with(|cache| {
-                position = cache.borrow().get_position(node_id, slot_id);
-            });
-            position
-        };
+        let cached_position = self.slot_positions.borrow().get_position(node_id, slot_id);
 
         if let Some(position) = cached_position {
             return position;
@@ -1316,12 +1610,9 @@ impl GraphCanvas {
         );
 
         // Cache the calculated position for future use - but don't borrow during recursion
-        {
-            Self::POSITION_CACHE.with(|cache| {
-                let mut cache_ref = cache.borrow_mut();
-                cache_ref.set_position(node_id, slot_id, position);
-            });
-        }
+        self.slot_positions
+            .borrow_mut()
+            .set_position(node_id, slot_id, position);
 
         position
     }
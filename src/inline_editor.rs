@@ -0,0 +1,392 @@
+use std::{cell::Cell, rc::Rc};
+
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{window, Document, HtmlElement};
+
+use crate::{
+    dom_bindings::InlineEditor,
+    errors::{GraphError, GraphResult},
+    graph::{FieldTemplate, FieldType, Graph},
+    layout::ViewTransform,
+    log, GraphCanvas,
+};
+
+// Half the height of a field row, in graph units, for hit testing
+const FIELD_ROW_HALF_HEIGHT: f64 = 7.5;
+const EDITOR_MIN_WIDTH: f64 = 120.0;
+
+/// Double-clicking a field drawn on a node opens an HTML control over it, chosen by the
+/// field's type. Enter or leaving the control commits the value; Escape throws it away.
+impl GraphCanvas {
+    /// The field row under a point in graph coordinates, as (node_id, field_template_id).
+    pub(crate) fn field_at(&self, x: f64, y: f64, graph: &Graph) -> Option<(String, String)> {
+        graph.node_instances.values().find_map(|instance| {
+            let template = graph.node_templates.get(&instance.template_id)?;
+            let center_x = instance.x + instance.radius;
+            // Keep to the middle of the node, where the text is drawn
+            if (x - center_x).abs() > instance.radius * 0.8 {
+                return None;
+            }
+            Self::field_rows(instance, template)
+                // 12px text sits about 4px above its baseline
+                .find(|(_, _, row_y)| (y - (row_y - 4.0)).abs() <= FIELD_ROW_HALF_HEIGHT)
+                .map(|(field_instance, _, _)| {
+                    (
+                        instance.instance_id.clone(),
+                        field_instance.field_template_id.clone(),
+                    )
+                })
+        })
+    }
+
+    // Double click handler; returns whether an editor was opened
+    pub(crate) fn handle_double_click(&self, screen_x: f64, screen_y: f64) -> GraphResult<bool> {
+        let field = {
+            let ix = self
+                .interaction
+                .lock()
+                .map_err(|err| GraphError::LockFailed(err.to_string()))?;
+            let graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+            let (x, y) = ix.view_transform.screen_to_graph(screen_x, screen_y);
+            self.field_at(x, y, &graph)
+        };
+        let Some((node_id, field_template_id)) = field else {
+            return Ok(false);
+        };
+        self.open_inline_field_editor(&node_id, &field_template_id)?;
+        Ok(true)
+    }
+
+    // Opens the editor the context menu asked for, after the pointer events that chose the
+    // menu item have finished moving focus around
+    pub(crate) fn open_requested_field_editor(&self) -> Result<(), JsValue> {
+        let request = match self.interaction.lock() {
+            Ok(mut ix) => ix.field_edit_request.take(),
+            Err(_) => None,
+        };
+        let Some((node_id, field_template_id)) = request else {
+            return Ok(());
+        };
+        let canvas = self.clone();
+        let callback = Closure::once_into_js(move || {
+            if let Err(err) = canvas.open_inline_field_editor(&node_id, &field_template_id) {
+                log(&format!("Could not open field editor: {}", err));
+            }
+        });
+        window()
+            .ok_or_else(|| JsValue::from_str("No window"))?
+            .set_timeout_with_callback_and_timeout_and_arguments_0(callback.unchecked_ref(), 0)?;
+        Ok(())
+    }
+
+    pub fn open_inline_field_editor(
+        &self,
+        node_id: &str,
+        field_template_id: &str,
+    ) -> GraphResult<()> {
+        let (field_template, value) = {
+            let graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+            let node = graph
+                .node_instances
+                .get(node_id)
+                .ok_or_else(|| GraphError::NodeNotFound(node_id.to_string()))?;
+            let template = graph
+                .node_templates
+                .get(&node.template_id)
+                .ok_or_else(|| GraphError::TemplateNotFound(node.template_id.clone()))?;
+            let field_template = template
+                .field_templates
+                .iter()
+                .find(|ft| ft.id == field_template_id)
+                .cloned()
+                .ok_or(GraphError::Other("Field template not found".to_string()))?;
+            let field = node
+                .fields
+                .iter()
+                .find(|f| f.field_template_id == field_template_id)
+                .ok_or(GraphError::Other(
+                    "Field not found in node instance".to_string(),
+                ))?;
+            if !node.can_modify_fields || !field.can_modify {
+                return Err(GraphError::Other("Field is locked".to_string()));
+            }
+            (field_template, field.value.clone())
+        };
+
+        let document = window().unwrap().document().unwrap();
+        let container = document
+            .get_element_by_id(&self.dom_id("graph-container"))
+            .ok_or_else(|| GraphError::Other("Graph container not found".to_string()))?;
+
+        // Only one field is edited at a time
+        self.dom_bindings.borrow_mut().close_inline_editor();
+
+        let element = Self::create_inline_editor_element(&document, &field_template, &value)
+            .map_err(|err| GraphError::Other(format!("{:?}", err)))?;
+        let session = Rc::new(InlineEditSession {
+            graph_canvas: self.clone(),
+            element: element.clone(),
+            node_id: node_id.to_string(),
+            field_template_id: field_template_id.to_string(),
+            original_value: value,
+            finished: Cell::new(false),
+        });
+        self.attach_inline_editor_handlers(&session, &field_template.field_type)
+            .map_err(|err| GraphError::Other(format!("{:?}", err)))?;
+
+        container
+            .append_child(&element)
+            .map_err(|err| GraphError::Other(format!("{:?}", err)))?;
+        self.dom_bindings
+            .borrow_mut()
+            .set_inline_editor(InlineEditor {
+                element: element.clone(),
+                node_id: node_id.to_string(),
+                field_template_id: field_template_id.to_string(),
+            });
+        self.position_inline_editor_now();
+        let _ = element.focus();
+        Ok(())
+    }
+
+    fn create_inline_editor_element(
+        document: &Document,
+        field_template: &FieldTemplate,
+        value: &str,
+    ) -> Result<HtmlElement, JsValue> {
+        let (tag, input_type) = match &field_template.field_type {
+            FieldType::Boolean | FieldType::Enum { .. } => ("select", None),
+            FieldType::Integer | FieldType::Float { .. } => ("input", Some("number")),
+            FieldType::Color => ("input", Some("color")),
+            FieldType::Date => ("input", Some("date")),
+            FieldType::MultilineText | FieldType::Json => ("textarea", None),
+            FieldType::String => ("input", Some("text")),
+        };
+        let element = document.create_element(tag)?;
+        if let Some(input_type) = input_type {
+            element.set_attribute("type", input_type)?;
+        }
+
+        match &field_template.field_type {
+            FieldType::Boolean => Self::add_select_options(document, &element, ["true", "false"])?,
+            FieldType::Enum { options } => {
+                Self::add_select_options(document, &element, options.iter().map(String::as_str))?
+            }
+            FieldType::Integer => element.set_attribute("step", "1")?,
+            FieldType::Float { min, max, step } => {
+                if let Some(min) = min {
+                    element.set_attribute("min", &min.to_string())?;
+                }
+                if let Some(max) = max {
+                    element.set_attribute("max", &max.to_string())?;
+                }
+                let step = step.map_or("any".to_string(), |step| step.to_string());
+                element.set_attribute("step", &step)?;
+            }
+            FieldType::MultilineText | FieldType::Json => element.set_attribute("rows", "4")?,
+            _ => {}
+        }
+
+        let value = match field_template.field_type {
+            FieldType::Color => color_input_value(value),
+            _ => value.to_string(),
+        };
+        // Set after any options exist, so a select can pick the matching one
+        js_sys::Reflect::set(&element, &"value".into(), &value.into())?;
+
+        let element = element.dyn_into::<HtmlElement>()?;
+        let style = element.style();
+        style.set_property("position", "absolute")?;
+        style.set_property("z-index", "10")?;
+        style.set_property("box-sizing", "border-box")?;
+        style.set_property("font-family", "Arial, sans-serif")?;
+        if field_template.field_type == FieldType::Json {
+            style.set_property("font-family", "monospace")?;
+        }
+        Ok(element)
+    }
+
+    fn add_select_options<'a>(
+        document: &Document,
+        select: &web_sys::Element,
+        options: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), JsValue> {
+        for option in options {
+            let option_element = document.create_element("option")?;
+            option_element.set_attribute("value", option)?;
+            option_element.set_text_content(Some(option));
+            select.append_child(&option_element)?;
+        }
+        Ok(())
+    }
+
+    fn attach_inline_editor_handlers(
+        &self,
+        session: &Rc<InlineEditSession>,
+        field_type: &FieldType,
+    ) -> Result<(), JsValue> {
+        let element = session.element.clone();
+        let mut dom_bindings = self.dom_bindings.borrow_mut();
+
+        let is_multiline = matches!(field_type, FieldType::MultilineText | FieldType::Json);
+        let key_session = session.clone();
+        let key_down = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+            // The canvas shortcuts must not see keys typed into the editor
+            event.stop_propagation();
+            match event.key().as_str() {
+                // Shift+Enter starts a new line in multi-line fields
+                "Enter" if !(is_multiline && event.shift_key()) => {
+                    event.prevent_default();
+                    key_session.finish(true);
+                }
+                "Escape" => {
+                    event.prevent_default();
+                    key_session.finish(false);
+                }
+                _ => {}
+            }
+        }) as Box<dyn FnMut(_)>);
+        dom_bindings.listen_inline_editor(&element, "keydown", key_down)?;
+
+        let blur_session = session.clone();
+        let blur = Closure::wrap(Box::new(move |_: web_sys::Event| {
+            blur_session.finish(true);
+        }) as Box<dyn FnMut(_)>);
+        dom_bindings.listen_inline_editor(&element, "blur", blur)?;
+
+        // Picking from a list or palette is already a decision
+        if matches!(
+            field_type,
+            FieldType::Boolean | FieldType::Enum { .. } | FieldType::Color
+        ) {
+            let change_session = session.clone();
+            let change = Closure::wrap(Box::new(move |_: web_sys::Event| {
+                change_session.finish(true);
+            }) as Box<dyn FnMut(_)>);
+            dom_bindings.listen_inline_editor(&element, "change", change)?;
+        }
+        Ok(())
+    }
+
+    // Called from the render loop, so the editor follows its node through drags, pans and zooms
+    pub(crate) fn position_inline_editor(&self, graph: &Graph, view_transform: &ViewTransform) {
+        let Ok(mut dom_bindings) = self.dom_bindings.try_borrow_mut() else {
+            return;
+        };
+        let Some(editor) = dom_bindings.inline_editor() else {
+            return;
+        };
+        if !Self::place_inline_editor(editor, graph, view_transform) {
+            // The node or field went away underneath the editor
+            dom_bindings.close_inline_editor();
+        }
+    }
+
+    fn position_inline_editor_now(&self) {
+        let (Ok(graph), Ok(ix)) = (self.graph.lock(), self.interaction.lock()) else {
+            return;
+        };
+        self.position_inline_editor(&graph, &ix.view_transform);
+    }
+
+    // Centres the editor over its field row; false if the field is no longer drawn
+    fn place_inline_editor(
+        editor: &InlineEditor,
+        graph: &Graph,
+        view_transform: &ViewTransform,
+    ) -> bool {
+        let Some(instance) = graph.node_instances.get(&editor.node_id) else {
+            return false;
+        };
+        let Some(template) = graph.node_templates.get(&instance.template_id) else {
+            return false;
+        };
+        let Some((_, _, row_y)) = Self::field_rows(instance, template)
+            .find(|(field, _, _)| field.field_template_id == editor.field_template_id)
+        else {
+            return false;
+        };
+
+        let zoom = view_transform.zoom;
+        let (center_x, center_y) =
+            view_transform.graph_to_screen(instance.x + instance.radius, row_y - 4.0);
+        let width = (instance.radius * 1.6 * zoom).max(EDITOR_MIN_WIDTH);
+        let font_size = (12.0 * zoom).clamp(11.0, 24.0);
+        let height = if editor.element.tag_name() == "TEXTAREA" {
+            font_size * 5.0
+        } else {
+            font_size + 10.0
+        };
+
+        // The canvas is the first thing in the container, so its corner is the container's
+        let style = editor.element.style();
+        let _ = style.set_property("left", &format!("{}px", center_x - width / 2.0));
+        let _ = style.set_property("top", &format!("{}px", center_y - height / 2.0));
+        let _ = style.set_property("width", &format!("{}px", width));
+        let _ = style.set_property("height", &format!("{}px", height));
+        let _ = style.set_property("font-size", &format!("{}px", font_size));
+        true
+    }
+}
+
+struct InlineEditSession {
+    graph_canvas: GraphCanvas,
+    element: HtmlElement,
+    node_id: String,
+    field_template_id: String,
+    original_value: String,
+    // Enter removes the element, which blurs it; the blur must not commit a second time
+    finished: Cell<bool>,
+}
+
+impl InlineEditSession {
+    // Runs inside the editor's own listeners, so it leaves them attached rather than dropping
+    // closures mid-call; `close_inline_editor` cleans them up later.
+    fn finish(&self, commit: bool) {
+        if self.finished.get() {
+            return;
+        }
+        if commit {
+            let value = js_sys::Reflect::get(&self.element, &"value".into())
+                .ok()
+                .and_then(|value| value.as_string())
+                .unwrap_or_default();
+            if value != self.original_value {
+                if let Err(err) =
+                    self.graph_canvas
+                        .update_field(&self.node_id, &self.field_template_id, &value)
+                {
+                    // Stay open so the value can be fixed, or dropped with Escape
+                    let _ = self
+                        .element
+                        .style()
+                        .set_property("outline", "2px solid #d32f2f");
+                    let _ = self.element.set_attribute("title", &err.to_string());
+                    log(&format!("Failed to update field: {}", err));
+                    return;
+                }
+            }
+        }
+        self.finished.set(true);
+        self.element.remove();
+        self.graph_canvas.request_redraw();
+        // Hand the keyboard back to the canvas
+        if let Some(canvas) = window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(&self.graph_canvas.canvas_id))
+            .and_then(|canvas| canvas.dyn_into::<HtmlElement>().ok())
+        {
+            let _ = canvas.focus();
+        }
+    }
+}
+
+// Colour inputs only take the six digit form
+pub(crate) fn color_input_value(value: &str) -> String {
+    match value.strip_prefix('#') {
+        Some(hex) if hex.len() == 3 => {
+            format!("#{}", hex.chars().flat_map(|c| [c, c]).collect::<String>())
+        }
+        _ => value.to_string(),
+    }
+}
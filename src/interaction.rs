@@ -1,10 +1,15 @@
+use std::collections::{BTreeSet, HashMap};
+
 use wasm_bindgen::prelude::*;
 
 use crate::{
     errors::{log_and_convert_error, GraphError, GraphResult},
-    events::{EventSystem, SystemEvent},
-    graph::{Connection, Graph, GraphCommand, NodeInstance, SlotInstance},
-    log, GraphCanvas,
+    events::{ContextMenuTarget, EventSystem, SystemEvent},
+    graph::{Connection, Graph, GraphCommand, NodeInstance, SlotInstance, SlotType},
+    layout::ViewTransform,
+    log,
+    validation::ValidationReport,
+    GraphCanvas,
 };
 
 struct DragStateResetter<'a> {
@@ -39,7 +44,8 @@ pub struct InteractionState {
     pub is_mouse_down: bool,
     pub click_initiated_on_node: Option<String>,
     pub click_initiated_on_slot: Option<(String, String)>,
-    pub currently_selected_node_instance: Option<String>,
+    pub selected_nodes: BTreeSet<String>,
+    pub selection_box: Option<SelectionBox>,
     pub is_dragging_node: bool,
     pub connection_drag: Option<ConnectionDragInfo>,
     pub context_menu: Option<ContextMenu>,
@@ -50,32 +56,20 @@ pub struct InteractionState {
     pub hovered_node: Option<String>,
     pub hovered_slot: Option<(String, String)>, // (node_id, slot_template_id)
     pub hovered_connection: Option<Connection>,
-}
-pub struct ViewTransform {
-    pub pan_x: f64,
-    pub pan_y: f64,
-    pub zoom: f64,
-}
-impl ViewTransform {
-    // Convert screen coordinates to graph coordinates
-    pub fn screen_to_graph(&self, x: f64, y: f64) -> (f64, f64) {
-        // First apply pan, then account for zoom
-        let center_x = x - self.pan_x;
-        let center_y = y - self.pan_y;
-
-        // Convert from screen to graph space (divide by zoom factor)
-        (center_x / self.zoom, center_y / self.zoom)
-    }
-
-    #[allow(dead_code)]
-    // Convert graph coordinates to screen coordinates
-    pub fn graph_to_screen(&self, x: f64, y: f64) -> (f64, f64) {
-        // First apply zoom, then add pan
-        let zoomed_x = x * self.zoom;
-        let zoomed_y = y * self.zoom;
-
-        (zoomed_x + self.pan_x, zoomed_y + self.pan_y)
-    }
+    // Refreshed every frame so the overlay tracks edits as they happen
+    pub validation_report: ValidationReport,
+    // Set from the pointerType of the event being handled
+    pub pointer_is_touch: bool,
+    // Screen positions of the pointers currently down, by pointerId
+    pub active_pointers: HashMap<i32, (f64, f64)>,
+    pub long_press: Option<LongPress>,
+    // Two pointers are down; single-pointer handling waits until all of them lift
+    pub is_pinching: bool,
+    // Starts from the config; the toolbar can turn it on and off
+    pub snap_to_grid: bool,
+    // (node_id, field_template_id) picked from the context menu. The inline editor needs the
+    // DOM, so it is opened once the pointer event has been handled.
+    pub field_edit_request: Option<(String, String)>,
 }
 impl InteractionState {
     pub fn new() -> Self {
@@ -84,7 +78,8 @@ impl InteractionState {
             is_mouse_down: false,
             click_initiated_on_node: None,
             click_initiated_on_slot: None,
-            currently_selected_node_instance: None,
+            selected_nodes: BTreeSet::new(),
+            selection_box: None,
             is_dragging_node: false,
             context_menu: None,
             connection_drag: None,
@@ -99,14 +94,65 @@ impl InteractionState {
             hovered_node: None,
             hovered_slot: None,
             hovered_connection: None,
+            validation_report: ValidationReport::default(),
+            pointer_is_touch: false,
+            active_pointers: HashMap::new(),
+            long_press: None,
+            is_pinching: false,
+            snap_to_grid: false,
+            field_edit_request: None,
         }
     }
 }
+impl InteractionState {
+    // Replaces the selection, emitting SelectionChanged only if it actually changed
+    pub fn set_selection(&mut self, nodes: BTreeSet<String>, events: &EventSystem) {
+        if nodes != self.selected_nodes {
+            self.selected_nodes = nodes;
+            events.emit(SystemEvent::SelectionChanged(
+                self.selected_nodes.iter().cloned().collect(),
+            ));
+        }
+    }
+}
+
 pub enum InteractionMode {
     Default,
     AddNode,
 }
 
+// A touch held in place, waiting to become a context menu request
+#[derive(Clone)]
+pub struct LongPress {
+    pub pointer_id: i32,
+    pub screen_x: f64,
+    pub screen_y: f64,
+    // The menu has been opened; the matching pointerup should do nothing else
+    pub fired: bool,
+}
+
+// A rubber-band selection in progress, in graph coordinates
+#[derive(Clone)]
+pub struct SelectionBox {
+    pub start_x: f64,
+    pub start_y: f64,
+    pub current_x: f64,
+    pub current_y: f64,
+    // Add to the existing selection instead of replacing it
+    pub additive: bool,
+}
+
+impl SelectionBox {
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle {
+            x: self.start_x.min(self.current_x),
+            y: self.start_y.min(self.current_y),
+            width: (self.current_x - self.start_x).abs(),
+            height: (self.current_y - self.start_y).abs(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ConnectionDragInfo {
     pub from_node: String,
@@ -122,84 +168,6 @@ pub struct ContextMenu {
     pub items: Vec<ContextMenuItem>,
     pub field_edit_value: Option<String>, // For field editing
 }
-#[derive(Clone, Debug)]
-pub enum ContextMenuTarget {
-    Node(String),
-    // from_node
-    Connection(Connection),
-    Slot {
-        node_id: String,
-        slot_template_id: String,
-    },
-    Field {
-        node_id: String,
-        field_template_id: String,
-    },
-}
-impl ContextMenuTarget {
-    pub fn get_title(&self, graph: &Graph) -> String {
-        match self {
-            ContextMenuTarget::Node(node_id) => {
-                if let Some(node) = graph.node_instances.get(node_id) {
-                    if let Some(template) = graph.node_templates.get(&node.template_id) {
-                        format!("Node: {}", template.name)
-                    } else {
-                        "Unknown Node".to_string()
-                    }
-                } else {
-                    "Unknown Node".to_string()
-                }
-            }
-            ContextMenuTarget::Connection { .. } => "Connection".to_string(),
-            ContextMenuTarget::Slot {
-                node_id,
-                slot_template_id,
-            } => {
-                if let Some(node) = graph.node_instances.get(node_id) {
-                    if let Some(slot) = node
-                        .slots
-                        .iter()
-                        .find(|s| s.slot_template_id == *slot_template_id)
-                    {
-                        if let Some(template) = graph.node_templates.get(&node.template_id) {
-                            if let Some(slot_template) = template
-                                .slot_templates
-                                .iter()
-                                .find(|t| t.id == slot.slot_template_id)
-                            {
-                                return format!("Slot: {}", slot_template.name);
-                            }
-                        }
-                    }
-                }
-                "Unknown Slot".to_string()
-            }
-            ContextMenuTarget::Field {
-                node_id,
-                field_template_id,
-            } => {
-                if let Some(node) = graph.node_instances.get(node_id) {
-                    if let Some(field) = node
-                        .fields
-                        .iter()
-                        .find(|f| f.field_template_id == *field_template_id)
-                    {
-                        if let Some(template) = graph.node_templates.get(&node.template_id) {
-                            if let Some(field_template) = template
-                                .field_templates
-                                .iter()
-                                .find(|t| t.id == field.field_template_id)
-                            {
-                                return format!("Field: {}", field_template.name);
-                            }
-                        }
-                    }
-                }
-                "Unknown Field".to_string()
-            }
-        }
-    }
-}
 #[derive(Clone)]
 pub struct ContextMenuItem {
     pub label: String,
@@ -225,15 +193,23 @@ impl Rectangle {
 #[derive(Clone)]
 pub enum ContextMenuAction {
     Delete,
+    DeleteSelection,
     DeleteAllSlotConnections,
     EditField,
     SetBooleanField(bool),
-    SetIntegerField(i32),
+    SetIntegerField(i64),
+    SetFloatField(f64),
     SetStringField(String),
 }
 #[wasm_bindgen]
 impl GraphCanvas {
-    pub(crate) fn handle_mouse_down(&self, screen_x: f64, screen_y: f64) -> Result<(), JsValue> {
+    /// `toggle_selection` is set while shift, ctrl or cmd is held.
+    pub(crate) fn handle_mouse_down(
+        &self,
+        screen_x: f64,
+        screen_y: f64,
+        toggle_selection: bool,
+    ) -> Result<(), JsValue> {
         let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
         let mut graph = self.graph.lock().map_err(log_and_convert_error)?;
         let events = self.events.lock().map_err(log_and_convert_error)?;
@@ -241,7 +217,14 @@ impl GraphCanvas {
         let (graph_x, graph_y) = ix.view_transform.screen_to_graph(screen_x, screen_y);
         match ix.mode {
             InteractionMode::Default => self
-                .internal_pointer_handle_mouse_down(graph_x, graph_y, &mut graph, &mut ix, &events)
+                .internal_pointer_handle_mouse_down(
+                    graph_x,
+                    graph_y,
+                    toggle_selection,
+                    &mut graph,
+                    &mut ix,
+                    &events,
+                )
                 .map_err(log_and_convert_error)?,
             InteractionMode::AddNode => self
                 .internal_add_node_handle_mouse_down(graph_x, graph_y, &mut graph, &mut ix, &events)
@@ -278,7 +261,12 @@ impl GraphCanvas {
         Ok(())
     }
 
-    pub(crate) fn handle_mouse_up(&self, screen_x: f64, screen_y: f64) -> Result<(), JsValue> {
+    pub(crate) fn handle_mouse_up(
+        &self,
+        screen_x: f64,
+        screen_y: f64,
+        toggle_selection: bool,
+    ) -> Result<(), JsValue> {
         let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
         let mut graph = self.graph.lock().map_err(log_and_convert_error)?;
         let events = self.events.lock().map_err(log_and_convert_error)?;
@@ -286,7 +274,14 @@ impl GraphCanvas {
         let (graph_x, graph_y) = ix.view_transform.screen_to_graph(screen_x, screen_y);
         match ix.mode {
             InteractionMode::Default => self
-                .internal_pointer_handle_mouse_up(graph_x, graph_y, &mut graph, &mut ix, &events)
+                .internal_pointer_handle_mouse_up(
+                    graph_x,
+                    graph_y,
+                    toggle_selection,
+                    &mut graph,
+                    &mut ix,
+                    &events,
+                )
                 .map_err(log_and_convert_error)?,
             InteractionMode::AddNode => self
                 .internal_add_node_handle_mouse_up(graph_x, graph_y, &mut graph, &mut ix, &events)
@@ -299,6 +294,7 @@ impl GraphCanvas {
         &self,
         action: ContextMenuAction,
         target: &ContextMenuTarget,
+        selection: &BTreeSet<String>,
         graph: &mut Graph,
         events: &EventSystem,
     ) -> GraphResult<()> {
@@ -306,6 +302,12 @@ impl GraphCanvas {
             (ContextMenuAction::Delete, ContextMenuTarget::Node(node_id)) => {
                 graph.execute_command(GraphCommand::DeleteNode(node_id.clone()), events)?;
             }
+            (ContextMenuAction::DeleteSelection, ContextMenuTarget::Node(_)) => {
+                graph.execute_command(
+                    GraphCommand::DeleteNodes(selection.iter().cloned().collect()),
+                    events,
+                )?;
+            }
             (ContextMenuAction::Delete, ContextMenuTarget::Connection(connection)) => {
                 graph
                     .execute_command(GraphCommand::DeleteConnection(connection.clone()), events)?;
@@ -364,6 +366,22 @@ impl GraphCanvas {
                     events,
                 )?;
             }
+            (
+                ContextMenuAction::SetFloatField(value),
+                ContextMenuTarget::Field {
+                    node_id,
+                    field_template_id,
+                },
+            ) => {
+                graph.execute_command(
+                    GraphCommand::UpdateField {
+                        node_id: node_id.clone(),
+                        field_template_id: field_template_id.clone(),
+                        new_value: value.to_string(),
+                    },
+                    events,
+                )?;
+            }
             (
                 ContextMenuAction::SetStringField(value),
                 ContextMenuTarget::Field {
@@ -382,9 +400,7 @@ impl GraphCanvas {
                 )?;
             }
             (ContextMenuAction::EditField, ContextMenuTarget::Field { .. }) => {
-                // For now, we'll handle this in the JavaScript side
-                // by providing a UI prompt for text entry
-                log("Edit field action needs to be implemented in JS");
+                // Needs the DOM; the caller records it in `field_edit_request`
             }
             _ => {
                 log("Unhandled context menu action");
@@ -393,6 +409,40 @@ impl GraphCanvas {
         Ok(())
     }
 
+    // The incoming slot under the pointer, ignoring the node a connection is being dragged from
+    fn incoming_slot_at(
+        &self,
+        x: f64,
+        y: f64,
+        graph: &Graph,
+        exclude_node_id: &str,
+        hit_radius: f64,
+    ) -> Option<(String, String)> {
+        graph
+            .node_instances
+            .values()
+            .filter(|node| node.instance_id != exclude_node_id)
+            .find_map(|node| {
+                node.slots
+                    .iter()
+                    .filter(|slot| {
+                        slot.capabilities(graph)
+                            .is_ok_and(|capa| capa.template.slot_type == SlotType::Incoming)
+                    })
+                    .find(|slot| self.is_point_in_slot(x, y, node, slot, graph, hit_radius))
+                    .map(|slot| (node.instance_id.clone(), slot.slot_template_id.clone()))
+            })
+    }
+
+    // Slots are hit at their drawn radius, or a finger-sized one (in screen pixels) for touch
+    fn slot_hit_radius(&self, ix: &InteractionState) -> f64 {
+        if ix.pointer_is_touch {
+            (self.config.touch_hit_radius / ix.view_transform.zoom).max(self.config.slot_radius)
+        } else {
+            self.config.slot_radius
+        }
+    }
+
     fn is_point_in_slot(
         &self,
         x: f64,
@@ -400,10 +450,12 @@ impl GraphCanvas {
         node: &NodeInstance,
         slot: &SlotInstance,
         graph: &Graph,
+        radius: f64,
     ) -> bool {
-        let capa = slot.capabilities(graph);
+        let Ok(capa) = slot.capabilities(graph) else {
+            return false;
+        };
         let (slot_x, slot_y) = self.calculate_slot_position(capa.template, node, graph);
-        let radius = self.config.slot_radius; // Same as drawing radius
 
         let dx = x - slot_x;
         let dy = y - slot_y;
@@ -479,6 +531,34 @@ impl GraphCanvas {
         self.interaction.lock().unwrap().mode = mode;
     }
 
+    pub fn set_snap_to_grid(&self, enabled: bool) -> GraphResult<()> {
+        self.interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?
+            .snap_to_grid = enabled;
+        // The grid is only drawn while snapping
+        self.request_redraw();
+        Ok(())
+    }
+
+    pub fn snap_to_grid(&self) -> GraphResult<bool> {
+        Ok(self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?
+            .snap_to_grid)
+    }
+
+    // Rounds a graph coordinate to the nearest grid line when snapping is on
+    pub(crate) fn snap_to_grid_point(&self, ix: &InteractionState, value: f64) -> f64 {
+        let grid_size = self.config.grid_size;
+        if ix.snap_to_grid && grid_size > 0.0 {
+            (value / grid_size).round() * grid_size
+        } else {
+            value
+        }
+    }
+
     /// Update the node template that should be added in AddNode mode.
     pub(crate) fn set_current_node_template(&self, template_id: &str) {
         self.interaction
@@ -494,8 +574,6 @@ impl GraphCanvas {
         screen_x: f64,
         screen_y: f64,
     ) -> Result<(), JsValue> {
-        let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
-
         // Calculate zoom factor change based on wheel delta
         let zoom_speed = 0.1; // Adjust for faster/slower zooming
         let zoom_delta = if delta < 0.0 {
@@ -503,6 +581,17 @@ impl GraphCanvas {
         } else {
             1.0 - zoom_speed
         };
+        self.zoom_by_factor(zoom_delta, screen_x, screen_y)
+    }
+
+    /// Multiplies the zoom by `zoom_delta`, keeping the given screen point fixed
+    pub(crate) fn zoom_by_factor(
+        &self,
+        zoom_delta: f64,
+        screen_x: f64,
+        screen_y: f64,
+    ) -> Result<(), JsValue> {
+        let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
 
         // Calculate new zoom level with min/max constraints
         let new_zoom = (ix.view_transform.zoom * zoom_delta).max(0.1).min(5.0);
@@ -526,7 +615,7 @@ impl GraphCanvas {
 
         // Save the view transform to the current view
         if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
-            layout_engine.save_view_transform(&ix);
+            layout_engine.save_view_transform(&ix.view_transform);
         }
 
         Ok(())
@@ -535,6 +624,7 @@ impl GraphCanvas {
         &self,
         x: f64,
         y: f64,
+        toggle_selection: bool,
         graph: &mut Graph,
         ix: &mut InteractionState,
         events: &EventSystem,
@@ -546,9 +636,10 @@ impl GraphCanvas {
         ix.is_mouse_down = true;
 
         // Check if we clicked on a slot
+        let hit_radius = self.slot_hit_radius(ix);
         for (node_id, node) in &graph.node_instances {
             for slot in &node.slots {
-                if self.is_point_in_slot(x, y, node, slot, graph) {
+                if self.is_point_in_slot(x, y, node, slot, graph, hit_radius) {
                     ix.click_initiated_on_slot =
                         Some((node_id.clone(), slot.slot_template_id.clone()));
                     return Ok(());
@@ -569,7 +660,16 @@ impl GraphCanvas {
 
             if distance <= radius {
                 ix.click_initiated_on_node = Some(id.clone());
-                ix.currently_selected_node_instance = Some(id.clone());
+                let mut selection = ix.selected_nodes.clone();
+                if toggle_selection {
+                    if !selection.remove(id) {
+                        selection.insert(id.clone());
+                    }
+                } else if !selection.contains(id) {
+                    // Clicking inside the selection keeps it so the whole group can be dragged
+                    selection = BTreeSet::from([id.clone()]);
+                }
+                ix.set_selection(selection, events);
                 return Ok(());
             }
         }
@@ -586,15 +686,34 @@ impl GraphCanvas {
                 for item in &menu.items {
                     if let Some(bounds) = &item.bounds {
                         if bounds.contains(x, y) {
+                            let edit_request = match (&item.action, &menu.target_type) {
+                                (
+                                    ContextMenuAction::EditField,
+                                    ContextMenuTarget::Field {
+                                        node_id,
+                                        field_template_id,
+                                    },
+                                ) => Some((node_id.clone(), field_template_id.clone())),
+                                _ => None,
+                            };
                             // Handle the action
                             self.handle_context_menu_action(
                                 item.action.clone(),
                                 &menu.target_type,
+                                &ix.selected_nodes,
                                 graph,
                                 events,
                             )?;
                             // Close menu after action
                             ix.context_menu = None;
+                            ix.field_edit_request = edit_request;
+                            let remaining = ix
+                                .selected_nodes
+                                .iter()
+                                .filter(|id| graph.node_instances.contains_key(*id))
+                                .cloned()
+                                .collect();
+                            ix.set_selection(remaining, events);
                             events.emit(SystemEvent::ContextMenuClosed);
                             return Ok(());
                         }
@@ -616,14 +735,25 @@ impl GraphCanvas {
             }
         }
 
-        // If we didn't click on any slot, menu, connection, or node, start panning
-        if self.config.is_movable {
+        // If we didn't click on any slot, menu, connection, or node, start panning,
+        // or a box selection when a modifier is held (or the view cannot pan)
+        if toggle_selection || !self.config.is_movable {
+            ix.selection_box = Some(SelectionBox {
+                start_x: x,
+                start_y: y,
+                current_x: x,
+                current_y: y,
+                additive: toggle_selection,
+            });
+        } else {
             ix.is_panning = true;
         }
+        if !toggle_selection {
+            ix.set_selection(BTreeSet::new(), events);
+        }
 
         ix.click_initiated_on_node = None;
         ix.click_initiated_on_slot = None;
-        ix.currently_selected_node_instance = None;
         Ok(())
     }
 
@@ -639,9 +769,10 @@ impl GraphCanvas {
         ix.hovered_slot = None;
         ix.hovered_connection = None;
         // Check for hovering over slots
+        let hit_radius = self.slot_hit_radius(ix);
         for (node_id, node) in &graph.node_instances {
             for slot in &node.slots {
-                if self.is_point_in_slot(x, y, node, slot, graph) {
+                if self.is_point_in_slot(x, y, node, slot, graph, hit_radius) {
                     ix.hovered_slot = Some((node_id.clone(), slot.slot_template_id.clone()));
                     return Ok(());
                 }
@@ -692,7 +823,11 @@ impl GraphCanvas {
     ) -> GraphResult<()> {
         self.internal_pointer_handle_mouse_move_hover(x, y, graph, ix)?;
 
-        if ix.is_mouse_down
+        // While a touch might still turn into a long press, it does not start drags
+        let can_start_drag = ix.long_press.is_none();
+
+        if can_start_drag
+            && ix.is_mouse_down
             && ix.click_initiated_on_node.is_some()
             && ix.connection_drag.is_none()
             && !ix.is_dragging_node
@@ -711,14 +846,21 @@ impl GraphCanvas {
         }
 
         // Start connection drag
-        if ix.is_mouse_down && ix.click_initiated_on_slot.is_some() && ix.connection_drag.is_none()
+        if can_start_drag
+            && ix.is_mouse_down
+            && ix.click_initiated_on_slot.is_some()
+            && ix.connection_drag.is_none()
         {
             if ix.context_menu.is_some() {
                 ix.context_menu = None;
                 events.emit(SystemEvent::ContextMenuClosed);
             }
             let (node_id, slot_template_id) = ix.click_initiated_on_slot.clone().unwrap();
-            let node_instance = graph.node_instances.get(&node_id);
+            // Connections are always drawn from the outgoing end
+            let is_outgoing = graph
+                .get_slot_capabilities(&node_id, &slot_template_id)
+                .is_some_and(|caps| caps.template.slot_type == SlotType::Outgoing);
+            let node_instance = graph.node_instances.get(&node_id).filter(|_| is_outgoing);
             if let Some(node_instance) = node_instance {
                 let slot = node_instance
                     .slots
@@ -742,11 +884,30 @@ impl GraphCanvas {
             connection_drag.current_x = x;
             connection_drag.current_y = y;
         }
+        if let Some(selection_box) = &mut ix.selection_box {
+            selection_box.current_x = x;
+            selection_box.current_y = y;
+        }
         if ix.is_dragging_node {
             if let Some(ref selected_id) = ix.click_initiated_on_node.clone() {
-                if let Some(instance) = graph.node_instances.get_mut(selected_id) {
-                    instance.x = x - instance.radius;
-                    instance.y = y - instance.radius;
+                if let Some(instance) = graph.node_instances.get(selected_id) {
+                    // The dragged node's center follows the pointer, landing on grid points
+                    // when snapping
+                    let move_x = self.snap_to_grid_point(ix, x) - instance.radius - instance.x;
+                    let move_y = self.snap_to_grid_point(ix, y) - instance.radius - instance.y;
+
+                    // Dragging a selected node carries the rest of the selection with it
+                    let moving = if ix.selected_nodes.contains(selected_id) {
+                        ix.selected_nodes.iter().cloned().collect::<Vec<_>>()
+                    } else {
+                        vec![selected_id.clone()]
+                    };
+                    for node_id in &moving {
+                        if let Some(instance) = graph.node_instances.get_mut(node_id) {
+                            instance.x += move_x;
+                            instance.y += move_y;
+                        }
+                    }
 
                     // Run a simulation step when in force directed mode
                     if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
@@ -767,6 +928,7 @@ impl GraphCanvas {
         &self,
         x: f64,
         y: f64,
+        toggle_selection: bool,
         graph: &mut Graph,
         ix: &mut InteractionState,
         events: &EventSystem,
@@ -776,32 +938,72 @@ impl GraphCanvas {
         }
         ix.is_mouse_down = false;
 
+        // Finish a box selection; nodes whose centers fall inside the box are selected
+        if let Some(selection_box) = ix.selection_box.take() {
+            let bounds = selection_box.bounds();
+            let mut selection = if selection_box.additive {
+                ix.selected_nodes.clone()
+            } else {
+                BTreeSet::new()
+            };
+            selection.extend(
+                graph
+                    .node_instances
+                    .values()
+                    .filter(|node| bounds.contains(node.x + node.radius, node.y + node.radius))
+                    .map(|node| node.instance_id.clone()),
+            );
+            ix.set_selection(selection, events);
+            if ix.context_menu.is_some() {
+                ix.context_menu = None;
+                events.emit(SystemEvent::ContextMenuClosed);
+            }
+            return Ok(());
+        }
+
         // If we were creating a connection
         if ix.connection_drag.is_some() {
+            let hit_radius = self.slot_hit_radius(ix);
             let resetter = DragStateResetter::new(&mut *ix, &mut *graph);
             let connection_drag = resetter.interaction_state.connection_drag.clone().unwrap();
-            // Check if we're over another node
-            for (target_node_id, target_node) in resetter.graph.node_instances.clone().into_iter() {
-                // Don't connect to self
-                if target_node_id != connection_drag.from_node {
-                    // Check if point is within node bounds
-                    if x >= target_node.x
-                        && x <= target_node.x + (target_node.radius * 2.0)
-                        && y >= target_node.y
-                        && y <= target_node.y + (target_node.radius * 2.0)
-                    {
-                        resetter.graph.connect_slots(
-                            Connection {
-                                host_node_id: connection_drag.from_node.clone(),
-                                host_slot_template_id: connection_drag.from_slot.clone(),
-                                target_node_id,
-                                target_slot_template_id: "incoming".to_string(),
-                                can_delete: true,
-                            },
-                            events,
-                        )?;
-                    }
-                }
+            // Dropping onto an incoming slot targets that slot; dropping onto a node body
+            // targets the first of its incoming slots that accepts the connection
+            let target = self
+                .incoming_slot_at(x, y, resetter.graph, &connection_drag.from_node, hit_radius)
+                .or_else(|| {
+                    resetter
+                        .graph
+                        .node_instances
+                        .values()
+                        .filter(|target_node| target_node.instance_id != connection_drag.from_node)
+                        .find(|target_node| {
+                            x >= target_node.x
+                                && x <= target_node.x + (target_node.radius * 2.0)
+                                && y >= target_node.y
+                                && y <= target_node.y + (target_node.radius * 2.0)
+                        })
+                        .and_then(|target_node| {
+                            resetter
+                                .graph
+                                .resolve_target_slot(
+                                    &connection_drag.from_node,
+                                    &connection_drag.from_slot,
+                                    &target_node.instance_id,
+                                )
+                                .map(|slot_id| (target_node.instance_id.clone(), slot_id))
+                        })
+                });
+            if let Some((target_node_id, target_slot_template_id)) = target {
+                resetter.graph.execute_command(
+                    GraphCommand::CreateConnection(Connection {
+                        host_node_id: connection_drag.from_node.clone(),
+                        host_slot_template_id: connection_drag.from_slot.clone(),
+                        target_node_id,
+                        target_slot_template_id,
+                        can_delete: true,
+                    }),
+                    events,
+                )?;
             }
         }
         // if we were dragging a node
@@ -812,104 +1014,84 @@ impl GraphCanvas {
                     x,
                     y,
                 });
+                if ix.selected_nodes.contains(moved_node) {
+                    for node_id in ix.selected_nodes.iter().filter(|id| *id != moved_node) {
+                        if let Some(node) = graph.node_instances.get(node_id) {
+                            events.emit(SystemEvent::NodeMoved {
+                                node: node_id.clone(),
+                                x: node.x + node.radius,
+                                y: node.y + node.radius,
+                            });
+                        }
+                    }
+                }
             }
 
             // Save current view transform to the view state
             if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
-                layout_engine.save_view_transform(ix);
+                layout_engine.save_view_transform(&ix.view_transform);
             }
 
             ix.is_dragging_node = false;
             ix.click_initiated_on_node = None;
-        } else if !ix.is_dragging_node {
-            //
-            for (instance_id, instance) in graph.node_instances.iter() {
-                // Check Slots
-                for slot in &instance.slots {
-                    if self.is_point_in_slot(x, y, instance, slot, graph) {
-                        let context_target = ContextMenuTarget::Slot {
-                            node_id: instance_id.clone(),
-                            slot_template_id: slot.slot_template_id.clone(),
-                        };
-                        ix.context_menu = Some(ContextMenu {
-                            x,
-                            y,
-                            target_type: context_target.clone(),
-                            items: vec![],
-                            field_edit_value: None,
-                        });
-                        events.emit(SystemEvent::ContextMenuOpened(context_target));
-                        return Ok(());
-                    }
-                }
+        } else if toggle_selection || ix.pointer_is_touch {
+            // Modified clicks and taps only change the selection; touch opens menus with a
+            // long press instead
+            ix.click_initiated_on_node = None;
+            if ix.context_menu.is_some() {
+                ix.context_menu = None;
+                events.emit(SystemEvent::ContextMenuClosed);
+            }
+            return Ok(());
+        } else if !ix.is_dragging_node && self.open_node_context_menu_at(x, y, graph, ix, events) {
+            return Ok(());
+        }
+        // Check to see if the click was on a connection
+        if self.open_connection_context_menu_at(x, y, graph, ix, events)? {
+            return Ok(());
+        }
+        // If we were dragging a node, stop any active simulation
+        if ix.is_dragging_node {
+            if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
+                layout_engine.stop_force_simulation();
+            }
+        }
 
-                // Calculate node center and dimensions for later checks
-                let center_x = instance.x + instance.radius;
-                let center_y = instance.y + instance.radius;
-                let radius = instance.radius;
-
-                // Calculate distance from center of node
-                let dx = x - center_x;
-                let dy = y - center_y;
-                let distance = (dx * dx + dy * dy).sqrt();
-
-                // Check if we clicked within node radius
-                if distance <= radius {
-                    // Get the template to access field information
-                    if let Some(template) = graph.node_templates.get(&instance.template_id) {
-                        // If node has fields, check if we clicked on a field
-                        if !instance.fields.is_empty() {
-                            // Calculate field positions
-                            let title_y = if !instance.fields.is_empty() {
-                                center_y - (instance.fields.len() as f64 * 15.0) / 2.0 - 15.0
-                            } else {
-                                center_y
-                            };
+        // If we were panning, save the view transform
+        if ix.is_panning {
+            if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
+                layout_engine.save_view_transform(&ix.view_transform);
+            }
+        }
 
-                            let mut y_offset = title_y + 20.0; // Start below the title
-
-                            // Check each field to see if it was clicked
-                            for field_instance in &instance.fields {
-                                // Field click area is approx +/- 10px vertically from text center
-                                if (y >= y_offset - 7.0) && (y <= y_offset + 7.0) {
-                                    // Check horizontal distance - if within reasonable bounds of the text
-                                    if distance <= radius * 0.8 {
-                                        // Somewhat arbitrary, just to make sure we're near the field text
-                                        // Get the field template for the menu title
-                                        if let Some(field_template) = template
-                                            .field_templates
-                                            .iter()
-                                            .find(|ft| ft.id == field_instance.field_template_id)
-                                        {
-                                            let context_target = ContextMenuTarget::Field {
-                                                node_id: instance_id.clone(),
-                                                field_template_id: field_instance
-                                                    .field_template_id
-                                                    .clone(),
-                                            };
-                                            ix.context_menu = Some(ContextMenu {
-                                                x,
-                                                y,
-                                                target_type: context_target.clone(),
-                                                items: vec![],
-                                                field_edit_value: Some(
-                                                    field_instance.value.clone(),
-                                                ),
-                                            });
-                                            events.emit(SystemEvent::ContextMenuOpened(
-                                                context_target,
-                                            ));
-                                            return Ok(());
-                                        }
-                                    }
-                                }
-                                y_offset += 15.0; // Move down for next field
-                            }
-                        }
-                    }
+        ix.is_dragging_node = false;
+        ix.is_panning = false;
+
+        if ix.context_menu.is_some() {
+            ix.context_menu = None;
+            events.emit(SystemEvent::ContextMenuClosed);
+        }
 
-                    // If we didn't click on a field but are within the node, open the node context menu
-                    let context_target = ContextMenuTarget::Node(instance_id.clone());
+        Ok(())
+    }
+    // Opens the menu for the slot, field or node under the point, if any
+    pub(crate) fn open_node_context_menu_at(
+        &self,
+        x: f64,
+        y: f64,
+        graph: &Graph,
+        ix: &mut InteractionState,
+        events: &EventSystem,
+    ) -> bool {
+        let hit_radius = self.slot_hit_radius(ix);
+        for (instance_id, instance) in graph.node_instances.iter() {
+            // Check Slots
+            for slot in &instance.slots {
+                if self.is_point_in_slot(x, y, instance, slot, graph, hit_radius) {
+                    let context_target = ContextMenuTarget::Slot {
+                        node_id: instance_id.clone(),
+                        slot_template_id: slot.slot_template_id.clone(),
+                    };
                     ix.context_menu = Some(ContextMenu {
                         x,
                         y,
@@ -918,11 +1100,95 @@ impl GraphCanvas {
                         field_edit_value: None,
                     });
                     events.emit(SystemEvent::ContextMenuOpened(context_target));
-                    return Ok(());
+                    return true;
+                }
+            }
+
+            // Calculate node center and dimensions for later checks
+            let center_x = instance.x + instance.radius;
+            let center_y = instance.y + instance.radius;
+            let radius = instance.radius;
+
+            // Calculate distance from center of node
+            let dx = x - center_x;
+            let dy = y - center_y;
+            let distance = (dx * dx + dy * dy).sqrt();
+
+            // Check if we clicked within node radius
+            if distance <= radius {
+                // Get the template to access field information
+                if let Some(template) = graph.node_templates.get(&instance.template_id) {
+                    // If node has fields, check if we clicked on a field
+                    if !instance.fields.is_empty() {
+                        // Calculate field positions
+                        let title_y = if !instance.fields.is_empty() {
+                            center_y - (instance.fields.len() as f64 * 15.0) / 2.0 - 15.0
+                        } else {
+                            center_y
+                        };
+
+                        let mut y_offset = title_y + 20.0; // Start below the title
+
+                        // Check each field to see if it was clicked
+                        for field_instance in &instance.fields {
+                            // Field click area is approx +/- 10px vertically from text center
+                            if (y >= y_offset - 7.0) && (y <= y_offset + 7.0) {
+                                // Check horizontal distance - if within reasonable bounds of the text
+                                if distance <= radius * 0.8 {
+                                    // Somewhat arbitrary, just to make sure we're near the field text
+                                    // Get the field template for the menu title
+                                    if let Some(field_template) = template
+                                        .field_templates
+                                        .iter()
+                                        .find(|ft| ft.id == field_instance.field_template_id)
+                                    {
+                                        let context_target = ContextMenuTarget::Field {
+                                            node_id: instance_id.clone(),
+                                            field_template_id: field_instance
+                                                .field_template_id
+                                                .clone(),
+                                        };
+                                        ix.context_menu = Some(ContextMenu {
+                                            x,
+                                            y,
+                                            target_type: context_target.clone(),
+                                            items: vec![],
+                                            field_edit_value: Some(field_instance.value.clone()),
+                                        });
+                                        events.emit(SystemEvent::ContextMenuOpened(context_target));
+                                        return true;
+                                    }
+                                }
+                            }
+                            y_offset += 15.0; // Move down for next field
+                        }
+                    }
                 }
+
+                // If we didn't click on a field but are within the node, open the node context menu
+                let context_target = ContextMenuTarget::Node(instance_id.clone());
+                ix.context_menu = Some(ContextMenu {
+                    x,
+                    y,
+                    target_type: context_target.clone(),
+                    items: vec![],
+                    field_edit_value: None,
+                });
+                events.emit(SystemEvent::ContextMenuOpened(context_target));
+                return true;
             }
         }
-        // Check to see if the click was on a connection
+        false
+    }
+
+    pub(crate) fn open_connection_context_menu_at(
+        &self,
+        x: f64,
+        y: f64,
+        graph: &Graph,
+        ix: &mut InteractionState,
+        events: &EventSystem,
+    ) -> GraphResult<bool> {
         for (instance_id, instance) in graph.node_instances.iter() {
             for slot in &instance.slots {
                 for connection in &slot.connections {
@@ -942,34 +1208,12 @@ impl GraphCanvas {
                             field_edit_value: None,
                         });
                         events.emit(SystemEvent::ContextMenuOpened(context_target));
-                        return Ok(());
+                        return Ok(true);
                     }
                 }
             }
         }
-        // If we were dragging a node, stop any active simulation
-        if ix.is_dragging_node {
-            if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
-                layout_engine.stop_force_simulation();
-            }
-        }
-
-        // If we were panning, save the view transform
-        if ix.is_panning {
-            if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
-                layout_engine.save_view_transform(ix);
-            }
-        }
-
-        ix.is_dragging_node = false;
-        ix.is_panning = false;
-
-        if ix.context_menu.is_some() {
-            ix.context_menu = None;
-            events.emit(SystemEvent::ContextMenuClosed);
-        }
-
-        Ok(())
+        Ok(false)
     }
     // Pan mode has been removed and integrated into Default mode
     fn internal_add_node_handle_mouse_down(
@@ -983,15 +1227,24 @@ impl GraphCanvas {
         if !self.config.is_mutable {
             return Ok(());
         }
-        let template_id = graph
+        let template = graph
             .node_templates
             .get(&ix.actively_creating_node_template_id)
             .ok_or(GraphError::TemplateNotFound(
                 ix.actively_creating_node_template_id.clone(),
-            ))?
-            .template_id
-            .clone();
-        graph.execute_command(GraphCommand::CreateNode { template_id, x, y }, events)?;
+            ))?;
+        let template_id = template.template_id.clone();
+        // Nodes are placed by their corner, but it is the center that goes on the grid
+        let radius = template.default_radius;
+        graph.execute_command(
+            GraphCommand::CreateNode {
+                template_id,
+                x: self.snap_to_grid_point(ix, x + radius) - radius,
+                y: self.snap_to_grid_point(ix, y + radius) - radius,
+                id: None,
+            },
+            events,
+        )?;
         Ok(())
     }
     fn internal_add_node_handle_mouse_move(
@@ -3,9 +3,23 @@ use crate::config::{
     GraphCanvasConfig, InitialConnection, InitialFieldValue, InitialNode, TemplateGroup,
     TemplateIdentifier,
 };
-use crate::graph::{FieldTemplate, FieldType, NodeTemplate, SlotPosition, SlotTemplate, SlotType};
+use crate::document::GraphDocument;
+use crate::errors::{GraphError, GraphResult, IntoJsError};
+use crate::events::ContextMenuTarget;
+use crate::events::{ListenerId, SystemEvent};
+use crate::graph::{
+    Connection, FieldConstraints, FieldTemplate, FieldType, GraphCommand, NodeInstance,
+    NodeTemplate, SlotPosition, SlotTemplate, SlotType,
+};
+use crate::keymap::Keymap;
+use crate::layout::LayoutType;
+use crate::validation::ValidationReport;
+use crate::{log, EditingSession, GraphCanvas, PendingConstraint, SessionState};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use tsify::Tsify;
+use wasm_bindgen::{prelude::*, JsCast};
 
 #[cfg(feature = "js")]
 #[derive(Serialize, Deserialize, Tsify)]
@@ -44,6 +58,8 @@ pub struct JsPartialConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub slot_radius: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub touch_hit_radius: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub node_templates: Option<Vec<JsPartialNodeTemplate>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub template_groups: Option<Vec<JsTemplateGroup>>,
@@ -59,13 +75,23 @@ pub struct JsPartialConfig {
     pub is_mutable: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_movable: Option<bool>,
+    // Overrides for individual actions; the rest keep their default keys
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<Keymap>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dom_id_prefix: Option<String>,
+    // A molecule_schema constraint schema as JSON; its templates are added to `node_templates`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub molecule_schema: Option<String>,
 }
 
-impl From<JsPartialConfig> for GraphCanvasConfig {
-    fn from(partial: JsPartialConfig) -> Self {
+impl TryFrom<JsPartialConfig> for GraphCanvasConfig {
+    type Error = GraphError;
+
+    fn try_from(partial: JsPartialConfig) -> GraphResult<Self> {
         let default = GraphCanvasConfig::default();
 
-        Self {
+        Ok(Self {
             context_menu_size: partial
                 .context_menu_size
                 .unwrap_or(default.context_menu_size),
@@ -79,12 +105,13 @@ impl From<JsPartialConfig> for GraphCanvasConfig {
                 .connection_control_point_distance
                 .unwrap_or(default.connection_control_point_distance),
             slot_radius: partial.slot_radius.unwrap_or(default.slot_radius),
+            touch_hit_radius: partial.touch_hit_radius.unwrap_or(default.touch_hit_radius),
             node_templates: partial
                 .node_templates
                 .unwrap_or(Default::default())
                 .into_iter()
-                .map(Into::into)
-                .collect(),
+                .map(TryInto::try_into)
+                .collect::<GraphResult<_>>()?,
             template_groups: partial
                 .template_groups
                 .unwrap_or_default()
@@ -104,7 +131,17 @@ impl From<JsPartialConfig> for GraphCanvasConfig {
             grid_size: partial.grid_size.unwrap_or(default.grid_size),
             is_mutable: partial.is_mutable.unwrap_or(default.is_mutable),
             is_movable: partial.is_movable.unwrap_or(default.is_movable),
-        }
+            keymap: {
+                let mut keymap = default.keymap;
+                if let Some(overrides) = partial.keymap {
+                    keymap.rebind(overrides);
+                }
+                keymap
+            },
+            dom_id_prefix: partial.dom_id_prefix,
+            // Imported separately by `new_js`, which records it
+            molecule_schema: None,
+        })
     }
 }
 
@@ -142,38 +179,6 @@ impl From<JsPartialConfig> for GraphCanvasConfig {
 //     }
 // }
 
-impl From<String> for SlotPosition {
-    fn from(value: String) -> Self {
-        match value.as_str() {
-            "Top" | "top" => SlotPosition::Top,
-            "Bottom" | "bottom" => SlotPosition::Bottom,
-            "Left" | "left" => SlotPosition::Left,
-            "Right" | "right" => SlotPosition::Right,
-            _ => SlotPosition::Top,
-        }
-    }
-}
-impl From<String> for SlotType {
-    fn from(value: String) -> Self {
-        match value.as_str() {
-            "Input" => SlotType::Incoming,
-            "Output" => SlotType::Outgoing,
-            _ => SlotType::Incoming,
-        }
-    }
-}
-
-impl From<String> for FieldType {
-    fn from(value: String) -> Self {
-        match value.as_str() {
-            "Boolean" | "bool" | "boolean" => FieldType::Boolean,
-            "Integer" | "int" | "integer" => FieldType::Integer,
-            "String" | "string" | "text" => FieldType::String,
-            _ => FieldType::String, // Default to string for unknown types
-        }
-    }
-}
-
 #[derive(Serialize, Deserialize, Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct JsPartialFieldTemplate {
@@ -181,16 +186,46 @@ pub struct JsPartialFieldTemplate {
     pub name: String,
     pub field_type: String,
     pub default_value: String,
+
+    // Optional fields, only read for the field types they apply to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>, // float
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>, // float
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<f64>, // float
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>, // enum
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<FieldConstraints>,
 }
 
-impl From<JsPartialFieldTemplate> for FieldTemplate {
-    fn from(js_field: JsPartialFieldTemplate) -> Self {
-        Self {
+impl TryFrom<JsPartialFieldTemplate> for FieldTemplate {
+    type Error = GraphError;
+
+    fn try_from(js_field: JsPartialFieldTemplate) -> GraphResult<Self> {
+        let field_type =
+            FieldType::from_name(&js_field.field_type, js_field.options).map_err(|err| {
+                GraphError::ConfigurationError(
+                    format!("Field {} is invalid", js_field.name),
+                    Box::new(err),
+                )
+            })?;
+        let field_type = match field_type {
+            FieldType::Float { .. } => FieldType::Float {
+                min: js_field.min,
+                max: js_field.max,
+                step: js_field.step,
+            },
+            field_type => field_type,
+        };
+        Ok(Self {
             id: generate_id(),
             name: js_field.name,
-            field_type: FieldType::from(js_field.field_type),
+            field_type,
             default_value: js_field.default_value,
-        }
+            constraints: js_field.constraints.unwrap_or_default(),
+        })
     }
 }
 
@@ -237,6 +272,8 @@ pub struct JsPartialSlotTemplate {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allowed_connections: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_source_slots: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub min_connections: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_connections: Option<usize>,
@@ -271,11 +308,15 @@ pub struct JsInitialConnection {
     pub host_slot_name: String,
     pub target_instance_id: String,
     pub can_delete: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_slot_name: Option<String>,
 }
 
 // Implement conversions with defaults
-impl From<JsPartialNodeTemplate> for NodeTemplate {
-    fn from(partial: JsPartialNodeTemplate) -> Self {
+impl TryFrom<JsPartialNodeTemplate> for NodeTemplate {
+    type Error = GraphError;
+
+    fn try_from(partial: JsPartialNodeTemplate) -> GraphResult<Self> {
         let default = NodeTemplate::new(&partial.name);
         let slot_templates = if let Some(partial_slot_templates) = partial.slot_templates {
             partial_slot_templates.into_iter().map(Into::into).collect()
@@ -285,12 +326,12 @@ impl From<JsPartialNodeTemplate> for NodeTemplate {
         let field_templates = if let Some(partial_field_templates) = partial.field_templates {
             partial_field_templates
                 .into_iter()
-                .map(Into::into)
-                .collect()
+                .map(TryInto::try_into)
+                .collect::<GraphResult<_>>()?
         } else {
             default.field_templates
         };
-        Self {
+        Ok(Self {
             template_id: default.template_id,
             name: partial.name,
             slot_templates,
@@ -305,7 +346,7 @@ impl From<JsPartialNodeTemplate> for NodeTemplate {
             can_modify_fields: partial
                 .can_modify_fields
                 .unwrap_or(default.can_modify_fields),
-        }
+        })
     }
 }
 
@@ -325,6 +366,9 @@ impl From<JsPartialSlotTemplate> for SlotTemplate {
             allowed_connections: partial
                 .allowed_connections
                 .unwrap_or(default.allowed_connections),
+            allowed_source_slots: partial
+                .allowed_source_slots
+                .unwrap_or(default.allowed_source_slots),
             min_connections: partial.min_connections.unwrap_or(default.min_connections),
             max_connections,
             can_modify_connections: partial
@@ -363,6 +407,7 @@ impl From<JsInitialConnection> for InitialConnection {
             can_delete: js_conn.can_delete,
             host_slot_name: js_conn.host_slot_name,
             target_instance_id: js_conn.target_instance_id,
+            target_slot_name: js_conn.target_slot_name,
         }
     }
 }
@@ -381,3 +426,486 @@ impl From<JsInitialFieldValue> for InitialFieldValue {
         }
     }
 }
+
+#[derive(Serialize, Deserialize, Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct JsViewTransform {
+    pub pan_x: f64,
+    pub pan_y: f64,
+    pub zoom: f64,
+}
+
+// The JS-facing API. Each method is a thin wrapper over the Rust method of the same name.
+#[wasm_bindgen]
+impl GraphCanvas {
+    /// Creates a node and returns its instance id.
+    #[wasm_bindgen(js_name = addNode)]
+    pub fn add_node_js(
+        &self,
+        template: TemplateIdentifier,
+        x: f64,
+        y: f64,
+    ) -> Result<String, JsValue> {
+        self.add_node(&template, x, y)
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = removeNode)]
+    pub fn remove_node_js(&self, node_id: &str) -> Result<(), JsValue> {
+        self.remove_node(node_id).map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = createConnection)]
+    pub fn create_connection_js(&self, connection: Connection) -> Result<(), JsValue> {
+        self.create_connection(connection)
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = deleteConnection)]
+    pub fn delete_connection_js(&self, connection: Connection) -> Result<(), JsValue> {
+        self.delete_connection(connection)
+            .map_err(|e| e.into_js_error())
+    }
+
+    /// `commands` is an array of GraphCommand, applied all together or not at all.
+    #[wasm_bindgen(js_name = executeBatch)]
+    pub fn execute_batch_js(&self, commands: JsValue) -> Result<(), JsValue> {
+        let commands: Vec<GraphCommand> = serde_wasm_bindgen::from_value(commands)?;
+        self.execute_batch(commands).map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = updateField)]
+    pub fn update_field_js(
+        &self,
+        node_id: &str,
+        field_template_id: &str,
+        value: &str,
+    ) -> Result<(), JsValue> {
+        self.update_field(node_id, field_template_id, value)
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = editField)]
+    pub fn open_inline_field_editor_js(
+        &self,
+        node_id: &str,
+        field_template_id: &str,
+    ) -> Result<(), JsValue> {
+        self.open_inline_field_editor(node_id, field_template_id)
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = getNode)]
+    pub fn get_node_js(&self, node_id: &str) -> Result<NodeInstance, JsValue> {
+        self.get_node(node_id).map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = getNodeIds)]
+    pub fn node_ids_js(&self) -> Result<Vec<String>, JsValue> {
+        self.node_ids().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = getSelectedNodes)]
+    pub fn selected_nodes_js(&self) -> Result<Vec<String>, JsValue> {
+        self.selected_nodes().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = setSelection)]
+    pub fn set_selection_js(&self, node_ids: Vec<String>) -> Result<(), JsValue> {
+        self.set_selection(node_ids).map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = clearSelection)]
+    pub fn clear_selection_js(&self) -> Result<(), JsValue> {
+        self.clear_selection().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = deleteSelection)]
+    pub fn delete_selection_js(&self) -> Result<(), JsValue> {
+        self.delete_selection().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = copySelection)]
+    pub fn copy_selection_js(&self) -> Result<String, JsValue> {
+        self.copy_selection().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = cutSelection)]
+    pub fn cut_selection_js(&self) -> Result<String, JsValue> {
+        self.cut_selection().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = paste)]
+    pub fn paste_js(&self) -> Result<Vec<String>, JsValue> {
+        self.paste().map_err(|e| e.into_js_error())
+    }
+
+    /// Pastes a payload returned by `copySelection`, e.g. one read back from the system clipboard.
+    #[wasm_bindgen(js_name = pasteJson)]
+    pub fn paste_json_js(&self, json: &str) -> Result<Vec<String>, JsValue> {
+        self.paste_json(json).map_err(|e| e.into_js_error())
+    }
+
+    /// Replaces the keys for each action in `overrides`; other actions keep theirs.
+    #[wasm_bindgen(js_name = rebindKeys)]
+    pub fn rebind_keys_js(&self, overrides: Keymap) -> Result<(), JsValue> {
+        self.rebind_keys(overrides).map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = getKeymap)]
+    pub fn keymap_js(&self) -> Result<Keymap, JsValue> {
+        self.keymap().map_err(|e| e.into_js_error())
+    }
+
+    /// Redraws on the next animation frame, for hosts that change something the canvas
+    /// cannot see.
+    #[wasm_bindgen(js_name = requestRedraw)]
+    pub fn request_redraw_js(&self) {
+        self.request_redraw();
+    }
+
+    #[wasm_bindgen(js_name = getDomIdPrefix)]
+    pub fn dom_id_prefix_js(&self) -> String {
+        self.dom_id_prefix().to_string()
+    }
+
+    #[wasm_bindgen(js_name = setSnapToGrid)]
+    pub fn set_snap_to_grid_js(&self, enabled: bool) -> Result<(), JsValue> {
+        self.set_snap_to_grid(enabled)
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = getSnapToGrid)]
+    pub fn snap_to_grid_js(&self) -> Result<bool, JsValue> {
+        self.snap_to_grid().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = setViewTransform)]
+    pub fn set_view_transform_js(&self, transform: JsViewTransform) -> Result<(), JsValue> {
+        self.set_view_transform(transform.pan_x, transform.pan_y, transform.zoom)
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = getViewTransform)]
+    pub fn view_transform_js(&self) -> Result<JsViewTransform, JsValue> {
+        let (pan_x, pan_y, zoom) = self.view_transform().map_err(|e| e.into_js_error())?;
+        Ok(JsViewTransform { pan_x, pan_y, zoom })
+    }
+
+    #[wasm_bindgen(js_name = setLayout)]
+    pub fn apply_layout_js(&self, layout: LayoutType) -> Result<(), JsValue> {
+        self.apply_layout(layout).map_err(|e| e.into_js_error())
+    }
+
+    /// Returns the graph as a document, failing if it does not conform to its templates.
+    #[wasm_bindgen(js_name = save)]
+    pub fn save_js(&self) -> Result<GraphDocument, JsValue> {
+        self.save()
+            .map(|graph| graph.to_document())
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = validate)]
+    pub fn validate_js(&self) -> Result<ValidationReport, JsValue> {
+        self.validate().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = saveJson)]
+    pub fn save_json_js(&self) -> Result<String, JsValue> {
+        self.save()
+            .and_then(|graph| graph.to_document().to_json())
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = load)]
+    pub fn load_js(&self, document: GraphDocument) -> Result<(), JsValue> {
+        self.load(document).map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = loadJson)]
+    pub fn load_json_js(&self, json: &str) -> Result<(), JsValue> {
+        self.load_json(json).map_err(|e| e.into_js_error())
+    }
+
+    /// The configured molecule_schema schema with the graph as its instances, failing if the
+    /// graph does not conform.
+    #[wasm_bindgen(js_name = exportMoleculeInstances)]
+    pub fn export_molecule_instances_js(&self) -> Result<String, JsValue> {
+        self.export_molecule_instances()
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = loadMoleculeInstances)]
+    pub fn load_molecule_instances_js(&self, schema: &str) -> Result<(), JsValue> {
+        self.load_molecule_instances(schema)
+            .map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = undo)]
+    pub fn undo_js(&self) -> Result<bool, JsValue> {
+        self.undo().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = redo)]
+    pub fn redo_js(&self) -> Result<bool, JsValue> {
+        self.redo().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = canUndo)]
+    pub fn can_undo_js(&self) -> bool {
+        self.can_undo()
+    }
+
+    #[wasm_bindgen(js_name = canRedo)]
+    pub fn can_redo_js(&self) -> bool {
+        self.can_redo()
+    }
+
+    #[wasm_bindgen(js_name = beginSession)]
+    pub fn begin_session_js(&self) -> Result<(), JsValue> {
+        self.begin_session().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = commitSession)]
+    pub fn commit_session_js(&self) -> Result<(), JsValue> {
+        self.commit_session().map_err(|e| e.into_js_error())
+    }
+
+    /// The session state and its remaining work, or undefined without a session.
+    #[wasm_bindgen(js_name = getSession)]
+    pub fn session_js(&self) -> Result<Option<EditingSession>, JsValue> {
+        self.session().map_err(|e| e.into_js_error())
+    }
+
+    #[wasm_bindgen(js_name = isDraft)]
+    pub fn is_draft_js(&self) -> bool {
+        self.is_draft()
+    }
+}
+
+// SystemEvent as seen from JS: an object with a `type` discriminator and named fields
+#[derive(Serialize, Tsify)]
+#[serde(tag = "type")]
+pub enum JsSystemEvent {
+    NodeMoved {
+        node: String,
+        x: f64,
+        y: f64,
+    },
+    ConnectionStarted {
+        node: String,
+        slot: String,
+    },
+    ConnectionCompleted {
+        connection: Connection,
+    },
+    ConnectionFailed {
+        reason: String,
+    },
+    ContextMenuOpened {
+        target: ContextMenuTarget,
+    },
+    ContextMenuClosed,
+    SelectionChanged {
+        nodes: Vec<String>,
+    },
+    CommandExecuted {
+        command: GraphCommand,
+    },
+    CommandFailed {
+        command: GraphCommand,
+        reason: String,
+    },
+    CommandUndone {
+        command: GraphCommand,
+    },
+    CommandRedone {
+        command: GraphCommand,
+    },
+    HistoryChanged {
+        can_undo: bool,
+        can_redo: bool,
+    },
+    SessionChanged {
+        state: SessionState,
+        pending: Vec<PendingConstraint>,
+    },
+    SyncRejected {
+        reason: String,
+    },
+}
+
+impl From<&SystemEvent> for JsSystemEvent {
+    fn from(event: &SystemEvent) -> Self {
+        match event {
+            SystemEvent::NodeMoved { node, x, y } => JsSystemEvent::NodeMoved {
+                node: node.clone(),
+                x: *x,
+                y: *y,
+            },
+            SystemEvent::ConnectionStarted { node, slot } => JsSystemEvent::ConnectionStarted {
+                node: node.clone(),
+                slot: slot.clone(),
+            },
+            SystemEvent::ConnectionCompleted(connection) => JsSystemEvent::ConnectionCompleted {
+                connection: connection.clone(),
+            },
+            SystemEvent::ConnectionFailed(reason) => JsSystemEvent::ConnectionFailed {
+                reason: reason.clone(),
+            },
+            SystemEvent::ContextMenuOpened(target) => JsSystemEvent::ContextMenuOpened {
+                target: target.clone(),
+            },
+            SystemEvent::ContextMenuClosed => JsSystemEvent::ContextMenuClosed,
+            SystemEvent::SelectionChanged(nodes) => JsSystemEvent::SelectionChanged {
+                nodes: nodes.clone(),
+            },
+            SystemEvent::CommandExecuted(command) => JsSystemEvent::CommandExecuted {
+                command: command.clone(),
+            },
+            SystemEvent::CommandFailed { command, reason } => JsSystemEvent::CommandFailed {
+                command: command.clone(),
+                reason: reason.to_string(),
+            },
+            SystemEvent::CommandUndone(command) => JsSystemEvent::CommandUndone {
+                command: command.clone(),
+            },
+            SystemEvent::CommandRedone(command) => JsSystemEvent::CommandRedone {
+                command: command.clone(),
+            },
+            SystemEvent::HistoryChanged { can_undo, can_redo } => JsSystemEvent::HistoryChanged {
+                can_undo: *can_undo,
+                can_redo: *can_redo,
+            },
+            SystemEvent::SessionChanged(session) => JsSystemEvent::SessionChanged {
+                state: session.state,
+                pending: session.pending.clone(),
+            },
+            SystemEvent::SyncRejected(reason) => JsSystemEvent::SyncRejected {
+                reason: reason.to_string(),
+            },
+        }
+    }
+}
+
+// JS functions are not Send, so event listeners cannot own them. Instead each listener holds
+// a key into this per-thread registry.
+#[derive(Default)]
+struct JsEventCallbacks {
+    next_key: u32,
+    callbacks: HashMap<u32, js_sys::Function>,
+    // (canvas_id, listener id) -> key, since listener ids are only unique per canvas
+    keys_by_listener: HashMap<(String, ListenerId), u32>,
+}
+
+thread_local! {
+    static JS_EVENT_CALLBACKS: RefCell<JsEventCallbacks> = RefCell::new(JsEventCallbacks::default());
+}
+
+fn invoke_js_event_callback(key: u32, event: &SystemEvent) {
+    let Some(callback) =
+        JS_EVENT_CALLBACKS.with(|registry| registry.borrow().callbacks.get(&key).cloned())
+    else {
+        return;
+    };
+    let payload = match JsSystemEvent::from(event)
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+    {
+        Ok(payload) => payload,
+        Err(err) => {
+            log(&format!("Could not serialize {}: {}", event.name(), err));
+            return;
+        }
+    };
+    // Events are emitted while the graph is locked, so the callback is deferred to a
+    // microtask; that way it can safely call back into the canvas.
+    let deferred = Closure::once_into_js(move || {
+        if let Err(err) = callback.call1(&JsValue::NULL, &payload) {
+            log(&format!("Event callback threw: {:?}", err));
+        }
+    });
+    // Workers have no window, but every global scope has queueMicrotask
+    let global = js_sys::global();
+    let queued = js_sys::Reflect::get(&global, &JsValue::from_str("queueMicrotask"))
+        .and_then(|queue| queue.dyn_into::<js_sys::Function>())
+        .and_then(|queue| queue.call1(&global, &deferred));
+    if let Err(err) = queued {
+        log(&format!(
+            "Could not defer the {} callback: {:?}",
+            event.name(),
+            err
+        ));
+    }
+}
+
+#[wasm_bindgen]
+impl GraphCanvas {
+    /// Calls `callback` with a `JsSystemEvent` whenever an event named `event_name` is emitted
+    /// ("*" subscribes to every event). Returns an id for `off`.
+    #[wasm_bindgen(js_name = on)]
+    pub fn on_js(&self, event_name: &str, callback: js_sys::Function) -> Result<u32, JsValue> {
+        let key = JS_EVENT_CALLBACKS.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            let key = registry.next_key;
+            registry.next_key += 1;
+            registry.callbacks.insert(key, callback);
+            key
+        });
+        let event_name = event_name.to_string();
+        let listener = Box::new(move |event: &SystemEvent| {
+            if event_name == "*" || event.name() == event_name {
+                invoke_js_event_callback(key, event);
+            }
+        });
+        let mut events = self
+            .events
+            .lock()
+            .map_err(|_| JsValue::from_str("Could not lock event system"))?;
+        let listener_id = events.subscribe(listener);
+        JS_EVENT_CALLBACKS.with(|registry| {
+            registry
+                .borrow_mut()
+                .keys_by_listener
+                .insert((self.canvas_id.clone(), listener_id), key);
+        });
+        Ok(listener_id)
+    }
+
+    /// Tears the canvas down (see `destroy`) and frees it; the JS object cannot be used
+    /// afterwards.
+    #[wasm_bindgen(js_name = destroy)]
+    pub fn destroy_js(self) -> Result<(), JsValue> {
+        JS_EVENT_CALLBACKS.with(|registry| {
+            let registry = &mut *registry.borrow_mut();
+            let callbacks = &mut registry.callbacks;
+            registry.keys_by_listener.retain(|(canvas_id, _), key| {
+                let keep = *canvas_id != self.canvas_id;
+                if !keep {
+                    callbacks.remove(key);
+                }
+                keep
+            });
+        });
+        self.destroy().map_err(|e| e.into_js_error())
+    }
+
+    /// Removes a callback registered with `on`. Returns false if the id was unknown, including
+    /// the ids of the canvas's own internal listeners.
+    #[wasm_bindgen(js_name = off)]
+    pub fn off_js(&self, listener_id: u32) -> Result<bool, JsValue> {
+        let Some(key) = JS_EVENT_CALLBACKS.with(|registry| {
+            registry
+                .borrow_mut()
+                .keys_by_listener
+                .remove(&(self.canvas_id.clone(), listener_id))
+        }) else {
+            return Ok(false);
+        };
+        JS_EVENT_CALLBACKS.with(|registry| registry.borrow_mut().callbacks.remove(&key));
+        let mut events = self
+            .events
+            .lock()
+            .map_err(|_| JsValue::from_str("Could not lock event system"))?;
+        Ok(events.unsubscribe(listener_id))
+    }
+}
@@ -0,0 +1,181 @@
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{window, HtmlElement};
+
+use crate::{
+    errors::{log_and_convert_error, GraphError, GraphResult},
+    events::SystemEvent,
+    interaction::InteractionMode,
+    keymap::{KeyAction, KeyBinding, Keymap},
+    GraphCanvas,
+};
+
+// Graph units moved per arrow key press
+const NUDGE_DISTANCE: f64 = 10.0;
+// Same sign convention as wheel deltas: negative zooms in
+const KEY_ZOOM_DELTA: f64 = 100.0;
+
+/// Key presses are looked up in the canvas keymap and run as actions on the selection,
+/// the view or the interaction mode.
+impl GraphCanvas {
+    /// Runs the action bound to `pressed`, if any. Returns whether the key was handled so
+    /// the caller can stop the browser's default behaviour.
+    pub(crate) fn handle_key_down(&self, pressed: &KeyBinding) -> Result<bool, JsValue> {
+        let action = self
+            .keymap
+            .lock()
+            .map_err(log_and_convert_error)?
+            .action_for(pressed);
+        let Some(action) = action else {
+            return Ok(false);
+        };
+        self.run_key_action(action).map_err(log_and_convert_error)?;
+        Ok(true)
+    }
+
+    fn run_key_action(&self, action: KeyAction) -> GraphResult<()> {
+        let is_editing_action = matches!(
+            action,
+            KeyAction::DeleteSelection
+                | KeyAction::NudgeLeft
+                | KeyAction::NudgeRight
+                | KeyAction::NudgeUp
+                | KeyAction::NudgeDown
+                | KeyAction::Cut
+                | KeyAction::Paste
+                | KeyAction::Undo
+                | KeyAction::Redo
+                | KeyAction::AddNodeMode
+        );
+        if is_editing_action && !self.config.is_mutable {
+            return Ok(());
+        }
+
+        match action {
+            KeyAction::DeleteSelection => self.delete_selection(),
+            KeyAction::Cancel => self.cancel_interaction(),
+            KeyAction::NudgeLeft => self.nudge_selection(-1.0, 0.0),
+            KeyAction::NudgeRight => self.nudge_selection(1.0, 0.0),
+            KeyAction::NudgeUp => self.nudge_selection(0.0, -1.0),
+            KeyAction::NudgeDown => self.nudge_selection(0.0, 1.0),
+            KeyAction::ZoomIn => self.zoom_at_center(-KEY_ZOOM_DELTA),
+            KeyAction::ZoomOut => self.zoom_at_center(KEY_ZOOM_DELTA),
+            KeyAction::SelectAll => self.set_selection(self.node_ids()?),
+            KeyAction::Copy => self.copy_selection().map(|_| ()),
+            KeyAction::Cut => self.cut_selection().map(|_| ()),
+            KeyAction::Paste => self.paste().map(|_| ()),
+            KeyAction::Undo => self.undo().map(|_| ()),
+            KeyAction::Redo => self.redo().map(|_| ()),
+            KeyAction::PointerMode => {
+                self.switch_mode("btn-pointer", InteractionMode::Default);
+                Ok(())
+            }
+            KeyAction::AddNodeMode => {
+                self.switch_mode("btn-add-node", InteractionMode::AddNode);
+                Ok(())
+            }
+        }
+    }
+
+    // Drops whatever gesture is in progress, innermost first
+    fn cancel_interaction(&self) -> GraphResult<()> {
+        let mut ix = self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?;
+        if ix.connection_drag.is_some() {
+            ix.connection_drag = None;
+            ix.click_initiated_on_slot = None;
+            ix.is_mouse_down = false;
+        } else if ix.selection_box.is_some() {
+            ix.selection_box = None;
+            ix.is_mouse_down = false;
+        } else if ix.context_menu.is_some() {
+            ix.context_menu = None;
+            let events = self
+                .events
+                .lock()
+                .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+            events.emit(SystemEvent::ContextMenuClosed);
+        } else if matches!(ix.mode, InteractionMode::AddNode) {
+            drop(ix);
+            self.switch_mode("btn-pointer", InteractionMode::Default);
+        } else {
+            drop(ix);
+            self.clear_selection()?;
+        }
+        Ok(())
+    }
+
+    fn nudge_selection(&self, direction_x: f64, direction_y: f64) -> GraphResult<()> {
+        let ix = self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?;
+        // With snapping on, move a whole grid cell so nodes stay aligned
+        let distance = if ix.snap_to_grid && self.config.grid_size > 0.0 {
+            self.config.grid_size
+        } else {
+            NUDGE_DISTANCE
+        };
+        let (dx, dy) = (direction_x * distance, direction_y * distance);
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        for node_id in &ix.selected_nodes {
+            if let Some(node) = graph.node_instances.get_mut(node_id) {
+                if !node.can_move {
+                    continue;
+                }
+                node.x += dx;
+                node.y += dy;
+                events.emit(SystemEvent::NodeMoved {
+                    node: node_id.clone(),
+                    x: node.x + node.radius,
+                    y: node.y + node.radius,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn zoom_at_center(&self, delta: f64) -> GraphResult<()> {
+        let canvas = window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(&self.canvas_id))
+            .ok_or(GraphError::Other("Canvas element not found".to_string()))?;
+        let rect = canvas.get_bounding_client_rect();
+        self.handle_zoom(delta, rect.width() / 2.0, rect.height() / 2.0)
+            .map_err(|err| GraphError::Other(format!("Zoom failed: {:?}", err.as_string())))
+    }
+
+    // Goes through the toolbar button when there is one so its state follows the mode
+    fn switch_mode(&self, toolbar_button_name: &str, mode: InteractionMode) {
+        let button = window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(&self.dom_id(toolbar_button_name)))
+            .and_then(|element| element.dyn_into::<HtmlElement>().ok());
+        match button {
+            Some(button) => button.click(),
+            None => self.set_interaction_mode(mode),
+        }
+    }
+
+    /// Replaces the bindings of each action in `overrides`; other actions keep their keys.
+    pub fn rebind_keys(&self, overrides: Keymap) -> GraphResult<()> {
+        self.keymap
+            .lock()
+            .map_err(|_| GraphError::LockFailed("keymap".to_string()))?
+            .rebind(overrides);
+        Ok(())
+    }
+
+    pub fn keymap(&self) -> GraphResult<Keymap> {
+        Ok(self
+            .keymap
+            .lock()
+            .map_err(|_| GraphError::LockFailed("keymap".to_string()))?
+            .clone())
+    }
+}
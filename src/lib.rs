@@ -1,22 +1,35 @@
+use dom_bindings::{request_redraw_for, unregister_redraw_target, DomBindings};
+use draw::SlotPositionCache;
 #[cfg(feature = "js")]
 use errors::IntoJsError;
-use errors::{GraphError, GraphResult};
+use errors::{setup_failed, GraphError, GraphResult};
+use graph::GraphCommand;
 use interaction::InteractionState;
 use layout::LayoutEngine;
+use pointer::PointerInput;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use wasm_bindgen::{prelude::*, JsCast};
 use web_sys::{window, HtmlCanvasElement, HtmlDivElement};
 
-mod common;
-mod config;
+// The DOM-free model lives in graph_canvas_core; this crate draws it and wires it to the page
+use graph_canvas_core::{
+    common, config, document, events, graph, history, keymap, layout, validation,
+};
+
+mod clipboard;
+mod dom_bindings;
 mod draw;
 mod errors;
-mod events;
-mod graph;
+mod inline_editor;
 mod interaction;
 #[cfg(feature = "js")]
 mod js;
-mod layout;
+mod keyboard;
+mod pointer;
 pub mod prelude;
 mod toolbar_ui;
 
@@ -26,7 +39,10 @@ pub use config::InitialFieldValue;
 pub use config::InitialNode;
 pub use config::TemplateGroup;
 pub use config::TemplateIdentifier;
+pub use document::GraphDocument;
+pub use document::CURRENT_DOCUMENT_VERSION;
 pub use graph::Connection;
+pub use graph::FieldConstraints;
 pub use graph::FieldTemplate;
 pub use graph::FieldType;
 pub use graph::Graph;
@@ -36,6 +52,13 @@ pub use graph::SlotInstance;
 pub use graph::SlotPosition;
 pub use graph::SlotTemplate;
 pub use graph::SlotType;
+pub use graph_canvas_core::ClipboardPayload;
+pub use graph_canvas_core::{import_molecule_schema, ImportedSchema};
+pub use graph_canvas_core::{
+    EditingSession, PendingConstraint, PendingConstraintKind, SessionState,
+};
+pub use graph_canvas_core::{GraphSyncAdapter, InMemorySyncAdapter, SyncMode};
+pub use history::CommandHistory;
 #[cfg(feature = "js")]
 pub use js::JsInitialConnection;
 #[cfg(feature = "js")]
@@ -50,7 +73,11 @@ pub use js::JsPartialNodeTemplate;
 pub use js::JsPartialSlotTemplate;
 #[cfg(feature = "js")]
 pub use js::JsTemplateGroup;
+#[cfg(feature = "js")]
+pub use js::{JsSystemEvent, JsViewTransform};
+pub use keymap::{KeyAction, KeyBinding, Keymap};
 pub use layout::LayoutType;
+pub use validation::{ValidationCode, ValidationIssue, ValidationReport, ValidationSeverity};
 
 #[wasm_bindgen]
 extern "C" {
@@ -66,6 +93,9 @@ extern "C" {
     fn warn(s: &str);
 }
 
+// Numbers the canvases on a page that were not given a DOM id prefix
+static NEXT_CANVAS_NUMBER: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Clone)]
 #[wasm_bindgen]
 pub struct GraphCanvas {
@@ -76,6 +106,16 @@ pub struct GraphCanvas {
     interaction: Arc<Mutex<InteractionState>>,
     events: Arc<Mutex<events::EventSystem>>,
     layout_engine: Arc<Mutex<LayoutEngine>>,
+    // Starts as config.keymap; hosts can rebind keys while the canvas runs
+    keymap: Arc<Mutex<Keymap>>,
+    // Every DOM id this canvas creates starts with this, so several canvases can share a page
+    dom_id_prefix: String,
+    // Slot angles carried between frames; only the render loop touches it
+    slot_positions: Rc<RefCell<SlotPositionCache>>,
+    // Listeners, observers and elements to release on `destroy`
+    dom_bindings: Rc<RefCell<DomBindings>>,
+    // Set when the graph changed in a way validation can see; the next frame revalidates
+    validation_stale: Arc<AtomicBool>,
 }
 impl std::fmt::Debug for GraphCanvas {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -94,11 +134,15 @@ impl GraphCanvas {
         container: &HtmlDivElement,
         js_config: JsPartialConfig,
     ) -> Result<GraphCanvas, JsValue> {
-        let config: GraphCanvasConfig = js_config.into();
-        log("GraphCanvas initializing");
-        let graph = Self::new_rust(container, config).map_err(|e| e.into_js_error());
-        log("GraphCanvas initialized");
-        graph
+        let mut js_config = js_config;
+        let molecule_schema = js_config.molecule_schema.take();
+        let mut config = GraphCanvasConfig::try_from(js_config).map_err(|e| e.into_js_error())?;
+        if let Some(schema) = molecule_schema {
+            config
+                .import_molecule_schema(&schema)
+                .map_err(|e| e.into_js_error())?;
+        }
+        Self::new_rust(container, config).map_err(|e| e.into_js_error())
     }
 }
 impl GraphCanvas {
@@ -111,8 +155,6 @@ impl GraphCanvas {
 
         let mut graph = Graph::new();
 
-        // Register a test template
-        graph.register_template(GraphCanvas::get_test_template());
         // Register templates
         for template in &config.node_templates {
             graph.register_template(template.clone());
@@ -122,34 +164,80 @@ impl GraphCanvas {
         graph.create_initial_nodes(&config.initial_nodes)?;
 
         let events = Arc::new(Mutex::new(events::EventSystem::new()));
-        events.lock().unwrap().subscribe(Box::new(|event| {
-            log(&format!("{:?}", event));
-        }));
 
-        let (canvas, toolbar_container) =
-            GraphCanvas::create_canvas(container).map_err(GraphError::SetupFailed)?;
+        let dom_id_prefix = config.dom_id_prefix.clone().unwrap_or_else(|| {
+            format!(
+                "graph-canvas-{}",
+                NEXT_CANVAS_NUMBER.fetch_add(1, Ordering::Relaxed)
+            )
+        });
+        let (canvas, graph_container, toolbar_container) =
+            GraphCanvas::create_canvas(container, &dom_id_prefix).map_err(setup_failed)?;
 
         // Create GraphCanvas...
-        let canvas_clone = canvas.clone();
+        let canvas_rect = canvas.get_bounding_client_rect();
         let graph_canvas = GraphCanvas {
             config: Arc::new(config.clone()),
-            interaction: Arc::new(Mutex::new(InteractionState::new())),
+            interaction: Arc::new(Mutex::new(InteractionState {
+                snap_to_grid: config.snap_to_grid,
+                ..InteractionState::new()
+            })),
             graph: Arc::new(Mutex::new(graph)),
             canvas_id: canvas.id().to_string(),
             events,
-            layout_engine: Arc::new(Mutex::new(LayoutEngine::new(canvas_clone.id().clone()))),
+            layout_engine: Arc::new(Mutex::new(LayoutEngine::new(
+                canvas_rect.width(),
+                canvas_rect.height(),
+            ))),
+            keymap: Arc::new(Mutex::new(config.keymap.clone())),
+            dom_id_prefix,
+            slot_positions: Rc::new(RefCell::new(SlotPositionCache::new())),
+            dom_bindings: Rc::new(RefCell::new(DomBindings::default())),
+            validation_stale: Arc::new(AtomicBool::new(true)),
         };
+        {
+            let mut dom_bindings = graph_canvas.dom_bindings.borrow_mut();
+            dom_bindings.add_element(toolbar_container.clone().into());
+            dom_bindings.add_element(graph_container.into());
+        }
+        // Anything that emits an event has changed what should be on screen
+        let canvas_id = graph_canvas.canvas_id.clone();
+        graph_canvas
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?
+            .subscribe(Box::new(move |_| request_redraw_for(&canvas_id)));
+        // Only commands, undo/redo and sync rollbacks change what validation finds
+        let validation_stale = graph_canvas.validation_stale.clone();
+        graph_canvas
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?
+            .subscribe(Box::new(move |event| {
+                if matches!(
+                    event,
+                    events::SystemEvent::CommandExecuted(_)
+                        | events::SystemEvent::CommandUndone(_)
+                        | events::SystemEvent::CommandRedone(_)
+                        | events::SystemEvent::SyncRejected(_)
+                ) {
+                    validation_stale.store(true, Ordering::Relaxed);
+                }
+            }));
+        graph_canvas
+            .observe_resize(container, &canvas)
+            .map_err(setup_failed)?;
 
         // Setup toolbar based on config
         // if config.show_default_toolbar {
         graph_canvas
             .setup_default_toolbar(&toolbar_container, &config, &graph_canvas)
-            .map_err(GraphError::SetupFailed)?;
+            .map_err(setup_failed)?;
         // }
         // if let Some(custom_toolbar) = &config.custom_toolbar {
         //     toolbar_container
         //         .append_child(custom_toolbar)
-        //         .map_err(|err| GraphError::SetupFailed(err))?;
+        //         .map_err(setup_failed)?;
         // }
         //
         graph_canvas.setup_events()?;
@@ -167,27 +255,31 @@ impl GraphCanvas {
 
         graph_canvas.start_render_loop()?;
 
-        log(&format!("{:#?}", graph_canvas));
-
         Ok(graph_canvas)
     }
 
     fn create_canvas(
         container: &web_sys::HtmlElement,
-    ) -> Result<(HtmlCanvasElement, HtmlDivElement), JsValue> {
+        dom_id_prefix: &str,
+    ) -> Result<(HtmlCanvasElement, HtmlDivElement, HtmlDivElement), JsValue> {
         // Create canvas
         let document = window().unwrap().document().unwrap();
         let canvas = document.create_element("canvas")?;
-        canvas.set_id("graph-canvas-canvas-element");
+        canvas.set_id(&format!("{}-canvas", dom_id_prefix));
         let canvas = canvas.dyn_into::<HtmlCanvasElement>()?;
 
         // Set canvas style to fill container
         canvas.style().set_property("width", "100%")?;
         canvas.style().set_property("height", "100%")?;
+        // Focusable so it can receive keyboard shortcuts
+        canvas.set_tab_index(0);
+        canvas.style().set_property("outline", "none")?;
+        // Touch gestures are handled here rather than scrolling or zooming the page
+        canvas.style().set_property("touch-action", "none")?;
         canvas.style().set_property("display", "block")?;
 
         let graph_container = document.create_element("div")?;
-        graph_container.set_id("graph_canvas_container");
+        graph_container.set_id(&format!("{}-graph-container", dom_id_prefix));
         let graph_container = graph_container.dyn_into::<web_sys::HtmlDivElement>()?;
         graph_container.style().set_property("width", "100%")?;
         graph_container.style().set_property("min-width", "400px")?;
@@ -198,10 +290,14 @@ impl GraphCanvas {
             .style()
             .set_property("min-height", "400px")?;
         graph_container.style().set_property("display", "block")?;
+        // Inline field editors are positioned against the container
+        graph_container
+            .style()
+            .set_property("position", "relative")?;
         graph_container.append_child(&canvas)?;
 
         let toolbar_container = document.create_element("div")?;
-        toolbar_container.set_id("graph_canvas_container");
+        toolbar_container.set_id(&format!("{}-toolbar-container", dom_id_prefix));
         let toolbar_container = toolbar_container.dyn_into::<web_sys::HtmlDivElement>()?;
         toolbar_container.style().set_property("width", "100%")?;
         toolbar_container.style().set_property("height", "40px")?;
@@ -211,10 +307,22 @@ impl GraphCanvas {
         container.append_child(&toolbar_container)?;
         container.append_child(&graph_container)?;
 
-        // Create resize observer
-        let canvas_weak = canvas.clone();
+        // Initial size
+        canvas.set_width(container.client_width() as u32);
+
+        Ok((canvas, graph_container, toolbar_container))
+    }
+
+    // Keeps the canvas resolution and the layout engine's viewport in step with the container
+    fn observe_resize(
+        &self,
+        container: &web_sys::HtmlElement,
+        canvas: &HtmlCanvasElement,
+    ) -> Result<(), JsValue> {
+        let canvas = canvas.clone();
+        let canvas_id = self.canvas_id.clone();
+        let layout_engine = self.layout_engine.clone();
         let resize_callback = Closure::wrap(Box::new(move |_: js_sys::Array| {
-            let canvas = canvas_weak.clone();
             let parent = canvas.parent_element().unwrap();
 
             // Get parent's client dimensions
@@ -224,18 +332,23 @@ impl GraphCanvas {
             // Update canvas dimensions
             canvas.set_width(width as u32);
             canvas.set_height(height as u32);
+
+            let rect = canvas.get_bounding_client_rect();
+            if let Ok(mut layout_engine) = layout_engine.lock() {
+                layout_engine.set_viewport(rect.width(), rect.height());
+            }
+            // Resizing the canvas clears it
+            request_redraw_for(&canvas_id);
         }) as Box<dyn FnMut(js_sys::Array)>);
 
         let observer = web_sys::ResizeObserver::new(resize_callback.as_ref().unchecked_ref())?;
         observer.observe(container);
 
-        // Keep the closure alive
-        resize_callback.forget();
-
-        // Initial size
-        canvas.set_width(container.client_width() as u32);
-
-        Ok((canvas, toolbar_container))
+        // Kept alive until `destroy` disconnects the observer
+        self.dom_bindings
+            .borrow_mut()
+            .set_resize_observer(observer, resize_callback);
+        Ok(())
     }
 
     fn setup_default_toolbar(
@@ -282,7 +395,7 @@ impl GraphCanvas {
     }
 
     fn setup_events(&self) -> Result<(), GraphError> {
-        // Mouse Down Handler
+        // Pointer Down Handler (mouse, pen and touch)
         let self_clone = self.clone();
         let canvas = window()
             .unwrap()
@@ -293,38 +406,53 @@ impl GraphCanvas {
             .dyn_into::<web_sys::HtmlCanvasElement>()
             .unwrap();
         let canvas_clone = canvas.clone();
-        let mouse_down = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-            let rect = canvas_clone.get_bounding_client_rect();
-            let x = event.client_x() as f64 - rect.left();
-            let y = event.client_y() as f64 - rect.top();
-            self_clone.handle_mouse_down(x, y).unwrap();
+        let pointer_down = Closure::wrap(Box::new(move |event: web_sys::PointerEvent| {
+            let input = PointerInput::from_event(&event, &canvas_clone);
+            // Keep receiving this pointer's moves even if it leaves the canvas mid-drag
+            let _ = canvas_clone.set_pointer_capture(input.pointer_id);
+            // Keyboard shortcuts go to the canvas once it has been clicked
+            let _ = canvas_clone.focus();
+            if let Err(e) = self_clone.handle_pointer_down(&input) {
+                log(&format!("{:?}", e.as_string()));
+            }
+            // "Edit" may have been picked from the context menu
+            if let Err(e) = self_clone.open_requested_field_editor() {
+                log(&format!("{:?}", e.as_string()));
+            }
+            self_clone.request_redraw();
         }) as Box<dyn FnMut(_)>);
 
-        // Mouse Move Handler
+        // Pointer Move Handler
         let self_clone = self.clone();
         let canvas_clone = canvas.clone();
-        let mouse_move = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-            let rect = canvas_clone.get_bounding_client_rect();
-            let x = event.client_x() as f64 - rect.left();
-            let y = event.client_y() as f64 - rect.top();
-            let dx = event.movement_x();
-            let dy = event.movement_y();
-            self_clone
-                .handle_mouse_move(x, y, dx as f64, dy as f64)
-                .unwrap();
+        let pointer_move = Closure::wrap(Box::new(move |event: web_sys::PointerEvent| {
+            let input = PointerInput::from_event(&event, &canvas_clone);
+            if let Err(e) = self_clone.handle_pointer_move(&input) {
+                log(&format!("{:?}", e.as_string()));
+            }
+            // Hover highlights change too, not just drags
+            self_clone.request_redraw();
         }) as Box<dyn FnMut(_)>);
 
-        // Mouse Up Handler
+        // Pointer Up Handler
         let self_clone = self.clone();
         let canvas_clone = canvas.clone();
-        let mouse_up = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
-            let rect = canvas_clone.get_bounding_client_rect();
-            let x = event.client_x() as f64 - rect.left();
-            let y = event.client_y() as f64 - rect.top();
-            match self_clone.handle_mouse_up(x, y) {
+        let pointer_up = Closure::wrap(Box::new(move |event: web_sys::PointerEvent| {
+            let input = PointerInput::from_event(&event, &canvas_clone);
+            match self_clone.handle_pointer_up(&input) {
                 Ok(_) => {}
                 Err(e) => log(&format!("{:?}", e.as_string())),
             }
+            self_clone.request_redraw();
+        }) as Box<dyn FnMut(_)>);
+
+        // Pointer Cancel Handler (the browser took over the touch, e.g. for scrolling)
+        let self_clone = self.clone();
+        let pointer_cancel = Closure::wrap(Box::new(move |event: web_sys::PointerEvent| {
+            if let Err(e) = self_clone.handle_pointer_cancel(event.pointer_id()) {
+                log(&format!("{:?}", e.as_string()));
+            }
+            self_clone.request_redraw();
         }) as Box<dyn FnMut(_)>);
 
         // Mouse Wheel Handler for zooming
@@ -345,161 +473,69 @@ impl GraphCanvas {
                 Ok(_) => {}
                 Err(e) => log(&format!("Zoom error: {:?}", e.as_string())),
             }
+            self_clone.request_redraw();
         }) as Box<dyn FnMut(_)>);
 
+        // Double Click Handler for editing fields in place
+        let self_clone = self.clone();
         let canvas_clone = canvas.clone();
-        // Add event listeners
-        canvas_clone
-            .add_event_listener_with_callback("mousedown", mouse_down.as_ref().unchecked_ref())
-            .map_err(GraphError::SetupFailed)?;
-        canvas_clone
-            .add_event_listener_with_callback("mousemove", mouse_move.as_ref().unchecked_ref())
-            .map_err(GraphError::SetupFailed)?;
-        canvas_clone
-            .add_event_listener_with_callback("mouseup", mouse_up.as_ref().unchecked_ref())
-            .map_err(GraphError::SetupFailed)?;
-        canvas_clone
-            .add_event_listener_with_callback("wheel", wheel_handler.as_ref().unchecked_ref())
-            .map_err(GraphError::SetupFailed)?;
-
-        // Prevent memory leaks
-        mouse_down.forget();
-        mouse_move.forget();
-        mouse_up.forget();
-        wheel_handler.forget();
+        let double_click = Closure::wrap(Box::new(move |event: web_sys::MouseEvent| {
+            let rect = canvas_clone.get_bounding_client_rect();
+            let x = event.client_x() as f64 - rect.left();
+            let y = event.client_y() as f64 - rect.top();
+            match self_clone.handle_double_click(x, y) {
+                Ok(true) => event.prevent_default(),
+                Ok(false) => {}
+                Err(e) => log(&format!("Double click error: {}", e)),
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        // Key Down Handler
+        let self_clone = self.clone();
+        let key_down = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+            let pressed = KeyBinding {
+                key: event.key(),
+                ctrl: event.ctrl_key() || event.meta_key(),
+                shift: event.shift_key(),
+                alt: event.alt_key(),
+            };
+            match self_clone.handle_key_down(&pressed) {
+                Ok(true) => {
+                    event.prevent_default();
+                    self_clone.request_redraw();
+                }
+                Ok(false) => {}
+                Err(e) => log(&format!("Key error: {:?}", e.as_string())),
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        // Add event listeners; they stay registered until `destroy`
+        let mut dom_bindings = self.dom_bindings.borrow_mut();
+        dom_bindings
+            .listen(&canvas, "pointerdown", pointer_down)
+            .map_err(setup_failed)?;
+        dom_bindings
+            .listen(&canvas, "pointermove", pointer_move)
+            .map_err(setup_failed)?;
+        dom_bindings
+            .listen(&canvas, "pointerup", pointer_up)
+            .map_err(setup_failed)?;
+        dom_bindings
+            .listen(&canvas, "pointercancel", pointer_cancel)
+            .map_err(setup_failed)?;
+        dom_bindings
+            .listen(&canvas, "wheel", wheel_handler)
+            .map_err(setup_failed)?;
+        dom_bindings
+            .listen(&canvas, "dblclick", double_click)
+            .map_err(setup_failed)?;
+        dom_bindings
+            .listen(&canvas, "keydown", key_down)
+            .map_err(setup_failed)?;
 
         Ok(())
     }
 
-    fn get_test_template() -> NodeTemplate {
-        NodeTemplate {
-            can_modify_slots: true,
-            min_instances: Some(1),
-            max_instances: None,
-            can_delete: true,
-            can_create: true,
-            template_id: "test_node".to_string(),
-            name: "Test Node".to_string(),
-            slot_templates: vec![
-                SlotTemplate {
-                    id: "first".to_string(),
-                    name: "First".to_string(),
-                    position: SlotPosition::Right,
-                    slot_type: SlotType::Outgoing,
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-                SlotTemplate {
-                    id: "second".to_string(),
-                    name: "Second".to_string(),
-                    position: SlotPosition::Right,
-                    slot_type: SlotType::Outgoing,
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-                SlotTemplate {
-                    id: "third".to_string(),
-                    name: "Third".to_string(),
-                    position: SlotPosition::Top,
-                    slot_type: SlotType::Outgoing,
-
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-                SlotTemplate {
-                    id: "fourth".to_string(),
-                    name: "Fourth".to_string(),
-                    position: SlotPosition::Top,
-                    slot_type: SlotType::Outgoing,
-
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-                SlotTemplate {
-                    id: "fifth".to_string(),
-                    name: "Fifth".to_string(),
-                    position: SlotPosition::Bottom,
-                    slot_type: SlotType::Outgoing,
-
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-                SlotTemplate {
-                    id: "sixth".to_string(),
-                    name: "Sixth".to_string(),
-                    position: SlotPosition::Bottom,
-                    slot_type: SlotType::Outgoing,
-
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-                SlotTemplate {
-                    id: "seventh".to_string(),
-                    name: "Seventh".to_string(),
-                    position: SlotPosition::Left,
-                    slot_type: SlotType::Outgoing,
-
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-                SlotTemplate {
-                    id: "eigth".to_string(),
-                    name: "Eigth".to_string(),
-                    position: SlotPosition::Left,
-                    slot_type: SlotType::Outgoing,
-
-                    allowed_connections: vec!["test_node".to_string(), "Node".to_string()],
-
-                    min_connections: 2,
-                    max_connections: Some(3),
-                    can_modify_connections: true,
-                },
-            ],
-            // Add field templates for testing
-            field_templates: vec![
-                FieldTemplate {
-                    id: "bool_field".to_string(),
-                    name: "Active".to_string(),
-                    field_type: FieldType::Boolean,
-                    default_value: "true".to_string(),
-                },
-                FieldTemplate {
-                    id: "int_field".to_string(),
-                    name: "Count".to_string(),
-                    field_type: FieldType::Integer,
-                    default_value: "42".to_string(),
-                },
-                FieldTemplate {
-                    id: "string_field".to_string(),
-                    name: "Label".to_string(),
-                    field_type: FieldType::String,
-                    default_value: "Test".to_string(),
-                },
-            ],
-            default_radius: 150.0,
-            default_color: "red".to_string(),
-            can_modify_fields: true,
-        }
-    }
     pub fn save(&self) -> GraphResult<Graph> {
         let graph = self.graph.try_lock();
         if graph.is_err() {
@@ -508,65 +544,478 @@ impl GraphCanvas {
             });
         }
         let graph = graph.unwrap();
-        self.check_conformity(&graph)?;
+        graph
+            .check_conformity()
+            .map_err(|err| GraphError::SaveFailed {
+                reason: Box::new(err),
+            })?;
         Ok(graph.clone())
     }
 
-    fn check_conformity(&self, graph: &Graph) -> GraphResult<()> {
-        let node_errors = graph
-            .node_instances
-            .values()
-            .filter_map(|instance| {
-                let template = graph
-                    .get_node_template_by_identifier(&TemplateIdentifier::Id(
-                        instance.template_id.clone(),
-                    ))
-                    .unwrap();
-                let slot_errors = instance
-                    .slots
-                    .iter()
-                    .filter_map(|slot_instance| {
-                        let slot_template = template
-                            .slot_templates
-                            .iter()
-                            .find(|slot_template| {
-                                slot_template.id == slot_instance.slot_template_id
-                            })
-                            .unwrap();
-                        if slot_template.min_connections > slot_instance.connections.len()
-                            || slot_template
-                                .max_connections
-                                .is_some_and(|max_connections| {
-                                    max_connections < slot_instance.connections.len()
-                                })
-                        {
-                            return Some(GraphError::SlotMalformed {
-                                template_name: template.name.clone(),
-                                slot_name: slot_template.name.clone(),
-                                min: slot_template.min_connections,
-                                max: slot_template.max_connections,
-                                actual: slot_instance.connections.len(),
-                            });
-                        }
-                        None
-                    })
-                    .collect::<Vec<_>>();
-                if slot_errors.is_empty() {
-                    return None;
-                }
-                Some(slot_errors)
-            })
-            .collect::<Vec<_>>();
-        if node_errors.is_empty() {
+    /// Checks the live graph against its templates. Unlike `save`, this never fails on
+    /// an invalid graph; the problems are listed in the report.
+    pub fn validate(&self) -> GraphResult<ValidationReport> {
+        Ok(self
+            .graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .validate())
+    }
+
+    /// Replaces the live graph with the one described by `document`.
+    /// Selection, hover state and undo history are discarded.
+    pub fn load(&self, document: GraphDocument) -> GraphResult<()> {
+        self.replace_graph(Graph::from_document(document)?)
+    }
+
+    /// Parses a JSON document produced by `GraphDocument::to_json` and loads it.
+    pub fn load_json(&self, json: &str) -> GraphResult<()> {
+        self.load(GraphDocument::from_json(json)?)
+    }
+
+    /// Exports the graph as the molecule_schema schema the canvas was configured with, its
+    /// `instance_library` holding the graph's nodes. Fails if the graph does not conform to
+    /// its templates.
+    pub fn export_molecule_instances(&self) -> GraphResult<String> {
+        let schema = self.config.molecule_schema.as_deref().ok_or_else(|| {
+            GraphError::InvalidSchema(
+                "The canvas was not configured with a molecule_schema schema".to_string(),
+            )
+        })?;
+        self.graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .to_molecule_instances(schema)
+    }
+
+    /// Replaces the live nodes with the library instances of an exported schema, keeping the
+    /// current templates, and lays them out.
+    pub fn load_molecule_instances(&self, schema: &str) -> GraphResult<()> {
+        let new_graph = self
+            .graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .with_molecule_instances(schema)?;
+        self.replace_graph(new_graph)?;
+
+        let mut layout_engine = self
+            .layout_engine
+            .lock()
+            .map_err(|_| GraphError::LockFailed("layout engine".to_string()))?;
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        layout_engine.arrange_unplaced_graph(&mut graph);
+        self.request_redraw();
+        Ok(())
+    }
+
+    // Swaps in a whole new graph, dropping everything that referred to the old one
+    fn replace_graph(&self, new_graph: Graph) -> GraphResult<()> {
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let mut ix = self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?;
+        let had_session = graph.session.is_some();
+        // The adapter stays attached and is sent the difference on its next sync
+        let sync = graph.sync.take();
+        *graph = new_graph;
+        graph.sync = sync;
+        self.validation_stale.store(true, Ordering::Relaxed);
+
+        ix.context_menu = None;
+        ix.connection_drag = None;
+        ix.is_dragging_node = false;
+        ix.click_initiated_on_node = None;
+        ix.click_initiated_on_slot = None;
+        ix.selection_box = None;
+        ix.hovered_node = None;
+        ix.hovered_slot = None;
+        ix.hovered_connection = None;
+
+        if let Ok(mut layout_engine) = self.layout_engine.lock() {
+            layout_engine.stop_force_simulation();
+            layout_engine.clear_snapshots();
+        }
+        self.reset_slot_positions();
+        if let Ok(events) = self.events.lock() {
+            ix.set_selection(BTreeSet::new(), &events);
+            graph.emit_history_changed(&events);
+            // A loaded graph is a fresh draft of the session the host already started
+            if had_session {
+                graph.begin_session(&events);
+            }
+            if graph.sync_mode() == Some(SyncMode::Immediate) {
+                graph.flush_sync(&events)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Runs a command against the live graph, the same way toolbar and pointer edits do
+    fn execute(&self, command: GraphCommand) -> GraphResult<()> {
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        graph.execute_command(command, &events)
+    }
+
+    /// Creates a node from the identified template and returns its instance id.
+    pub fn add_node(&self, template: &TemplateIdentifier, x: f64, y: f64) -> GraphResult<String> {
+        let template_id = self
+            .graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .get_node_template_by_identifier(template)
+            .ok_or(GraphError::TemplateNotFound(template.to_string()))?
+            .template_id;
+        let id = common::generate_id();
+        self.execute(GraphCommand::CreateNode {
+            template_id,
+            x,
+            y,
+            id: Some(id.clone()),
+        })?;
+        Ok(id)
+    }
+
+    pub fn remove_node(&self, node_id: &str) -> GraphResult<()> {
+        self.execute(GraphCommand::DeleteNode(node_id.to_string()))?;
+        self.clear_stale_interaction_state()
+    }
+
+    /// Ids of the selected nodes, sorted.
+    pub fn selected_nodes(&self) -> GraphResult<Vec<String>> {
+        let ix = self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?;
+        Ok(ix.selected_nodes.iter().cloned().collect())
+    }
+
+    /// Replaces the selection. Fails without changing it if any id is unknown.
+    pub fn set_selection(&self, node_ids: Vec<String>) -> GraphResult<()> {
+        let graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        if let Some(missing) = node_ids
+            .iter()
+            .find(|id| !graph.node_instances.contains_key(*id))
+        {
+            return Err(GraphError::NodeNotFound(missing.clone()));
+        }
+        let mut ix = self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        ix.set_selection(node_ids.into_iter().collect(), &events);
+        Ok(())
+    }
+
+    pub fn clear_selection(&self) -> GraphResult<()> {
+        self.set_selection(vec![])
+    }
+
+    /// Deletes every selected node as a single undoable command.
+    pub fn delete_selection(&self) -> GraphResult<()> {
+        let selected = self.selected_nodes()?;
+        if selected.is_empty() {
             return Ok(());
         }
-        Err(GraphError::SaveFailed {
-            reason: Box::new(GraphError::ListOfErrors(
-                node_errors.into_iter().flatten().collect::<Vec<_>>(),
-            )),
+        self.execute(GraphCommand::DeleteNodes(selected))?;
+        self.clear_stale_interaction_state()
+    }
+
+    pub fn create_connection(&self, connection: Connection) -> GraphResult<()> {
+        self.execute(GraphCommand::CreateConnection(connection))
+    }
+
+    pub fn delete_connection(&self, connection: Connection) -> GraphResult<()> {
+        self.execute(GraphCommand::DeleteConnection(connection))
+    }
+
+    pub fn update_field(
+        &self,
+        node_id: &str,
+        field_template_id: &str,
+        value: &str,
+    ) -> GraphResult<()> {
+        self.execute(GraphCommand::UpdateField {
+            node_id: node_id.to_string(),
+            field_template_id: field_template_id.to_string(),
+            new_value: value.to_string(),
         })
     }
-    pub fn apply_layout(&mut self, layout: LayoutType) -> Result<(), GraphError> {
+
+    /// Runs `commands` as one undoable command. If any of them fails the graph is left
+    /// untouched.
+    pub fn execute_batch(&self, commands: Vec<GraphCommand>) -> GraphResult<()> {
+        self.execute(GraphCommand::Batch(commands))?;
+        self.clear_stale_interaction_state()
+    }
+
+    pub fn get_node(&self, node_id: &str) -> GraphResult<NodeInstance> {
+        self.graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .node_instances
+            .get(node_id)
+            .cloned()
+            .ok_or(GraphError::NodeNotFound(node_id.to_string()))
+    }
+
+    /// The prefix of every DOM id this canvas created, e.g. `<prefix>-canvas`.
+    pub fn dom_id_prefix(&self) -> &str {
+        &self.dom_id_prefix
+    }
+
+    /// Removes the canvas and its toolbar from the page and releases what they hooked into
+    /// it: DOM listeners, the resize observer, the render loop and event subscribers.
+    /// The canvas should not be used afterwards.
+    pub fn destroy(&self) -> GraphResult<()> {
+        self.dom_bindings.borrow_mut().release();
+        unregister_redraw_target(&self.canvas_id);
+        if let Ok(mut layout_engine) = self.layout_engine.lock() {
+            layout_engine.stop_force_simulation();
+        }
+        self.events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?
+            .clear();
+        Ok(())
+    }
+
+    pub(crate) fn dom_id(&self, name: &str) -> String {
+        format!("{}-{}", self.dom_id_prefix, name)
+    }
+
+    pub fn node_ids(&self) -> GraphResult<Vec<String>> {
+        let graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let mut ids = graph.node_instances.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Sets pan (in screen pixels) and zoom, and remembers them for the current view.
+    pub fn set_view_transform(&self, pan_x: f64, pan_y: f64, zoom: f64) -> GraphResult<()> {
+        let mut ix = self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?;
+        ix.view_transform.pan_x = pan_x;
+        ix.view_transform.pan_y = pan_y;
+        ix.view_transform.zoom = zoom.clamp(0.1, 5.0);
+        if let Ok(mut layout_engine) = self.layout_engine.lock() {
+            layout_engine.save_view_transform(&ix.view_transform);
+        }
+        self.request_redraw();
+        Ok(())
+    }
+
+    /// Returns (pan_x, pan_y, zoom)
+    pub fn view_transform(&self) -> GraphResult<(f64, f64, f64)> {
+        let ix = self
+            .interaction
+            .lock()
+            .map_err(|_| GraphError::LockFailed("interaction".to_string()))?;
+        Ok((
+            ix.view_transform.pan_x,
+            ix.view_transform.pan_y,
+            ix.view_transform.zoom,
+        ))
+    }
+
+    /// Reverts the most recent graph command. Returns false if there was nothing to undo.
+    pub fn undo(&self) -> GraphResult<bool> {
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        let undone = graph.undo(&events);
+        if undone {
+            self.prune_interaction_state(&graph, &events);
+        }
+        Ok(undone)
+    }
+
+    /// Re-applies the most recently undone graph command. Returns false if there was nothing to redo.
+    pub fn redo(&self) -> GraphResult<bool> {
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        let redone = graph.redo(&events);
+        if redone {
+            self.prune_interaction_state(&graph, &events);
+        }
+        Ok(redone)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.graph
+            .lock()
+            .map(|graph| graph.history.can_undo())
+            .unwrap_or(false)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.graph
+            .lock()
+            .map(|graph| graph.history.can_redo())
+            .unwrap_or(false)
+    }
+
+    /// Starts a draft editing session, tracking the slot and template minimums the graph
+    /// does not meet yet.
+    pub fn begin_session(&self) -> GraphResult<()> {
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        graph.begin_session(&events);
+        self.request_redraw();
+        Ok(())
+    }
+
+    /// Marks the draft as finished. Fails with the validation report while the graph does
+    /// not conform to its templates.
+    pub fn commit_session(&self) -> GraphResult<()> {
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        graph.commit_session(&events)?;
+        self.request_redraw();
+        Ok(())
+    }
+
+    /// The current session, or None if `begin_session` has not been called.
+    pub fn session(&self) -> GraphResult<Option<EditingSession>> {
+        Ok(self
+            .graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .session()
+            .cloned())
+    }
+
+    pub fn is_draft(&self) -> bool {
+        self.graph
+            .lock()
+            .map(|graph| graph.is_draft())
+            .unwrap_or(false)
+    }
+
+    /// Mirrors later edits into `adapter`, which is assumed to hold the graph as it is now.
+    /// In `SyncMode::Batched` the canvas flushes on its own timer until the adapter is
+    /// cleared or the canvas destroyed.
+    pub fn set_sync_adapter(
+        &self,
+        adapter: Arc<Mutex<dyn GraphSyncAdapter>>,
+        mode: SyncMode,
+    ) -> GraphResult<()> {
+        self.graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .set_sync_adapter(adapter, mode);
+
+        let mut dom_bindings = self.dom_bindings.borrow_mut();
+        if let SyncMode::Batched { interval_ms } = mode {
+            let graph_canvas = self.clone();
+            let flush = Closure::wrap(Box::new(move || {
+                if let Err(err) = graph_canvas.flush_sync() {
+                    log(&format!("Sync failed: {}", err));
+                }
+            }) as Box<dyn FnMut()>);
+            dom_bindings.set_sync_interval(interval_ms, flush);
+        } else {
+            dom_bindings.clear_sync_interval();
+        }
+        Ok(())
+    }
+
+    pub fn clear_sync_adapter(&self) -> GraphResult<()> {
+        self.graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .clear_sync_adapter();
+        self.dom_bindings.borrow_mut().clear_sync_interval();
+        Ok(())
+    }
+
+    /// Sends everything changed since the last sync. A rejection rolls the graph back to the
+    /// last synced state.
+    pub fn flush_sync(&self) -> GraphResult<()> {
+        let mut graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        let result = graph.flush_sync(&events);
+        if result.is_err() {
+            self.prune_interaction_state(&graph, &events);
+            self.request_redraw();
+        }
+        result
+    }
+
+    /// The remaining work before the graph can be committed. Available with or without a
+    /// session.
+    pub fn pending_constraints(&self) -> GraphResult<Vec<PendingConstraint>> {
+        Ok(self
+            .graph
+            .lock()
+            .map_err(|_| GraphError::GraphLockFailed)?
+            .pending_constraints())
+    }
+
+    fn clear_stale_interaction_state(&self) -> GraphResult<()> {
+        let graph = self.graph.lock().map_err(|_| GraphError::GraphLockFailed)?;
+        let events = self
+            .events
+            .lock()
+            .map_err(|_| GraphError::LockFailed("events".to_string()))?;
+        self.prune_interaction_state(&graph, &events);
+        Ok(())
+    }
+
+    // Undo/redo can remove nodes out from under the pointer, so drop anything that refers to them
+    fn prune_interaction_state(&self, graph: &Graph, events: &events::EventSystem) {
+        if let Ok(mut ix) = self.interaction.lock() {
+            ix.context_menu = None;
+            ix.hovered_connection = None;
+            let selection = ix
+                .selected_nodes
+                .iter()
+                .filter(|id| graph.node_instances.contains_key(*id))
+                .cloned()
+                .collect();
+            ix.set_selection(selection, events);
+            if ix
+                .hovered_node
+                .as_ref()
+                .is_some_and(|id| !graph.node_instances.contains_key(id))
+            {
+                ix.hovered_node = None;
+            }
+            if ix
+                .hovered_slot
+                .as_ref()
+                .is_some_and(|(id, _)| !graph.node_instances.contains_key(id))
+            {
+                ix.hovered_slot = None;
+            }
+        }
+    }
+
+    pub fn apply_layout(&self, layout: LayoutType) -> Result<(), GraphError> {
         match self.layout_engine.lock() {
             Ok(mut engine) => {
                 match self.graph.lock() {
@@ -575,9 +1024,10 @@ impl GraphCanvas {
                     }
                     Err(_) => return Err(GraphError::GraphLockFailed),
                 }
+                self.request_redraw();
                 Ok(())
             }
-            Err(_) => Err(GraphError::GraphLockFailed),
+            Err(_) => Err(GraphError::LockFailed("layout engine".to_string())),
         }
     }
 }
@@ -0,0 +1,217 @@
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::window;
+
+use crate::{
+    errors::log_and_convert_error,
+    interaction::{InteractionState, LongPress},
+    log, GraphCanvas,
+};
+
+// How long a touch has to stay put before it opens the context menu
+const LONG_PRESS_MS: i32 = 500;
+// Screen pixels a finger may wander before a long press is abandoned
+const LONG_PRESS_TOLERANCE: f64 = 8.0;
+
+/// A pointer event reduced to what the canvas needs, in canvas-relative screen pixels
+pub(crate) struct PointerInput {
+    pub pointer_id: i32,
+    pub x: f64,
+    pub y: f64,
+    pub is_touch: bool,
+    pub toggle_selection: bool,
+}
+
+impl PointerInput {
+    pub fn from_event(event: &web_sys::PointerEvent, canvas: &web_sys::HtmlCanvasElement) -> Self {
+        let rect = canvas.get_bounding_client_rect();
+        Self {
+            pointer_id: event.pointer_id(),
+            x: event.client_x() as f64 - rect.left(),
+            y: event.client_y() as f64 - rect.top(),
+            is_touch: event.pointer_type() == "touch",
+            toggle_selection: event.shift_key() || event.ctrl_key() || event.meta_key(),
+        }
+    }
+}
+
+/// Mouse, pen and touch all arrive here. A single pointer drives the usual mouse handlers;
+/// a second pointer turns the gesture into a pinch that pans and zooms the view.
+impl GraphCanvas {
+    pub(crate) fn handle_pointer_down(&self, input: &PointerInput) -> Result<(), JsValue> {
+        {
+            let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
+            ix.pointer_is_touch = input.is_touch;
+            ix.active_pointers
+                .insert(input.pointer_id, (input.x, input.y));
+            match ix.active_pointers.len() {
+                1 => {}
+                2 => {
+                    Self::abandon_single_pointer_gesture(&mut ix);
+                    ix.is_pinching = true;
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            }
+            if input.is_touch {
+                ix.long_press = Some(LongPress {
+                    pointer_id: input.pointer_id,
+                    screen_x: input.x,
+                    screen_y: input.y,
+                    fired: false,
+                });
+                self.schedule_long_press(input.pointer_id)?;
+            }
+        }
+        self.handle_mouse_down(input.x, input.y, input.toggle_selection)
+    }
+
+    pub(crate) fn handle_pointer_move(&self, input: &PointerInput) -> Result<(), JsValue> {
+        let (dx, dy) = {
+            let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
+            ix.pointer_is_touch = input.is_touch;
+            let Some(&(last_x, last_y)) = ix.active_pointers.get(&input.pointer_id) else {
+                // Hovering; nothing is held down
+                drop(ix);
+                return self.handle_mouse_move(input.x, input.y, 0.0, 0.0);
+            };
+
+            if ix.is_pinching {
+                let before = Self::pinch_geometry(&ix);
+                ix.active_pointers
+                    .insert(input.pointer_id, (input.x, input.y));
+                let after = Self::pinch_geometry(&ix);
+                let (Some(before), Some(after)) = (before, after) else {
+                    return Ok(());
+                };
+                // Two fingers moving together pan; moving apart or together zooms
+                ix.view_transform.pan_x += after.0 - before.0;
+                ix.view_transform.pan_y += after.1 - before.1;
+                drop(ix);
+                if before.2 > 0.0 {
+                    self.zoom_by_factor(after.2 / before.2, after.0, after.1)?;
+                }
+                return Ok(());
+            }
+
+            ix.active_pointers
+                .insert(input.pointer_id, (input.x, input.y));
+            let strayed = ix.long_press.as_ref().is_some_and(|press| {
+                !press.fired
+                    && (input.x - press.screen_x).hypot(input.y - press.screen_y)
+                        > LONG_PRESS_TOLERANCE
+            });
+            if strayed {
+                ix.long_press = None;
+            }
+            (input.x - last_x, input.y - last_y)
+        };
+        self.handle_mouse_move(input.x, input.y, dx, dy)
+    }
+
+    pub(crate) fn handle_pointer_up(&self, input: &PointerInput) -> Result<(), JsValue> {
+        {
+            let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
+            ix.active_pointers.remove(&input.pointer_id);
+            let long_press = ix.long_press.take();
+            if ix.is_pinching {
+                // Wait for every finger to lift before accepting new gestures
+                if ix.active_pointers.is_empty() {
+                    ix.is_pinching = false;
+                    if let Ok(mut layout_engine) = self.layout_engine.try_lock() {
+                        layout_engine.save_view_transform(&ix.view_transform);
+                    }
+                }
+                return Ok(());
+            }
+            if long_press.is_some_and(|press| press.fired) {
+                // The press already opened a menu; lifting the finger should not close it
+                return Ok(());
+            }
+        }
+        self.handle_mouse_up(input.x, input.y, input.toggle_selection)
+    }
+
+    pub(crate) fn handle_pointer_cancel(&self, pointer_id: i32) -> Result<(), JsValue> {
+        let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
+        ix.active_pointers.remove(&pointer_id);
+        if ix.active_pointers.is_empty() {
+            Self::abandon_single_pointer_gesture(&mut ix);
+            ix.is_pinching = false;
+        }
+        Ok(())
+    }
+
+    // Drops any drag, connection or box selection in progress
+    fn abandon_single_pointer_gesture(ix: &mut InteractionState) {
+        ix.is_mouse_down = false;
+        ix.is_dragging_node = false;
+        ix.is_panning = false;
+        ix.connection_drag = None;
+        ix.selection_box = None;
+        ix.click_initiated_on_node = None;
+        ix.click_initiated_on_slot = None;
+        ix.long_press = None;
+    }
+
+    // (midpoint x, midpoint y, distance) of the first two pointers down
+    fn pinch_geometry(ix: &InteractionState) -> Option<(f64, f64, f64)> {
+        let mut pointers = ix.active_pointers.iter().collect::<Vec<_>>();
+        pointers.sort_by_key(|(id, _)| **id);
+        let [(_, &(x1, y1)), (_, &(x2, y2))] = pointers.get(..2)? else {
+            return None;
+        };
+        Some(((x1 + x2) / 2.0, (y1 + y2) / 2.0, (x2 - x1).hypot(y2 - y1)))
+    }
+
+    fn schedule_long_press(&self, pointer_id: i32) -> Result<(), JsValue> {
+        let canvas = self.clone();
+        let callback = Closure::once_into_js(move || {
+            if let Err(err) = canvas.fire_long_press(pointer_id) {
+                log(&format!("Long press failed: {:?}", err.as_string()));
+            }
+        });
+        window()
+            .ok_or_else(|| JsValue::from_str("No window"))?
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                callback.unchecked_ref(),
+                LONG_PRESS_MS,
+            )?;
+        Ok(())
+    }
+
+    // Opens the context menu under a touch that has been held still long enough
+    fn fire_long_press(&self, pointer_id: i32) -> Result<(), JsValue> {
+        let mut ix = self.interaction.lock().map_err(log_and_convert_error)?;
+        let graph = self.graph.lock().map_err(log_and_convert_error)?;
+        let events = self.events.lock().map_err(log_and_convert_error)?;
+
+        let Some(press) = ix.long_press.clone() else {
+            return Ok(());
+        };
+        if press.pointer_id != pointer_id || press.fired {
+            return Ok(());
+        }
+
+        let (x, y) = ix
+            .view_transform
+            .screen_to_graph(press.screen_x, press.screen_y);
+        let opened = self.open_node_context_menu_at(x, y, &graph, &mut ix, &events)
+            || self
+                .open_connection_context_menu_at(x, y, &graph, &mut ix, &events)
+                .map_err(log_and_convert_error)?;
+        if opened {
+            ix.is_mouse_down = false;
+            ix.is_panning = false;
+            ix.click_initiated_on_node = None;
+            ix.click_initiated_on_slot = None;
+            ix.long_press = Some(LongPress {
+                fired: true,
+                ..press
+            });
+        } else {
+            // Nothing to open a menu for; let the touch carry on as a drag
+            ix.long_press = None;
+        }
+        Ok(())
+    }
+}
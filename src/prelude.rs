@@ -4,7 +4,9 @@ pub use crate::config::InitialFieldValue;
 pub use crate::config::InitialNode;
 pub use crate::config::TemplateGroup;
 pub use crate::config::TemplateIdentifier;
+pub use crate::document::GraphDocument;
 pub use crate::graph::Connection;
+pub use crate::graph::FieldConstraints;
 pub use crate::graph::FieldTemplate;
 pub use crate::graph::FieldType;
 pub use crate::graph::Graph;
@@ -12,7 +14,11 @@ pub use crate::graph::NodeInstance;
 pub use crate::graph::NodeTemplate;
 pub use crate::graph::SlotInstance;
 pub use crate::graph::SlotPosition;
+pub use crate::import_molecule_schema;
 pub use crate::layout::LayoutType;
+pub use crate::validation::ValidationReport;
+pub use crate::{EditingSession, PendingConstraint, SessionState};
+pub use crate::{GraphSyncAdapter, InMemorySyncAdapter, SyncMode};
 
 pub use crate::graph::SlotTemplate;
 pub use crate::graph::SlotType;
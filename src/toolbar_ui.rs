@@ -1,6 +1,7 @@
 use crate::interaction::InteractionMode;
 use crate::layout::LayoutType;
-use crate::{log, GraphCanvas};
+use crate::validation::{ValidationCode, ValidationReport};
+use crate::{inline_editor::color_input_value, log, Graph, GraphCanvas, SessionState};
 use wasm_bindgen::{prelude::*, JsCast};
 use web_sys::{window, Document, Element, HtmlElement, HtmlInputElement};
 
@@ -12,6 +13,7 @@ pub struct Styles {
     pub toolbar: &'static str,
     pub label: &'static str,
     pub field_editor: &'static str,
+    pub session: &'static str,
     pub template_container: &'static str,
     pub template_button: &'static str,
 }
@@ -25,6 +27,7 @@ impl Styles {
                 toolbar: "display: flex; gap: 12px; padding: 8px; background-color: #f5f5f5; border-bottom: 1px solid #ddd; align-items: center; flex-wrap: wrap;",
                 label: "font-size: 12px; font-weight: bold;",
                 field_editor: "display: none; gap: 8px; align-items: center; margin-left: 10px; padding: 4px 8px; border: 1px solid #eee; border-radius: 4px; background-color: #fff;",
+                session: "display: none; gap: 8px; align-items: center; padding: 4px 8px; border: 1px solid #eee; border-radius: 4px; background-color: #fff;",
                 template_container: "display: none; position: absolute; top: 40px; left: 160px; background: white; border: 1px solid #ccc; border-radius: 4px; box-shadow: 0 2px 10px rgba(0,0,0,0.1); z-index: 100; min-width: 200px; padding: 8px;",
                 template_button: "padding: 2px 10px; border: 1px solid #ddd; border-radius: 4px; text-align: left; background: white; cursor: pointer; margin: 2px 0;",
             }
@@ -99,6 +102,8 @@ pub fn create_label(
 pub struct ToolbarElements {
     pub toolbar: HtmlElement,
     pub pointer_btn: HtmlElement,
+    pub undo_btn: HtmlElement,
+    pub redo_btn: HtmlElement,
     pub add_node_btn: HtmlElement,
     pub cancel_btn: HtmlElement,
     pub template_group_container: HtmlElement,
@@ -108,8 +113,10 @@ pub struct ToolbarElements {
     pub layout_buttons: Vec<(HtmlElement, String)>,
     pub reset_btn: HtmlElement,
     pub physics_checkbox: HtmlInputElement,
+    pub snap_checkbox: HtmlInputElement,
     pub field_editor_section: HtmlElement,
     pub field_editor_container: Element,
+    pub commit_btn: HtmlElement,
 }
 
 // --- ToolbarBuilder for toolbar creation ---
@@ -138,13 +145,14 @@ impl<'a> ToolbarBuilder<'a> {
         let toolbar: HtmlElement = create_element(
             self.document,
             "div",
-            Some("graph-canvas-toolbar"),
+            Some(&self.graph_canvas.dom_id("toolbar")),
             None,
             Some(self.styles.toolbar),
         )?;
 
         // Create all sections with their elements
-        let (interaction_section, pointer_btn) = self.create_interaction_section()?;
+        let (interaction_section, pointer_btn, undo_btn, redo_btn) =
+            self.create_interaction_section()?;
         let (
             add_node_section,
             add_node_btn,
@@ -154,19 +162,29 @@ impl<'a> ToolbarBuilder<'a> {
             template_buttons,
         ) = self.create_add_node_section()?;
         let (field_editor_section, field_editor_container) = self.create_field_editor_section()?;
-        let (layout_section, view_buttons, layout_buttons, reset_btn, physics_checkbox) =
-            self.create_layout_section()?;
+        let (
+            layout_section,
+            view_buttons,
+            layout_buttons,
+            reset_btn,
+            physics_checkbox,
+            snap_checkbox,
+        ) = self.create_layout_section()?;
+        let (session_section, commit_btn) = self.create_session_section()?;
 
         // Add sections to toolbar
         toolbar.append_child(&interaction_section)?;
         toolbar.append_child(&add_node_section)?;
         toolbar.append_child(&field_editor_section)?;
         toolbar.append_child(&layout_section)?;
+        toolbar.append_child(&session_section)?;
 
         // Collect all created elements to return
         Ok(ToolbarElements {
             toolbar,
             pointer_btn,
+            undo_btn,
+            redo_btn,
             add_node_btn,
             cancel_btn,
             template_group_container,
@@ -176,12 +194,16 @@ impl<'a> ToolbarBuilder<'a> {
             layout_buttons,
             reset_btn,
             physics_checkbox,
+            snap_checkbox,
             field_editor_section,
             field_editor_container,
+            commit_btn,
         })
     }
 
-    fn create_interaction_section(&self) -> Result<(HtmlElement, HtmlElement), JsValue> {
+    fn create_interaction_section(
+        &self,
+    ) -> Result<(HtmlElement, HtmlElement, HtmlElement, HtmlElement), JsValue> {
         let section = create_section(self.document, None, Some(self.styles.section), None)?;
 
         // Add label
@@ -192,14 +214,32 @@ impl<'a> ToolbarBuilder<'a> {
         let pointer_btn = create_button(
             self.document,
             "🖱 Pointer",
-            Some("btn-pointer"),
+            Some(&self.graph_canvas.dom_id("btn-pointer")),
             Some("toolbar-btn active"),
             Some(self.styles.button_active),
         )?;
         section.append_child(&pointer_btn)?;
 
+        // Undo/redo buttons
+        let undo_btn = create_button(
+            self.document,
+            "↶ Undo",
+            Some(&self.graph_canvas.dom_id("btn-undo")),
+            Some("toolbar-btn"),
+            Some(self.styles.button),
+        )?;
+        section.append_child(&undo_btn)?;
+        let redo_btn = create_button(
+            self.document,
+            "↷ Redo",
+            Some(&self.graph_canvas.dom_id("btn-redo")),
+            Some("toolbar-btn"),
+            Some(self.styles.button),
+        )?;
+        section.append_child(&redo_btn)?;
+
         // Return both the section and important elements
-        Ok((section, pointer_btn))
+        Ok((section, pointer_btn, undo_btn, redo_btn))
     }
 
     fn create_add_node_section(
@@ -221,7 +261,7 @@ impl<'a> ToolbarBuilder<'a> {
         let add_node_btn = create_button(
             self.document,
             "➕ Add Node",
-            Some("btn-add-node"),
+            Some(&self.graph_canvas.dom_id("btn-add-node")),
             Some("toolbar-btn"),
             Some(self.styles.button),
         )?;
@@ -231,7 +271,7 @@ impl<'a> ToolbarBuilder<'a> {
         let cancel_btn = create_button(
             self.document,
             "Cancel",
-            Some("btn-cancel"),
+            Some(&self.graph_canvas.dom_id("btn-cancel")),
             None,
             Some(&format!("{}; display: none", self.styles.button)),
         )?;
@@ -259,7 +299,7 @@ impl<'a> ToolbarBuilder<'a> {
         let container: HtmlElement = create_element(
             self.document,
             "div",
-            Some("template-group-container"),
+            Some(&self.graph_canvas.dom_id("template-group-container")),
             None,
             Some(self.styles.template_container),
         )?;
@@ -370,21 +410,21 @@ impl<'a> ToolbarBuilder<'a> {
         let section: HtmlElement = create_element(
             self.document,
             "div",
-            Some("field-editor-section"),
+            Some(&self.graph_canvas.dom_id("field-editor-section")),
             None,
             Some(self.styles.field_editor),
         )?;
 
         // Field editor title
         let title = create_label(self.document, "Node Fields", Some(self.styles.label))?;
-        title.set_attribute("id", "field-editor-title")?;
+        title.set_attribute("id", &self.graph_canvas.dom_id("field-editor-title"))?;
         section.append_child(&title)?;
 
         // Field editor container
         let container: Element = create_element(
             self.document,
             "div",
-            Some("field-editor-container"),
+            Some(&self.graph_canvas.dom_id("field-editor-container")),
             None,
             Some("display: flex; flex-direction: column; gap: 6px;"),
         )?;
@@ -393,6 +433,42 @@ impl<'a> ToolbarBuilder<'a> {
         Ok((section, container))
     }
 
+    // Hidden until the host starts an editing session; filled in by refresh_session_checklist
+    fn create_session_section(&self) -> Result<(HtmlElement, HtmlElement), JsValue> {
+        let section: HtmlElement = create_element(
+            self.document,
+            "div",
+            Some(&self.graph_canvas.dom_id("session-section")),
+            None,
+            Some(self.styles.session),
+        )?;
+
+        let status = create_label(self.document, "Draft", Some(self.styles.label))?;
+        status.set_attribute("id", &self.graph_canvas.dom_id("session-status"))?;
+        section.append_child(&status)?;
+
+        // Remaining work checklist
+        let checklist: Element = create_element(
+            self.document,
+            "ul",
+            Some(&self.graph_canvas.dom_id("session-checklist")),
+            None,
+            Some("margin: 0; padding: 0; list-style: none; font-size: 12px;"),
+        )?;
+        section.append_child(&checklist)?;
+
+        let commit_btn = create_button(
+            self.document,
+            "✓ Commit",
+            Some(&self.graph_canvas.dom_id("btn-commit")),
+            Some("toolbar-btn"),
+            Some(self.styles.button),
+        )?;
+        section.append_child(&commit_btn)?;
+
+        Ok((section, commit_btn))
+    }
+
     fn create_layout_section(
         &self,
     ) -> Result<
@@ -402,6 +478,7 @@ impl<'a> ToolbarBuilder<'a> {
             Vec<(HtmlElement, String)>,
             HtmlElement,
             HtmlInputElement,
+            HtmlInputElement,
         ),
         JsValue,
     > {
@@ -508,13 +585,46 @@ impl<'a> ToolbarBuilder<'a> {
         )?;
         physics_toggle.append_child(&physics_label)?;
 
-        let physics_checkbox: HtmlInputElement =
-            create_element(self.document, "input", Some("physics-toggle"), None, None)?;
+        let physics_checkbox: HtmlInputElement = create_element(
+            self.document,
+            "input",
+            Some(&self.graph_canvas.dom_id("physics-toggle")),
+            None,
+            None,
+        )?;
         physics_checkbox.set_attribute("type", "checkbox")?;
         physics_checkbox.set_attribute("checked", "")?;
         physics_toggle.append_child(&physics_checkbox)?;
         section.append_child(&physics_toggle)?;
 
+        // Snap to grid toggle
+        let snap_toggle: HtmlElement = create_element(
+            self.document,
+            "div",
+            None,
+            None,
+            Some("display: flex; align-items: center; margin-left: 10px;"),
+        )?;
+
+        let snap_label = create_label(
+            self.document,
+            "Snap to grid:",
+            Some("font-size: 12px; margin-right: 4px;"),
+        )?;
+        snap_toggle.append_child(&snap_label)?;
+
+        let snap_checkbox: HtmlInputElement = create_element(
+            self.document,
+            "input",
+            Some(&self.graph_canvas.dom_id("snap-toggle")),
+            None,
+            None,
+        )?;
+        snap_checkbox.set_attribute("type", "checkbox")?;
+        snap_checkbox.set_checked(self.graph_canvas.snap_to_grid().unwrap_or(false));
+        snap_toggle.append_child(&snap_checkbox)?;
+        section.append_child(&snap_toggle)?;
+
         // Reset layout button
         let reset_btn = create_button(
             self.document,
@@ -531,6 +641,7 @@ impl<'a> ToolbarBuilder<'a> {
             layout_buttons,
             reset_btn,
             physics_checkbox,
+            snap_checkbox,
         ))
     }
 }
@@ -568,11 +679,13 @@ impl<'a> ToolbarEventHandler<'a> {
 
     pub fn attach_all_handlers(&self) -> Result<(), JsValue> {
         self.attach_mode_handlers()?;
+        self.attach_history_handlers()?;
         self.attach_add_node_handlers()?;
         self.attach_template_handlers()?;
         self.attach_view_handlers()?;
         self.attach_layout_handlers()?;
         self.attach_node_selection_handler()?;
+        self.attach_session_handlers()?;
 
         Ok(())
     }
@@ -608,9 +721,55 @@ impl<'a> ToolbarEventHandler<'a> {
                 .unwrap();
         }) as Box<dyn FnMut(_)>);
 
-        pointer_btn
-            .add_event_listener_with_callback("click", pointer_click.as_ref().unchecked_ref())?;
-        pointer_click.forget();
+        self.graph_canvas
+            .dom_bindings
+            .borrow_mut()
+            .listen(pointer_btn, "click", pointer_click)?;
+
+        Ok(())
+    }
+
+    fn attach_history_handlers(&self) -> Result<(), JsValue> {
+        let graph_canvas_clone = self.graph_canvas.clone();
+        let undo_click = Closure::wrap(Box::new(move |_: web_sys::MouseEvent| {
+            if let Err(err) = graph_canvas_clone.undo() {
+                log(&format!("Undo failed: {}", err));
+            }
+        }) as Box<dyn FnMut(_)>);
+        self.graph_canvas.dom_bindings.borrow_mut().listen(
+            &self.elements.undo_btn,
+            "click",
+            undo_click,
+        )?;
+
+        let graph_canvas_clone = self.graph_canvas.clone();
+        let redo_click = Closure::wrap(Box::new(move |_: web_sys::MouseEvent| {
+            if let Err(err) = graph_canvas_clone.redo() {
+                log(&format!("Redo failed: {}", err));
+            }
+        }) as Box<dyn FnMut(_)>);
+        self.graph_canvas.dom_bindings.borrow_mut().listen(
+            &self.elements.redo_btn,
+            "click",
+            redo_click,
+        )?;
+
+        Ok(())
+    }
+
+    fn attach_session_handlers(&self) -> Result<(), JsValue> {
+        let graph_canvas_clone = self.graph_canvas.clone();
+        let commit_click = Closure::wrap(Box::new(move |_: web_sys::MouseEvent| {
+            // The checklist already shows what is missing
+            if let Err(err) = graph_canvas_clone.commit_session() {
+                log(&format!("Commit failed: {}", err));
+            }
+        }) as Box<dyn FnMut(_)>);
+        self.graph_canvas.dom_bindings.borrow_mut().listen(
+            &self.elements.commit_btn,
+            "click",
+            commit_click,
+        )?;
 
         Ok(())
     }
@@ -647,9 +806,11 @@ impl<'a> ToolbarEventHandler<'a> {
                 .unwrap();
         }) as Box<dyn FnMut(_)>);
 
-        add_node_btn
-            .add_event_listener_with_callback("click", add_node_click.as_ref().unchecked_ref())?;
-        add_node_click.forget();
+        self.graph_canvas.dom_bindings.borrow_mut().listen(
+            add_node_btn,
+            "click",
+            add_node_click,
+        )?;
 
         // Cancel button click handler
         let graph_canvas_clone = self.graph_canvas.clone();
@@ -677,9 +838,10 @@ impl<'a> ToolbarEventHandler<'a> {
                 .unwrap();
         }) as Box<dyn FnMut(_)>);
 
-        cancel_btn
-            .add_event_listener_with_callback("click", cancel_click.as_ref().unchecked_ref())?;
-        cancel_click.forget();
+        self.graph_canvas
+            .dom_bindings
+            .borrow_mut()
+            .listen(cancel_btn, "click", cancel_click)?;
 
         Ok(())
     }
@@ -734,9 +896,10 @@ impl<'a> ToolbarEventHandler<'a> {
                 }
             }) as Box<dyn FnMut(_)>);
 
-            tab_button
-                .add_event_listener_with_callback("click", tab_click.as_ref().unchecked_ref())?;
-            tab_click.forget();
+            self.graph_canvas
+                .dom_bindings
+                .borrow_mut()
+                .listen(tab_button, "click", tab_click)?;
         }
 
         // Handle template buttons
@@ -774,11 +937,11 @@ impl<'a> ToolbarEventHandler<'a> {
                 graph_canvas_clone.set_current_node_template(&template_id);
             }) as Box<dyn FnMut(_)>);
 
-            template_button.add_event_listener_with_callback(
+            self.graph_canvas.dom_bindings.borrow_mut().listen(
+                template_button,
                 "click",
-                template_click.as_ref().unchecked_ref(),
+                template_click,
             )?;
-            template_click.forget();
         }
 
         Ok(())
@@ -810,18 +973,19 @@ impl<'a> ToolbarEventHandler<'a> {
                 let mut graph = graph_canvas_clone.graph.lock().unwrap();
                 let mut ix = graph_canvas_clone.interaction.lock().unwrap();
 
-                layout_engine.switch_to_view(view_index, &mut graph, &mut ix);
+                layout_engine.switch_to_view(view_index, &mut graph, &mut ix.view_transform);
+                graph_canvas_clone.request_redraw();
 
                 // Update physics checkbox
                 let physics_enabled = layout_engine.is_physics_enabled();
                 physics_checkbox_clone.set_checked(physics_enabled);
             }) as Box<dyn FnMut(_)>);
 
-            view_btn.add_event_listener_with_callback(
+            self.graph_canvas.dom_bindings.borrow_mut().listen(
+                view_btn,
                 "click",
-                on_view_change.as_ref().unchecked_ref(),
+                on_view_change,
             )?;
-            on_view_change.forget();
         }
 
         // Physics toggle handler
@@ -845,11 +1009,31 @@ impl<'a> ToolbarEventHandler<'a> {
             }
         }) as Box<dyn FnMut(_)>);
 
-        physics_checkbox.add_event_listener_with_callback(
+        self.graph_canvas.dom_bindings.borrow_mut().listen(
+            physics_checkbox,
             "change",
-            on_physics_toggle.as_ref().unchecked_ref(),
+            on_physics_toggle,
+        )?;
+
+        // Snap to grid toggle handler
+        let graph_canvas_clone = self.graph_canvas.clone();
+        let on_snap_toggle = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            let checkbox = event
+                .target()
+                .unwrap()
+                .dyn_into::<HtmlInputElement>()
+                .unwrap();
+            if let Err(err) = graph_canvas_clone.set_snap_to_grid(checkbox.checked()) {
+                checkbox.set_checked(!checkbox.checked());
+                log(&format!("Could not toggle snapping: {}", err));
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        self.graph_canvas.dom_bindings.borrow_mut().listen(
+            &self.elements.snap_checkbox,
+            "change",
+            on_snap_toggle,
         )?;
-        on_physics_toggle.forget();
 
         Ok(())
     }
@@ -881,13 +1065,13 @@ impl<'a> ToolbarEventHandler<'a> {
                 let mut layout_engine = graph_canvas_clone.layout_engine.lock().unwrap();
                 let mut graph = graph_canvas_clone.graph.lock().unwrap();
                 layout_engine.switch_layout(layout_type.clone(), &mut graph);
+                graph_canvas_clone.request_redraw();
             }) as Box<dyn FnMut(_)>);
 
-            btn.add_event_listener_with_callback(
-                "click",
-                on_layout_change.as_ref().unchecked_ref(),
-            )?;
-            on_layout_change.forget();
+            self.graph_canvas
+                .dom_bindings
+                .borrow_mut()
+                .listen(btn, "click", on_layout_change)?;
         }
 
         // Reset button handler
@@ -898,11 +1082,14 @@ impl<'a> ToolbarEventHandler<'a> {
             let mut layout_engine = graph_canvas_clone.layout_engine.lock().unwrap();
             let mut graph = graph_canvas_clone.graph.lock().unwrap();
             let mut ix = graph_canvas_clone.interaction.lock().unwrap();
-            layout_engine.reset_current_layout(&mut graph, &mut ix);
+            layout_engine.reset_current_layout(&mut graph, &mut ix.view_transform);
+            graph_canvas_clone.request_redraw();
         }) as Box<dyn FnMut(_)>);
 
-        reset_btn.add_event_listener_with_callback("click", on_reset.as_ref().unchecked_ref())?;
-        on_reset.forget();
+        self.graph_canvas
+            .dom_bindings
+            .borrow_mut()
+            .listen(reset_btn, "click", on_reset)?;
 
         Ok(())
     }
@@ -936,11 +1123,15 @@ impl<'a> ToolbarEventHandler<'a> {
 
                             // Set title
                             let title_elem = document_clone
-                                .get_element_by_id("field-editor-title")
+                                .get_element_by_id(&graph_canvas_clone.dom_id("field-editor-title"))
                                 .unwrap();
                             title_elem.set_inner_html(&format!("{} Fields:", node_template.name));
 
                             // Clear existing fields
+                            graph_canvas_clone
+                                .dom_bindings
+                                .borrow_mut()
+                                .clear_field_editor();
                             field_editor_container_clone.set_inner_html("");
 
                             // Create field editor UI
@@ -999,6 +1190,47 @@ impl<'a> ToolbarEventHandler<'a> {
                                                 &graph_canvas_clone,
                                             );
                                         }
+                                        crate::FieldType::Float { .. } => {
+                                            Self::create_float_field(
+                                                &document_clone,
+                                                &field_container,
+                                                field_template,
+                                                field_instance,
+                                                selected_node_id,
+                                                &graph_canvas_clone,
+                                            );
+                                        }
+                                        crate::FieldType::Enum { .. } => {
+                                            Self::create_enum_field(
+                                                &document_clone,
+                                                &field_container,
+                                                field_template,
+                                                field_instance,
+                                                selected_node_id,
+                                                &graph_canvas_clone,
+                                            );
+                                        }
+                                        crate::FieldType::Color | crate::FieldType::Date => {
+                                            Self::create_picker_field(
+                                                &document_clone,
+                                                &field_container,
+                                                field_template,
+                                                field_instance,
+                                                selected_node_id,
+                                                &graph_canvas_clone,
+                                            );
+                                        }
+                                        crate::FieldType::MultilineText
+                                        | crate::FieldType::Json => {
+                                            Self::create_multiline_field(
+                                                &document_clone,
+                                                &field_container,
+                                                field_template,
+                                                field_instance,
+                                                selected_node_id,
+                                                &graph_canvas_clone,
+                                            );
+                                        }
                                     }
 
                                     field_editor_container_clone
@@ -1030,11 +1262,11 @@ impl<'a> ToolbarEventHandler<'a> {
             }
         }) as Box<dyn FnMut(_)>);
 
-        self.canvas.add_event_listener_with_callback(
+        self.graph_canvas.dom_bindings.borrow_mut().listen(
+            &self.canvas,
             "mouseup",
-            node_selection_handler.as_ref().unchecked_ref(),
+            node_selection_handler,
         )?;
-        node_selection_handler.forget();
 
         Ok(())
     }
@@ -1094,10 +1326,11 @@ impl<'a> ToolbarEventHandler<'a> {
                 .unwrap_or_else(|_| log("Failed to update boolean field"));
         }) as Box<dyn FnMut(_)>);
 
-        checkbox
-            .add_event_listener_with_callback("change", change_callback.as_ref().unchecked_ref())
+        graph_canvas
+            .dom_bindings
+            .borrow_mut()
+            .listen_field_editor(&checkbox, "change", change_callback)
             .unwrap();
-        change_callback.forget();
 
         container.append_child(&checkbox).unwrap();
     }
@@ -1149,10 +1382,11 @@ impl<'a> ToolbarEventHandler<'a> {
                 .unwrap_or_else(|_| log("Failed to update integer field"));
         }) as Box<dyn FnMut(_)>);
 
-        number_input
-            .add_event_listener_with_callback("change", change_callback.as_ref().unchecked_ref())
+        graph_canvas
+            .dom_bindings
+            .borrow_mut()
+            .listen_field_editor(&number_input, "change", change_callback)
             .unwrap();
-        change_callback.forget();
 
         container.append_child(&number_input).unwrap();
     }
@@ -1204,11 +1438,247 @@ impl<'a> ToolbarEventHandler<'a> {
                 .unwrap_or_else(|_| log("Failed to update string field"));
         }) as Box<dyn FnMut(_)>);
 
-        text_input
-            .add_event_listener_with_callback("change", change_callback.as_ref().unchecked_ref())
+        graph_canvas
+            .dom_bindings
+            .borrow_mut()
+            .listen_field_editor(&text_input, "change", change_callback)
             .unwrap();
-        change_callback.forget();
 
         container.append_child(&text_input).unwrap();
     }
+    fn create_float_field(
+        document: &Document,
+        container: &Element,
+        field_template: &crate::graph::FieldTemplate,
+        field_instance: &crate::graph::FieldInstance,
+        node_id: &str,
+        graph_canvas: &GraphCanvas,
+    ) {
+        let number_input = document.create_element("input").unwrap();
+        number_input.set_attribute("type", "number").unwrap();
+        if let crate::FieldType::Float { min, max, step } = &field_template.field_type {
+            if let Some(min) = min {
+                number_input.set_attribute("min", &min.to_string()).unwrap();
+            }
+            if let Some(max) = max {
+                number_input.set_attribute("max", &max.to_string()).unwrap();
+            }
+            // Without "any" the browser only accepts whole numbers
+            let step = step.map_or("any".to_string(), |step| step.to_string());
+            number_input.set_attribute("step", &step).unwrap();
+        }
+        number_input
+            .set_attribute("value", &field_instance.value)
+            .unwrap();
+        number_input.set_attribute("style", "width: 80px;").unwrap();
+
+        Self::attach_field_change_handler(&number_input, field_instance, node_id, graph_canvas);
+        container.append_child(&number_input).unwrap();
+    }
+
+    fn create_enum_field(
+        document: &Document,
+        container: &Element,
+        field_template: &crate::graph::FieldTemplate,
+        field_instance: &crate::graph::FieldInstance,
+        node_id: &str,
+        graph_canvas: &GraphCanvas,
+    ) {
+        let select = document.create_element("select").unwrap();
+        select.set_attribute("style", "width: 120px;").unwrap();
+        if let crate::FieldType::Enum { options } = &field_template.field_type {
+            for option in options {
+                let option_element = document.create_element("option").unwrap();
+                option_element.set_attribute("value", option).unwrap();
+                option_element.set_text_content(Some(option));
+                if *option == field_instance.value {
+                    option_element.set_attribute("selected", "").unwrap();
+                }
+                select.append_child(&option_element).unwrap();
+            }
+        }
+
+        Self::attach_field_change_handler(&select, field_instance, node_id, graph_canvas);
+        container.append_child(&select).unwrap();
+    }
+
+    // Color and date fields use the browser's own pickers
+    fn create_picker_field(
+        document: &Document,
+        container: &Element,
+        field_template: &crate::graph::FieldTemplate,
+        field_instance: &crate::graph::FieldInstance,
+        node_id: &str,
+        graph_canvas: &GraphCanvas,
+    ) {
+        let input = document.create_element("input").unwrap();
+        let value = match field_template.field_type {
+            crate::FieldType::Color => {
+                input.set_attribute("type", "color").unwrap();
+                color_input_value(&field_instance.value)
+            }
+            _ => {
+                input.set_attribute("type", "date").unwrap();
+                field_instance.value.clone()
+            }
+        };
+        input.set_attribute("value", &value).unwrap();
+
+        Self::attach_field_change_handler(&input, field_instance, node_id, graph_canvas);
+        container.append_child(&input).unwrap();
+    }
+
+    fn create_multiline_field(
+        document: &Document,
+        container: &Element,
+        field_template: &crate::graph::FieldTemplate,
+        field_instance: &crate::graph::FieldInstance,
+        node_id: &str,
+        graph_canvas: &GraphCanvas,
+    ) {
+        let text_area = document.create_element("textarea").unwrap();
+        text_area.set_attribute("rows", "3").unwrap();
+        text_area.set_text_content(Some(&field_instance.value));
+        let style = if field_template.field_type == crate::FieldType::Json {
+            "width: 160px; font-family: monospace; font-size: 11px;"
+        } else {
+            "width: 160px;"
+        };
+        text_area.set_attribute("style", style).unwrap();
+
+        Self::attach_field_change_handler(&text_area, field_instance, node_id, graph_canvas);
+        container.append_child(&text_area).unwrap();
+    }
+
+    // Writes the element's value to the field when it changes. Values the field rejects leave
+    // the element outlined in red, with the reason as its tooltip.
+    fn attach_field_change_handler(
+        element: &Element,
+        field_instance: &crate::graph::FieldInstance,
+        node_id: &str,
+        graph_canvas: &GraphCanvas,
+    ) {
+        element
+            .set_attribute("data-field-id", &field_instance.field_template_id)
+            .unwrap();
+        element.set_attribute("data-node-id", node_id).unwrap();
+
+        let graph_canvas_clone = graph_canvas.clone();
+        let field_id = field_instance.field_template_id.clone();
+        let node_id = node_id.to_string();
+
+        let change_callback = Closure::wrap(Box::new(move |event: web_sys::Event| {
+            let element = event.target().unwrap().dyn_into::<HtmlElement>().unwrap();
+            // Inputs, selects and text areas all expose `value`
+            let value = js_sys::Reflect::get(&element, &JsValue::from_str("value"))
+                .ok()
+                .and_then(|value| value.as_string())
+                .unwrap_or_default();
+
+            let mut graph = graph_canvas_clone.graph.lock().unwrap();
+            let events = graph_canvas_clone.events.lock().unwrap();
+
+            let result = graph.execute_command(
+                crate::graph::GraphCommand::UpdateField {
+                    node_id: node_id.clone(),
+                    field_template_id: field_id.clone(),
+                    new_value: value,
+                },
+                &events,
+            );
+            match result {
+                Ok(()) => {
+                    element.style().remove_property("outline").unwrap();
+                    element.remove_attribute("title").unwrap();
+                }
+                Err(err) => {
+                    element
+                        .style()
+                        .set_property("outline", "1px solid #d32f2f")
+                        .unwrap();
+                    element.set_attribute("title", &err.to_string()).unwrap();
+                    log(&format!("Failed to update field: {}", err));
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        graph_canvas
+            .dom_bindings
+            .borrow_mut()
+            .listen_field_editor(element, "change", change_callback)
+            .unwrap();
+    }
+}
+
+/// While the host has an editing session open, the toolbar lists what is still missing
+/// before the graph can be committed.
+impl GraphCanvas {
+    // Called from the render loop; the list is only rebuilt when its contents change
+    pub(crate) fn refresh_session_checklist(&self, graph: &Graph, report: &ValidationReport) {
+        let Some(document) = window().and_then(|window| window.document()) else {
+            return;
+        };
+        let find = |name: &str| {
+            document
+                .get_element_by_id(&self.dom_id(name))
+                .and_then(|element| element.dyn_into::<HtmlElement>().ok())
+        };
+        let (Some(section), Some(status), Some(checklist), Some(commit_btn)) = (
+            find("session-section"),
+            find("session-status"),
+            find("session-checklist"),
+            find("btn-commit"),
+        ) else {
+            return;
+        };
+
+        let Some(session) = graph.session() else {
+            let _ = section.style().set_property("display", "none");
+            return;
+        };
+        let _ = section.style().set_property("display", "flex");
+
+        let mut items = session
+            .pending
+            .iter()
+            .map(|pending| pending.message.clone())
+            .collect::<Vec<_>>();
+        // Errors that more nodes or connections will not fix also block a commit
+        let other_errors = report
+            .errors()
+            .filter(|issue| {
+                !matches!(
+                    issue.code,
+                    ValidationCode::SlotBelowMinimum | ValidationCode::TooFewInstances
+                )
+            })
+            .count();
+        if session.is_draft() && other_errors > 0 {
+            items.push(format!("Fix {} other validation error(s)", other_errors));
+        }
+        let status_text = match session.state {
+            SessionState::Committed => "Committed".to_string(),
+            SessionState::Draft if items.is_empty() => "Draft: ready to commit".to_string(),
+            SessionState::Draft => format!("Draft: {} to do", items.len()),
+        };
+
+        let rendered = format!("{}\n{}", status_text, items.join("\n"));
+        if checklist.get_attribute("data-rendered").as_deref() == Some(rendered.as_str()) {
+            return;
+        }
+        let _ = checklist.set_attribute("data-rendered", &rendered);
+        status.set_text_content(Some(&status_text));
+        checklist.set_inner_html("");
+        for item in &items {
+            if let Ok(li) = document.create_element("li") {
+                li.set_text_content(Some(&format!("☐ {}", item)));
+                let _ = checklist.append_child(&li);
+            }
+        }
+        if session.is_draft() && items.is_empty() {
+            let _ = commit_btn.remove_attribute("disabled");
+        } else {
+            let _ = commit_btn.set_attribute("disabled", "");
+        }
+    }
 }